@@ -0,0 +1,225 @@
+//! ABI encoding and strict decoding.
+
+use ibank_wallet_core::{Result, WalletError};
+
+use crate::abi::types::{fits_signed, ParamType, Token};
+use crate::address::Address;
+use crate::quantity::U256;
+
+/// Encodes tokens as the components of a tuple (function arguments,
+/// return values, event data).
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_tuple(tokens, &mut out);
+    out
+}
+
+/// Encodes `tokens` after checking them against `types`.
+pub fn encode_checked(types: &[ParamType], tokens: &[Token]) -> Result<Vec<u8>> {
+    if types.len() != tokens.len() {
+        return Err(WalletError::InvalidInput(format!(
+            "expected {} abi values, got {}",
+            types.len(),
+            tokens.len()
+        )));
+    }
+    for (index, (kind, token)) in types.iter().zip(tokens).enumerate() {
+        if !token.type_check(kind) {
+            return Err(WalletError::InvalidInput(format!(
+                "abi value {index} is not a valid {kind}"
+            )));
+        }
+    }
+    Ok(encode(tokens))
+}
+
+/// Decodes `data` as a tuple of `types`.
+///
+/// Decoding is strict: offsets and lengths must stay in bounds and padding
+/// bits of addresses, booleans, sized integers and `bytesN` must be clean.
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>> {
+    decode_tuple(types, data, 0)
+}
+
+fn encode_tuple(tokens: &[Token], out: &mut Vec<u8>) {
+    let head_size: usize = tokens.iter().map(head_size).sum();
+    let mut tail = Vec::new();
+    for token in tokens {
+        if is_dynamic(token) {
+            out.extend_from_slice(&word(U256::from(head_size + tail.len())));
+            encode_token(token, &mut tail);
+        } else {
+            encode_token(token, out);
+        }
+    }
+    out.extend_from_slice(&tail);
+}
+
+fn encode_token(token: &Token, out: &mut Vec<u8>) {
+    match token {
+        Token::Address(address) => {
+            out.extend_from_slice(&[0u8; 12]);
+            out.extend_from_slice(address.as_slice());
+        }
+        Token::Bool(value) => out.extend_from_slice(&word(U256::from(u8::from(*value)))),
+        Token::Uint(value) | Token::Int(value) => out.extend_from_slice(&word(*value)),
+        Token::FixedBytes(bytes) => pad_right(bytes, out),
+        Token::Bytes(bytes) => {
+            out.extend_from_slice(&word(U256::from(bytes.len())));
+            pad_right(bytes, out);
+        }
+        Token::String(value) => {
+            out.extend_from_slice(&word(U256::from(value.len())));
+            pad_right(value.as_bytes(), out);
+        }
+        Token::Array(items) => {
+            out.extend_from_slice(&word(U256::from(items.len())));
+            encode_tuple(items, out);
+        }
+        Token::FixedArray(items) | Token::Tuple(items) => encode_tuple(items, out),
+    }
+}
+
+fn is_dynamic(token: &Token) -> bool {
+    match token {
+        Token::Bytes(_) | Token::String(_) | Token::Array(_) => true,
+        Token::FixedArray(items) | Token::Tuple(items) => items.iter().any(is_dynamic),
+        _ => false,
+    }
+}
+
+fn head_size(token: &Token) -> usize {
+    match token {
+        _ if is_dynamic(token) => 32,
+        Token::FixedArray(items) | Token::Tuple(items) => items.iter().map(head_size).sum(),
+        _ => 32,
+    }
+}
+
+fn word(value: U256) -> [u8; 32] {
+    value.to_big_endian()
+}
+
+fn pad_right(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(bytes);
+    let padding = (32 - bytes.len() % 32) % 32;
+    out.resize(out.len() + padding, 0);
+}
+
+fn decode_tuple(types: &[ParamType], data: &[u8], base: usize) -> Result<Vec<Token>> {
+    let mut tokens = Vec::with_capacity(types.len());
+    let mut head = base;
+    for kind in types {
+        if kind.is_dynamic() {
+            let offset = read_usize(data, head)?;
+            let start = base
+                .checked_add(offset)
+                .filter(|start| *start <= data.len())
+                .ok_or_else(|| decode_error("offset out of bounds"))?;
+            tokens.push(decode_token(kind, data, start)?);
+        } else {
+            tokens.push(decode_token(kind, data, head)?);
+        }
+        head = kind
+            .head_size()
+            .and_then(|size| head.checked_add(size))
+            .ok_or_else(|| decode_error("type too large"))?;
+    }
+    Ok(tokens)
+}
+
+fn decode_token(kind: &ParamType, data: &[u8], at: usize) -> Result<Token> {
+    match kind {
+        ParamType::Address => {
+            let value = read_word(data, at)?;
+            if value[..12].iter().any(|b| *b != 0) {
+                return Err(decode_error("dirty address padding"));
+            }
+            Ok(Token::Address(Address::from_slice(&value[12..])?))
+        }
+        ParamType::Bool => match U256::from_big_endian(read_word(data, at)?) {
+            value if value.is_zero() => Ok(Token::Bool(false)),
+            value if value == U256::one() => Ok(Token::Bool(true)),
+            _ => Err(decode_error("invalid bool")),
+        },
+        ParamType::Uint(bits) => {
+            let value = U256::from_big_endian(read_word(data, at)?);
+            if value.bits() > *bits {
+                return Err(decode_error("uint out of range"));
+            }
+            Ok(Token::Uint(value))
+        }
+        ParamType::Int(bits) => {
+            let value = U256::from_big_endian(read_word(data, at)?);
+            if !fits_signed(&value, *bits) {
+                return Err(decode_error("int out of range"));
+            }
+            Ok(Token::Int(value))
+        }
+        ParamType::FixedBytes(size) => {
+            let value = read_word(data, at)?;
+            if value[*size..].iter().any(|b| *b != 0) {
+                return Err(decode_error("dirty bytes padding"));
+            }
+            Ok(Token::FixedBytes(value[..*size].to_vec()))
+        }
+        ParamType::Bytes => read_bytes(data, at).map(|bytes| Token::Bytes(bytes.to_vec())),
+        ParamType::String => {
+            let bytes = read_bytes(data, at)?;
+            String::from_utf8(bytes.to_vec())
+                .map(Token::String)
+                .map_err(|_| decode_error("string is not utf-8"))
+        }
+        ParamType::Array(inner) => {
+            let len = read_usize(data, at)?;
+            let start = at + 32;
+            check_array_len(inner, len, data.len().saturating_sub(start))?;
+            let types = vec![(**inner).clone(); len];
+            decode_tuple(&types, data, start).map(Token::Array)
+        }
+        ParamType::FixedArray(inner, size) => {
+            check_array_len(inner, *size, data.len().saturating_sub(at))?;
+            let types = vec![(**inner).clone(); *size];
+            decode_tuple(&types, data, at).map(Token::FixedArray)
+        }
+        ParamType::Tuple(components) => decode_tuple(components, data, at).map(Token::Tuple),
+    }
+}
+
+/// Checks that the heads of `len` elements fit in the `remaining` data before
+/// they are allocated; zero-sized elements are rejected so the data bounds
+/// the length.
+fn check_array_len(inner: &ParamType, len: usize, remaining: usize) -> Result<()> {
+    let size = inner
+        .head_size()
+        .and_then(|head| head.checked_mul(len))
+        .ok_or_else(|| decode_error("array too large"))?;
+    if size > remaining || (size == 0 && len > 0) {
+        return Err(decode_error("array length out of bounds"));
+    }
+    Ok(())
+}
+
+fn read_word(data: &[u8], at: usize) -> Result<&[u8]> {
+    at.checked_add(32)
+        .and_then(|end| data.get(at..end))
+        .ok_or_else(|| decode_error("data too short"))
+}
+
+fn read_usize(data: &[u8], at: usize) -> Result<usize> {
+    let value = U256::from_big_endian(read_word(data, at)?);
+    if value.bits() > 32 {
+        return Err(decode_error("offset or length too large"));
+    }
+    Ok(value.as_usize())
+}
+
+fn read_bytes(data: &[u8], at: usize) -> Result<&[u8]> {
+    let len = read_usize(data, at)?;
+    data.get(at + 32..at + 32 + len)
+        .ok_or_else(|| decode_error("bytes length out of bounds"))
+}
+
+fn decode_error(detail: &str) -> WalletError {
+    WalletError::InvalidInput(format!("invalid abi data: {detail}"))
+}
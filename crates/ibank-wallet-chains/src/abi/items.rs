@@ -0,0 +1,554 @@
+//! Functions, events and errors from human-readable signatures and JSON ABIs.
+
+use std::fmt;
+
+use ibank_wallet_core::{Result, WalletError};
+use serde::Deserialize;
+
+use crate::abi::codec::{decode, encode_checked};
+use crate::abi::types::{matching_paren, parse_param_decl, split_params, ParamType, Token};
+use crate::evm::keccak256;
+use crate::quantity::U256;
+
+/// Selector of the builtin `Error(string)` revert reason.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of the builtin `Panic(uint256)` revert reason.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// A named function, event or error parameter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Param {
+    /// Parameter name (may be empty).
+    pub name: String,
+    /// Parameter type.
+    pub kind: ParamType,
+    /// Whether an event parameter is stored in a topic.
+    pub indexed: bool,
+}
+
+/// Function state mutability.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateMutability {
+    /// Does not read or modify state.
+    Pure,
+    /// Reads but does not modify state.
+    View,
+    /// Modifies state and rejects value.
+    #[default]
+    NonPayable,
+    /// Modifies state and accepts value.
+    Payable,
+}
+
+/// A contract function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    /// Function name.
+    pub name: String,
+    /// Input parameters.
+    pub inputs: Vec<Param>,
+    /// Output parameters.
+    pub outputs: Vec<Param>,
+    /// State mutability.
+    pub state_mutability: StateMutability,
+}
+
+impl Function {
+    /// Parses a human-readable signature, e.g. `transfer(address,uint256)` or
+    /// `function balanceOf(address owner) view returns (uint256)`.
+    pub fn parse(signature: &str) -> Result<Self> {
+        let parsed = parse_signature(signature, "function")?;
+        let state_mutability = parsed
+            .modifiers
+            .iter()
+            .find_map(|word| match *word {
+                "pure" => Some(StateMutability::Pure),
+                "view" | "constant" => Some(StateMutability::View),
+                "payable" => Some(StateMutability::Payable),
+                _ => None,
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            name: parsed.name,
+            inputs: parsed.inputs,
+            outputs: parsed.outputs,
+            state_mutability,
+        })
+    }
+
+    /// Returns the canonical signature, e.g. `transfer(address,uint256)`.
+    pub fn signature(&self) -> String {
+        canonical_signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the 4-byte selector.
+    pub fn selector(&self) -> [u8; 4] {
+        selector(&self.signature())
+    }
+
+    /// Encodes calldata: selector followed by the encoded arguments.
+    pub fn encode_input(&self, args: &[Token]) -> Result<Vec<u8>> {
+        let mut out = self.selector().to_vec();
+        out.extend(encode_checked(&kinds(&self.inputs), args)?);
+        Ok(out)
+    }
+
+    /// Decodes calldata produced for this function.
+    pub fn decode_input(&self, calldata: &[u8]) -> Result<Vec<Token>> {
+        let args = strip_selector(calldata, self.selector(), &self.name)?;
+        decode(&kinds(&self.inputs), args)
+    }
+
+    /// Decodes return data of this function.
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<Token>> {
+        decode(&kinds(&self.outputs), data)
+    }
+}
+
+/// A contract event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// Event name.
+    pub name: String,
+    /// Event parameters.
+    pub inputs: Vec<Param>,
+    /// Whether the event omits its signature topic.
+    pub anonymous: bool,
+}
+
+impl Event {
+    /// Parses a human-readable signature, e.g.
+    /// `event Transfer(address indexed from, address indexed to, uint256 value)`.
+    pub fn parse(signature: &str) -> Result<Self> {
+        let parsed = parse_signature(signature, "event")?;
+        Ok(Self {
+            name: parsed.name,
+            inputs: parsed.inputs,
+            anonymous: parsed.modifiers.contains(&"anonymous"),
+        })
+    }
+
+    /// Returns the canonical signature, e.g. `Transfer(address,address,uint256)`.
+    pub fn signature(&self) -> String {
+        canonical_signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the signature topic (topic 0 of non-anonymous events).
+    pub fn topic(&self) -> [u8; 32] {
+        keccak256(self.signature().as_bytes())
+    }
+
+    /// Decodes a log into the event parameters, in declaration order. Indexed
+    /// parameters of dynamic types are returned as their 32-byte topic hash.
+    pub fn decode_log(&self, topics: &[[u8; 32]], data: &[u8]) -> Result<Vec<Token>> {
+        let mut topics = topics.iter();
+        if !self.anonymous && topics.next() != Some(&self.topic()) {
+            return Err(WalletError::InvalidInput(format!(
+                "log is not a {} event",
+                self.name
+            )));
+        }
+        let data_kinds: Vec<ParamType> = self
+            .inputs
+            .iter()
+            .filter(|param| !param.indexed)
+            .map(|param| param.kind.clone())
+            .collect();
+        let mut data_tokens = decode(&data_kinds, data)?.into_iter();
+        let mut tokens = Vec::with_capacity(self.inputs.len());
+        for param in &self.inputs {
+            if !param.indexed {
+                tokens.extend(data_tokens.next());
+                continue;
+            }
+            let topic = topics.next().ok_or_else(|| {
+                WalletError::InvalidInput(format!("missing topic for {}", param.name))
+            })?;
+            let token = match &param.kind {
+                ParamType::Bytes
+                | ParamType::String
+                | ParamType::Array(_)
+                | ParamType::FixedArray(..)
+                | ParamType::Tuple(_) => Token::FixedBytes(topic.to_vec()),
+                kind => decode(std::slice::from_ref(kind), topic)?.remove(0),
+            };
+            tokens.push(token);
+        }
+        if topics.next().is_some() {
+            return Err(WalletError::InvalidInput(format!(
+                "too many topics for {}",
+                self.name
+            )));
+        }
+        Ok(tokens)
+    }
+}
+
+/// A custom error (`error InsufficientBalance(uint256 available)`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbiError {
+    /// Error name.
+    pub name: String,
+    /// Error parameters.
+    pub inputs: Vec<Param>,
+}
+
+impl AbiError {
+    /// Parses a human-readable signature, e.g. `error Unauthorized(address caller)`.
+    pub fn parse(signature: &str) -> Result<Self> {
+        let parsed = parse_signature(signature, "error")?;
+        Ok(Self {
+            name: parsed.name,
+            inputs: parsed.inputs,
+        })
+    }
+
+    /// Returns the canonical signature.
+    pub fn signature(&self) -> String {
+        canonical_signature(&self.name, &self.inputs)
+    }
+
+    /// Returns the 4-byte selector.
+    pub fn selector(&self) -> [u8; 4] {
+        selector(&self.signature())
+    }
+
+    /// Decodes revert data raised with this error.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<Token>> {
+        let args = strip_selector(data, self.selector(), &self.name)?;
+        decode(&kinds(&self.inputs), args)
+    }
+}
+
+/// A decoded revert reason.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Revert {
+    /// `revert("reason")` / `require(cond, "reason")`.
+    Error(String),
+    /// Compiler-inserted `Panic(uint256)` (overflow, assert, ...).
+    Panic(U256),
+    /// A custom error declared in the ABI.
+    Custom {
+        /// Error name.
+        name: String,
+        /// Decoded error arguments.
+        args: Vec<Token>,
+    },
+    /// Revert data that matches no known error (including empty data).
+    Unknown(Vec<u8>),
+}
+
+impl Revert {
+    /// Describes a panic code per the Solidity documentation.
+    pub fn panic_reason(code: U256) -> &'static str {
+        if code.bits() > 8 {
+            return "unknown panic code";
+        }
+        match code.low_u32() {
+            0x00 => "generic compiler panic",
+            0x01 => "assertion failed",
+            0x11 => "arithmetic overflow or underflow",
+            0x12 => "division or modulo by zero",
+            0x21 => "invalid enum value",
+            0x22 => "invalid storage byte array encoding",
+            0x31 => "pop on empty array",
+            0x32 => "array index out of bounds",
+            0x41 => "out of memory",
+            0x51 => "call to zero-initialized function",
+            _ => "unknown panic code",
+        }
+    }
+}
+
+impl fmt::Display for Revert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Revert::Error(reason) => write!(f, "execution reverted: {reason}"),
+            Revert::Panic(code) => write!(f, "panic {code:#x} ({})", Revert::panic_reason(*code)),
+            Revert::Custom { name, args } => {
+                write!(f, "{name}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                f.write_str(")")
+            }
+            Revert::Unknown(data) if data.is_empty() => f.write_str("execution reverted"),
+            Revert::Unknown(data) => write!(f, "execution reverted: 0x{}", hex::encode(data)),
+        }
+    }
+}
+
+/// Decodes revert data against the builtin `Error(string)` and `Panic(uint256)`
+/// reasons and the given custom errors.
+pub fn decode_revert(data: &[u8], errors: &[AbiError]) -> Result<Revert> {
+    let Some(selector) = data.get(..4) else {
+        return Ok(Revert::Unknown(data.to_vec()));
+    };
+    if selector == ERROR_SELECTOR {
+        let mut tokens = decode(&[ParamType::String], &data[4..])?;
+        if let Some(Token::String(reason)) = tokens.pop() {
+            return Ok(Revert::Error(reason));
+        }
+    }
+    if selector == PANIC_SELECTOR {
+        let mut tokens = decode(&[ParamType::Uint(256)], &data[4..])?;
+        if let Some(Token::Uint(code)) = tokens.pop() {
+            return Ok(Revert::Panic(code));
+        }
+    }
+    for error in errors {
+        if selector == error.selector() {
+            return Ok(Revert::Custom {
+                name: error.name.clone(),
+                args: error.decode(data)?,
+            });
+        }
+    }
+    Ok(Revert::Unknown(data.to_vec()))
+}
+
+/// A contract ABI: its functions, events and errors.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Abi {
+    /// Functions, including overloads.
+    pub functions: Vec<Function>,
+    /// Events.
+    pub events: Vec<Event>,
+    /// Custom errors.
+    pub errors: Vec<AbiError>,
+}
+
+impl Abi {
+    /// Parses a JSON ABI as emitted by solc. Constructors, fallback and
+    /// receive entries are ignored.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let entries: Vec<JsonEntry> = serde_json::from_str(json)
+            .map_err(|err| WalletError::InvalidInput(format!("invalid json abi: {err}")))?;
+        let mut abi = Abi::default();
+        for entry in entries {
+            let inputs = json_params(&entry.inputs)?;
+            match entry.kind.as_str() {
+                "function" => abi.functions.push(Function {
+                    name: entry.name,
+                    inputs,
+                    outputs: json_params(&entry.outputs)?,
+                    state_mutability: entry.state_mutability.unwrap_or_default(),
+                }),
+                "event" => abi.events.push(Event {
+                    name: entry.name,
+                    inputs,
+                    anonymous: entry.anonymous,
+                }),
+                "error" => abi.errors.push(AbiError {
+                    name: entry.name,
+                    inputs,
+                }),
+                "constructor" | "fallback" | "receive" => {}
+                other => {
+                    return Err(WalletError::InvalidInput(format!(
+                        "unknown abi entry type: {other}"
+                    )))
+                }
+            }
+        }
+        Ok(abi)
+    }
+
+    /// Parses human-readable signatures (`function ...`, `event ...`, `error ...`).
+    pub fn parse(signatures: &[&str]) -> Result<Self> {
+        let mut abi = Abi::default();
+        for signature in signatures {
+            let signature = signature.trim();
+            if signature.starts_with("event ") {
+                abi.events.push(Event::parse(signature)?);
+            } else if signature.starts_with("error ") {
+                abi.errors.push(AbiError::parse(signature)?);
+            } else {
+                abi.functions.push(Function::parse(signature)?);
+            }
+        }
+        Ok(abi)
+    }
+
+    /// Returns the first function named `name`.
+    pub fn function(&self, name: &str) -> Result<&Function> {
+        self.functions
+            .iter()
+            .find(|function| function.name == name)
+            .ok_or_else(|| WalletError::InvalidInput(format!("unknown function: {name}")))
+    }
+
+    /// Returns the function whose selector starts `calldata`.
+    pub fn function_for_calldata(&self, calldata: &[u8]) -> Option<&Function> {
+        let selector = calldata.get(..4)?;
+        self.functions
+            .iter()
+            .find(|function| function.selector() == selector)
+    }
+
+    /// Returns the event named `name`.
+    pub fn event(&self, name: &str) -> Result<&Event> {
+        self.events
+            .iter()
+            .find(|event| event.name == name)
+            .ok_or_else(|| WalletError::InvalidInput(format!("unknown event: {name}")))
+    }
+
+    /// Decodes revert data using the builtin reasons and this ABI's errors.
+    pub fn decode_revert(&self, data: &[u8]) -> Result<Revert> {
+        decode_revert(data, &self.errors)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonEntry {
+    #[serde(rename = "type", default = "default_entry_type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    inputs: Vec<JsonParam>,
+    #[serde(default)]
+    outputs: Vec<JsonParam>,
+    #[serde(rename = "stateMutability", default)]
+    state_mutability: Option<StateMutability>,
+    #[serde(default)]
+    anonymous: bool,
+}
+
+#[derive(Deserialize)]
+struct JsonParam {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    components: Vec<JsonParam>,
+    #[serde(default)]
+    indexed: bool,
+}
+
+fn default_entry_type() -> String {
+    "function".to_string()
+}
+
+fn json_params(params: &[JsonParam]) -> Result<Vec<Param>> {
+    params
+        .iter()
+        .map(|param| {
+            Ok(Param {
+                name: param.name.clone(),
+                kind: json_kind(param)?,
+                indexed: param.indexed,
+            })
+        })
+        .collect()
+}
+
+fn json_kind(param: &JsonParam) -> Result<ParamType> {
+    match param.kind.strip_prefix("tuple") {
+        Some(suffix) => {
+            let components = param
+                .components
+                .iter()
+                .map(json_kind)
+                .collect::<Result<Vec<_>>>()?;
+            ParamType::apply_array_suffix(ParamType::Tuple(components), suffix)
+        }
+        None => ParamType::parse(&param.kind),
+    }
+}
+
+struct ParsedSignature<'a> {
+    name: String,
+    inputs: Vec<Param>,
+    outputs: Vec<Param>,
+    modifiers: Vec<&'a str>,
+}
+
+fn parse_signature<'a>(signature: &'a str, keyword: &str) -> Result<ParsedSignature<'a>> {
+    let invalid = || WalletError::InvalidInput(format!("invalid {keyword} signature: {signature}"));
+    let trimmed = signature.trim();
+    let trimmed = trimmed
+        .strip_prefix(keyword)
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .unwrap_or(trimmed)
+        .trim_start();
+    let open = trimmed.find('(').ok_or_else(invalid)?;
+    let name = trimmed[..open].trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    {
+        return Err(invalid());
+    }
+    let close = open + matching_paren(&trimmed[open..])?;
+    let inputs = parse_params(&trimmed[open + 1..close])?;
+    let mut rest = trimmed[close + 1..].trim();
+    let mut outputs = Vec::new();
+    let mut modifiers = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("returns") {
+            let after = after.trim_start();
+            if !after.starts_with('(') {
+                return Err(invalid());
+            }
+            let end = matching_paren(after)?;
+            outputs = parse_params(&after[1..end])?;
+            rest = after[end + 1..].trim_start();
+            continue;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        modifiers.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Ok(ParsedSignature {
+        name: name.to_string(),
+        inputs,
+        outputs,
+        modifiers,
+    })
+}
+
+fn parse_params(list: &str) -> Result<Vec<Param>> {
+    split_params(list)?
+        .into_iter()
+        .map(|decl| {
+            let (kind, indexed, name) = parse_param_decl(decl)?;
+            Ok(Param {
+                name,
+                kind,
+                indexed,
+            })
+        })
+        .collect()
+}
+
+fn canonical_signature(name: &str, params: &[Param]) -> String {
+    let kinds: Vec<String> = params.iter().map(|param| param.kind.to_string()).collect();
+    format!("{name}({})", kinds.join(","))
+}
+
+fn kinds(params: &[Param]) -> Vec<ParamType> {
+    params.iter().map(|param| param.kind.clone()).collect()
+}
+
+fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn strip_selector<'a>(data: &'a [u8], expected: [u8; 4], name: &str) -> Result<&'a [u8]> {
+    match data.split_first_chunk::<4>() {
+        Some((selector, args)) if *selector == expected => Ok(args),
+        _ => Err(WalletError::InvalidInput(format!(
+            "data does not start with the {name} selector"
+        ))),
+    }
+}
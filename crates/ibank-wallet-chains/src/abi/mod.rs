@@ -0,0 +1,299 @@
+//! Solidity contract ABI: types, encoding, selectors, event topics and
+//! revert decoding.
+
+mod codec;
+mod items;
+mod types;
+
+pub use codec::{decode, encode, encode_checked};
+pub use items::{
+    decode_revert, Abi, AbiError, Event, Function, Param, Revert, StateMutability, ERROR_SELECTOR,
+    PANIC_SELECTOR,
+};
+pub use types::{ParamType, Token};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Address;
+    use crate::quantity::U256;
+
+    fn words(hex_words: &[&str]) -> Vec<u8> {
+        hex_words
+            .iter()
+            .flat_map(|word| {
+                let padded = format!("{word:0>64}");
+                hex::decode(padded).expect("valid hex")
+            })
+            .collect()
+    }
+
+    fn uint(value: u64) -> Token {
+        Token::Uint(U256::from(value))
+    }
+
+    #[test]
+    fn computes_selectors_and_topics() {
+        let transfer =
+            Function::parse("function transfer(address to, uint256 amount) returns (bool)")
+                .expect("function");
+        assert_eq!(transfer.signature(), "transfer(address,uint256)");
+        assert_eq!(hex::encode(transfer.selector()), "a9059cbb");
+        assert_eq!(transfer.outputs[0].kind, ParamType::Bool);
+
+        let event =
+            Event::parse("event Transfer(address indexed from, address indexed to, uint256 value)")
+                .expect("event");
+        assert_eq!(
+            hex::encode(event.topic()),
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+
+        let tuple = Function::parse("fill((address maker, uint256[] amounts)[] orders, bytes sig)")
+            .expect("tuple function");
+        assert_eq!(tuple.signature(), "fill((address,uint256[])[],bytes)");
+    }
+
+    #[test]
+    fn encodes_solidity_documentation_examples() {
+        let baz = Function::parse("baz(uint32,bool)").expect("baz");
+        let calldata = baz
+            .encode_input(&[uint(69), Token::Bool(true)])
+            .expect("encode");
+        let mut expected = hex::decode("cdcd77c0").expect("selector");
+        expected.extend(words(&["45", "1"]));
+        assert_eq!(calldata, expected);
+
+        let f = Function::parse("f(uint256,uint32[],bytes10,bytes)").expect("f");
+        let args = vec![
+            uint(0x123),
+            Token::Array(vec![uint(0x456), uint(0x789)]),
+            Token::FixedBytes(b"1234567890".to_vec()),
+            Token::Bytes(b"Hello, world!".to_vec()),
+        ];
+        let calldata = f.encode_input(&args).expect("encode");
+        let mut expected = hex::decode("8be65246").expect("selector");
+        expected.extend(words(&[
+            "123",
+            "80",
+            "3132333435363738393000000000000000000000000000000000000000000000",
+            "e0",
+            "2",
+            "456",
+            "789",
+            "d",
+            "48656c6c6f2c20776f726c642100000000000000000000000000000000000000",
+        ]));
+        assert_eq!(calldata, expected);
+        assert_eq!(f.decode_input(&calldata).expect("decode"), args);
+    }
+
+    #[test]
+    fn round_trips_nested_dynamic_types() {
+        let g = Function::parse("g(uint256[][],string[])").expect("g");
+        let args = vec![
+            Token::Array(vec![
+                Token::Array(vec![uint(1), uint(2)]),
+                Token::Array(vec![uint(3)]),
+            ]),
+            Token::Array(vec![
+                Token::String("one".to_string()),
+                Token::String("two".to_string()),
+                Token::String("three".to_string()),
+            ]),
+        ];
+        let calldata = g.encode_input(&args).expect("encode");
+        let mut expected = hex::decode("2289b18c").expect("selector");
+        expected.extend(words(&[
+            "40",
+            "140",
+            "2",
+            "40",
+            "a0",
+            "2",
+            "1",
+            "2",
+            "1",
+            "3",
+            "3",
+            "60",
+            "a0",
+            "e0",
+            "3",
+            "6f6e650000000000000000000000000000000000000000000000000000000000",
+            "3",
+            "74776f0000000000000000000000000000000000000000000000000000000000",
+            "5",
+            "7468726565000000000000000000000000000000000000000000000000000000",
+        ]));
+        assert_eq!(calldata, expected);
+        assert_eq!(g.decode_input(&calldata).expect("decode"), args);
+
+        let kinds = vec![
+            ParamType::parse("(int8,bytes,address[2])").expect("tuple type"),
+            ParamType::parse("int").expect("int"),
+        ];
+        let tokens = vec![
+            Token::Tuple(vec![
+                Token::int(-1),
+                Token::Bytes(vec![0xab; 40]),
+                Token::FixedArray(vec![
+                    Token::Address(Address([1; 20])),
+                    Token::Address(Address([2; 20])),
+                ]),
+            ]),
+            Token::int(i128::MIN),
+        ];
+        let encoded = encode_checked(&kinds, &tokens).expect("encode");
+        assert_eq!(decode(&kinds, &encoded).expect("decode"), tokens);
+        assert_eq!(tokens[0].to_string().split(',').next(), Some("(-1"));
+    }
+
+    #[test]
+    fn rejects_invalid_values_and_data() {
+        let kinds = [ParamType::Uint(8)];
+        assert!(encode_checked(&kinds, &[uint(256)]).is_err());
+        assert!(decode(&kinds, &words(&["100"])).is_err());
+        assert!(decode(&[ParamType::Int(8)], &words(&["80"])).is_err());
+        assert!(decode(&[ParamType::Bool], &words(&["2"])).is_err());
+        assert!(decode(&[ParamType::Address], &words(&[&"f".repeat(64)])).is_err());
+        // Offset and length beyond the data.
+        assert!(decode(&[ParamType::Bytes], &words(&["40"])).is_err());
+        assert!(decode(&[ParamType::Bytes], &words(&["20", "ffff"])).is_err());
+        assert!(decode(
+            &[ParamType::parse("uint256[]").expect("array")],
+            &words(&["20", "ffffffff"])
+        )
+        .is_err());
+        assert!(ParamType::parse("uint7").is_err());
+        assert!(ParamType::parse("bytes33").is_err());
+    }
+
+    #[test]
+    fn bounds_array_sizes_before_allocating() {
+        assert!(ParamType::parse("uint256[0]").is_err());
+        assert!(ParamType::parse("uint256[0][]").is_err());
+        assert!(ParamType::parse(&format!("uint256[{}]", usize::MAX)).is_err());
+        assert!(ParamType::parse(&format!("uint256[{}][2]", usize::MAX / 32)).is_err());
+        assert!(ParamType::parse(&format!("(uint256[{}],uint256)", usize::MAX / 32)).is_err());
+
+        // Lengths are checked against the data even for types built directly.
+        let huge = words(&["20", "ffffffff"]);
+        let zero_sized = [
+            ParamType::Array(Box::new(ParamType::FixedArray(
+                Box::new(ParamType::Uint(256)),
+                0,
+            ))),
+            ParamType::Array(Box::new(ParamType::Tuple(Vec::new()))),
+        ];
+        for kind in zero_sized {
+            assert!(decode(&[kind], &huge).is_err());
+        }
+        let fixed = ParamType::parse("uint256[1000000]").expect("fixed array");
+        assert!(decode(&[fixed], &words(&["1"])).is_err());
+        let nested = ParamType::parse("uint256[2][]").expect("nested array");
+        assert!(decode(
+            std::slice::from_ref(&nested),
+            &words(&["20", "2", "1", "2", "3"])
+        )
+        .is_err());
+        assert_eq!(
+            decode(&[nested], &words(&["20", "1", "1", "2"])).expect("decoded"),
+            vec![Token::Array(vec![Token::FixedArray(vec![
+                uint(1),
+                uint(2)
+            ])])]
+        );
+    }
+
+    #[test]
+    fn decodes_revert_reasons() {
+        let mut error = ERROR_SELECTOR.to_vec();
+        error.extend(encode(&[Token::String(
+            "Not enough Ether provided.".to_string(),
+        )]));
+        let revert = decode_revert(&error, &[]).expect("error");
+        assert_eq!(
+            revert,
+            Revert::Error("Not enough Ether provided.".to_string())
+        );
+        assert_eq!(
+            revert.to_string(),
+            "execution reverted: Not enough Ether provided."
+        );
+
+        let mut panic = PANIC_SELECTOR.to_vec();
+        panic.extend(words(&["11"]));
+        assert_eq!(
+            decode_revert(&panic, &[]).expect("panic").to_string(),
+            "panic 0x11 (arithmetic overflow or underflow)"
+        );
+
+        let abi = Abi::parse(&["error InsufficientBalance(uint256 available, uint256 required)"])
+            .expect("abi");
+        let custom = &abi.errors[0];
+        let mut data = custom.selector().to_vec();
+        data.extend(encode(&[uint(1), uint(2)]));
+        assert_eq!(
+            abi.decode_revert(&data).expect("custom").to_string(),
+            "InsufficientBalance(1, 2)"
+        );
+        assert_eq!(
+            decode_revert(&data, &[]).expect("unknown"),
+            Revert::Unknown(data.clone())
+        );
+        assert_eq!(
+            decode_revert(&[], &[]).expect("empty"),
+            Revert::Unknown(Vec::new())
+        );
+    }
+
+    #[test]
+    fn parses_json_abi_and_decodes_logs() {
+        let abi = Abi::from_json(
+            r#"[
+                {"type":"function","name":"submit","stateMutability":"payable",
+                 "inputs":[{"name":"orders","type":"tuple[]","components":[
+                    {"name":"maker","type":"address"},{"name":"amount","type":"uint128"}]}],
+                 "outputs":[]},
+                {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+                    {"name":"from","type":"address","indexed":true},
+                    {"name":"to","type":"address","indexed":true},
+                    {"name":"value","type":"uint256","indexed":false}]},
+                {"type":"error","name":"Unauthorized","inputs":[{"name":"caller","type":"address"}]},
+                {"type":"constructor","inputs":[]}
+            ]"#,
+        )
+        .expect("json abi");
+        let submit = abi.function("submit").expect("submit");
+        assert_eq!(submit.signature(), "submit((address,uint128)[])");
+        assert_eq!(submit.state_mutability, StateMutability::Payable);
+        let calldata = submit
+            .encode_input(&[Token::Array(vec![Token::Tuple(vec![
+                Token::Address(Address([7; 20])),
+                uint(5),
+            ])])])
+            .expect("encode");
+        assert_eq!(abi.function_for_calldata(&calldata), Some(submit));
+
+        let transfer = abi.event("Transfer").expect("event");
+        let mut from = [0u8; 32];
+        from[12..].copy_from_slice(&[0x11; 20]);
+        let mut to = [0u8; 32];
+        to[12..].copy_from_slice(&[0x22; 20]);
+        let tokens = transfer
+            .decode_log(&[transfer.topic(), from, to], &words(&["64"]))
+            .expect("log");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Address(Address([0x11; 20])),
+                Token::Address(Address([0x22; 20])),
+                uint(100),
+            ]
+        );
+        assert!(transfer
+            .decode_log(&[from, from, to], &words(&["64"]))
+            .is_err());
+    }
+}
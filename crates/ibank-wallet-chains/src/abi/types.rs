@@ -0,0 +1,411 @@
+//! ABI parameter types and values.
+
+use std::fmt;
+
+use ibank_wallet_core::{Result, WalletError};
+
+use crate::address::Address;
+use crate::quantity::U256;
+
+/// A Solidity ABI type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParamType {
+    /// `address`.
+    Address,
+    /// `bool`.
+    Bool,
+    /// `uintN` with N in 8..=256, a multiple of 8.
+    Uint(usize),
+    /// `intN` with N in 8..=256, a multiple of 8.
+    Int(usize),
+    /// `bytesN` with N in 1..=32.
+    FixedBytes(usize),
+    /// `bytes`.
+    Bytes,
+    /// `string`.
+    String,
+    /// `T[]`.
+    Array(Box<ParamType>),
+    /// `T[k]`.
+    FixedArray(Box<ParamType>, usize),
+    /// `(T1,T2,...)`.
+    Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+    /// Parses a canonical or human-readable type (`uint`, `(address,uint256)[]`,
+    /// `tuple(uint256 a, bytes b)`).
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let value = value.strip_prefix("tuple").unwrap_or(value).trim_start();
+        let (base, suffix) = if value.starts_with('(') {
+            let close = matching_paren(value)?;
+            let components = split_params(&value[1..close])?
+                .into_iter()
+                .map(|param| parse_param_decl(param).map(|(kind, _, _)| kind))
+                .collect::<Result<Vec<_>>>()?;
+            (ParamType::Tuple(components), &value[close + 1..])
+        } else {
+            let end = value.find('[').unwrap_or(value.len());
+            (Self::parse_elementary(&value[..end])?, &value[end..])
+        };
+        Self::apply_array_suffix(base, suffix)
+    }
+
+    /// Wraps `base` in the array dimensions of `suffix` (e.g. `[2][]`).
+    pub(crate) fn apply_array_suffix(mut base: ParamType, mut suffix: &str) -> Result<Self> {
+        while !suffix.is_empty() {
+            let inner = suffix
+                .strip_prefix('[')
+                .and_then(|rest| rest.find(']').map(|end| (&rest[..end], &rest[end + 1..])));
+            let Some((size, rest)) = inner else {
+                return Err(invalid_type(suffix));
+            };
+            base = if size.is_empty() {
+                ParamType::Array(Box::new(base))
+            } else {
+                // Empty arrays and arrays whose encoding overflows are rejected.
+                let size = size
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .filter(|size| {
+                        base.head_size()
+                            .and_then(|head| head.checked_mul(*size))
+                            .is_some()
+                    })
+                    .ok_or_else(|| invalid_type(suffix))?;
+                ParamType::FixedArray(Box::new(base), size)
+            };
+            suffix = rest;
+        }
+        if base.head_size().is_none() {
+            return Err(invalid_type(&base.to_string()));
+        }
+        Ok(base)
+    }
+
+    fn parse_elementary(name: &str) -> Result<Self> {
+        match name {
+            "address" => return Ok(ParamType::Address),
+            "bool" => return Ok(ParamType::Bool),
+            "bytes" => return Ok(ParamType::Bytes),
+            "string" => return Ok(ParamType::String),
+            "uint" => return Ok(ParamType::Uint(256)),
+            "int" => return Ok(ParamType::Int(256)),
+            "function" => return Ok(ParamType::FixedBytes(24)),
+            _ => {}
+        }
+        let sized = |prefix: &str, min: usize, max: usize, step: usize| {
+            name.strip_prefix(prefix)
+                .and_then(|size| size.parse::<usize>().ok())
+                .filter(|size| *size >= min && *size <= max && size % step == 0)
+        };
+        if let Some(bits) = sized("uint", 8, 256, 8) {
+            return Ok(ParamType::Uint(bits));
+        }
+        if let Some(bits) = sized("int", 8, 256, 8) {
+            return Ok(ParamType::Int(bits));
+        }
+        if let Some(size) = sized("bytes", 1, 32, 1) {
+            return Ok(ParamType::FixedBytes(size));
+        }
+        Err(invalid_type(name))
+    }
+
+    /// Returns true if the encoding of the type has a variable length.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(inner, _) => inner.is_dynamic(),
+            ParamType::Tuple(components) => components.iter().any(ParamType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Returns the size of the type in the head of an enclosing tuple, or
+    /// `None` if it overflows.
+    pub(crate) fn head_size(&self) -> Option<usize> {
+        if self.is_dynamic() {
+            return Some(32);
+        }
+        match self {
+            ParamType::FixedArray(inner, size) => inner.head_size()?.checked_mul(*size),
+            ParamType::Tuple(components) => {
+                components.iter().try_fold(0usize, |total, component| {
+                    total.checked_add(component.head_size()?)
+                })
+            }
+            _ => Some(32),
+        }
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamType::Address => f.write_str("address"),
+            ParamType::Bool => f.write_str("bool"),
+            ParamType::Uint(bits) => write!(f, "uint{bits}"),
+            ParamType::Int(bits) => write!(f, "int{bits}"),
+            ParamType::FixedBytes(size) => write!(f, "bytes{size}"),
+            ParamType::Bytes => f.write_str("bytes"),
+            ParamType::String => f.write_str("string"),
+            ParamType::Array(inner) => write!(f, "{inner}[]"),
+            ParamType::FixedArray(inner, size) => write!(f, "{inner}[{size}]"),
+            ParamType::Tuple(components) => {
+                f.write_str("(")?;
+                for (i, component) in components.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{component}")?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// A decoded or to-be-encoded ABI value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    /// `address` value.
+    Address(Address),
+    /// `bool` value.
+    Bool(bool),
+    /// `uintN` value.
+    Uint(U256),
+    /// `intN` value in 256-bit two's complement.
+    Int(U256),
+    /// `bytesN` value.
+    FixedBytes(Vec<u8>),
+    /// `bytes` value.
+    Bytes(Vec<u8>),
+    /// `string` value.
+    String(String),
+    /// `T[]` elements.
+    Array(Vec<Token>),
+    /// `T[k]` elements.
+    FixedArray(Vec<Token>),
+    /// Tuple components.
+    Tuple(Vec<Token>),
+}
+
+impl Token {
+    /// Builds an `intN` token from a signed integer.
+    pub fn int(value: i128) -> Self {
+        let magnitude = U256::from(value.unsigned_abs());
+        if value < 0 {
+            Token::Int(negate(magnitude))
+        } else {
+            Token::Int(magnitude)
+        }
+    }
+
+    /// Returns true if the token is a valid value of `kind`, including range
+    /// checks for sized integers and byte strings.
+    pub fn type_check(&self, kind: &ParamType) -> bool {
+        match (self, kind) {
+            (Token::Address(_), ParamType::Address)
+            | (Token::Bool(_), ParamType::Bool)
+            | (Token::Bytes(_), ParamType::Bytes)
+            | (Token::String(_), ParamType::String) => true,
+            (Token::Uint(value), ParamType::Uint(bits)) => value.bits() <= *bits,
+            (Token::Int(value), ParamType::Int(bits)) => fits_signed(value, *bits),
+            (Token::FixedBytes(bytes), ParamType::FixedBytes(size)) => bytes.len() == *size,
+            (Token::Array(items), ParamType::Array(inner)) => {
+                items.iter().all(|item| item.type_check(inner))
+            }
+            (Token::FixedArray(items), ParamType::FixedArray(inner, size)) => {
+                items.len() == *size && items.iter().all(|item| item.type_check(inner))
+            }
+            (Token::Tuple(items), ParamType::Tuple(components)) => {
+                items.len() == components.len()
+                    && items
+                        .iter()
+                        .zip(components)
+                        .all(|(item, component)| item.type_check(component))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the address if the token is an `address`.
+    pub fn as_address(&self) -> Option<Address> {
+        match self {
+            Token::Address(address) => Some(*address),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if the token is a `uintN`.
+    pub fn as_uint(&self) -> Option<U256> {
+        match self {
+            Token::Uint(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if the token is a `bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Token::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of `bytes` and `bytesN` tokens.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Token::Bytes(bytes) | Token::FixedBytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if the token is a `string`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Token::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of arrays and tuples.
+    pub fn as_list(&self) -> Option<&[Token]> {
+        match self {
+            Token::Array(items) | Token::FixedArray(items) | Token::Tuple(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Address(address) => write!(f, "{address}"),
+            Token::Bool(value) => write!(f, "{value}"),
+            Token::Uint(value) => write!(f, "{value}"),
+            Token::Int(value) if value.bit(255) => write!(f, "-{}", negate(*value)),
+            Token::Int(value) => write!(f, "{value}"),
+            Token::FixedBytes(bytes) | Token::Bytes(bytes) => {
+                write!(f, "0x{}", hex::encode(bytes))
+            }
+            Token::String(value) => write!(f, "{value:?}"),
+            Token::Array(items) | Token::FixedArray(items) => write_list(f, items, "[", "]"),
+            Token::Tuple(items) => write_list(f, items, "(", ")"),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, items: &[Token], open: &str, close: &str) -> fmt::Result {
+    f.write_str(open)?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    f.write_str(close)
+}
+
+/// Returns the two's complement negation of `value`.
+pub(crate) fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+/// Returns true if the two's complement `value` is the sign extension of an
+/// `bits`-wide integer.
+pub(crate) fn fits_signed(value: &U256, bits: usize) -> bool {
+    if bits == 256 {
+        return true;
+    }
+    let magnitude = if value.bit(255) { !*value } else { *value };
+    magnitude.bits() < bits
+}
+
+/// A declared parameter: its type, whether it is `indexed` and its name.
+pub(crate) type ParamDecl = (ParamType, bool, String);
+
+/// Parses a human-readable parameter declaration such as
+/// `address indexed from` or `(uint256,bytes)[] calldata orders`.
+pub(crate) fn parse_param_decl(value: &str) -> Result<ParamDecl> {
+    let value = value.trim();
+    let type_end = if value.starts_with('(') || value.starts_with("tuple(") {
+        let open = value.find('(').unwrap_or(0);
+        let close = open + matching_paren(&value[open..])?;
+        close
+            + 1
+            + value[close + 1..]
+                .find(char::is_whitespace)
+                .unwrap_or(value.len() - close - 1)
+    } else {
+        value.find(char::is_whitespace).unwrap_or(value.len())
+    };
+    let kind = ParamType::parse(&value[..type_end])?;
+    let mut indexed = false;
+    let mut name = String::new();
+    for word in value[type_end..].split_whitespace() {
+        match word {
+            "indexed" => indexed = true,
+            "memory" | "calldata" | "storage" | "payable" => {}
+            _ if name.is_empty() => name = word.to_string(),
+            _ => {
+                return Err(WalletError::InvalidInput(format!(
+                    "invalid parameter declaration: {value}"
+                )))
+            }
+        }
+    }
+    Ok((kind, indexed, name))
+}
+
+/// Splits a comma-separated parameter list at top-level commas.
+pub(crate) fn split_params(value: &str) -> Result<Vec<&str>> {
+    if value.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.checked_sub(1).ok_or_else(|| invalid_type(value))?;
+            }
+            ',' if depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(invalid_type(value));
+    }
+    parts.push(&value[start..]);
+    Ok(parts)
+}
+
+/// Returns the index of the parenthesis closing the one `value` starts with.
+pub(crate) fn matching_paren(value: &str) -> Result<usize> {
+    let mut depth = 0usize;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(invalid_type(value))
+}
+
+fn invalid_type(value: &str) -> WalletError {
+    WalletError::InvalidInput(format!("invalid abi type: {value}"))
+}
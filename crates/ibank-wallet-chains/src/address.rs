@@ -0,0 +1,229 @@
+//! EVM account addresses with EIP-55 checksummed hex encoding.
+
+use std::fmt;
+use std::str::FromStr;
+
+use ibank_wallet_core::{CaipAccountId, CaipChainId, Result, WalletError, EIP155_NAMESPACE};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::evm::keccak256;
+
+/// 20-byte EVM account address.
+///
+/// Displays and serializes as `0x`-prefixed EIP-55 checksummed hex. Parsing
+/// accepts all-lowercase or all-uppercase hex, and validates the checksum of
+/// mixed-case input.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    /// The zero address.
+    pub const ZERO: Self = Self([0u8; 20]);
+
+    /// Wraps raw address bytes.
+    pub const fn new(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+
+    /// Builds an address from a 20-byte slice.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 20] = bytes.try_into().map_err(|_| {
+            WalletError::InvalidInput(format!("address must be 20 bytes, got {}", bytes.len()))
+        })?;
+        Ok(Self(bytes))
+    }
+
+    /// Derives the address of an uncompressed secp256k1 public key
+    /// (65 bytes with the 0x04 prefix, or the 64-byte coordinates).
+    pub fn from_public_key(public_key: &[u8]) -> Result<Self> {
+        let coordinates = match public_key {
+            [0x04, rest @ ..] if rest.len() == 64 => rest,
+            _ if public_key.len() == 64 => public_key,
+            _ => {
+                return Err(WalletError::InvalidInput(
+                    "public key must be uncompressed".to_string(),
+                ))
+            }
+        };
+        Self::from_slice(&keccak256(coordinates)[12..])
+    }
+
+    /// Returns the raw address bytes.
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// Returns the raw address bytes as a slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the `0x`-prefixed EIP-55 checksummed hex encoding.
+    pub fn to_checksum(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = keccak256(lower.as_bytes());
+        let mut out = String::with_capacity(42);
+        out.push_str("0x");
+        for (i, c) in lower.chars().enumerate() {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                out.push(c.to_ascii_uppercase());
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Returns the CAIP-10 account id of this address on an EVM chain.
+    pub fn to_caip10(&self, chain_id: &CaipChainId) -> CaipAccountId {
+        CaipAccountId::new(chain_id.clone(), self.to_checksum())
+            .expect("checksummed addresses are valid CAIP-10 account addresses")
+    }
+
+    /// Parses the address of an `eip155` CAIP-10 account id.
+    pub fn from_caip10(account: &CaipAccountId) -> Result<Self> {
+        if account.chain_id().namespace() != EIP155_NAMESPACE {
+            return Err(WalletError::InvalidInput(format!(
+                "account {account} is not an eip155 account"
+            )));
+        }
+        account.address().parse()
+    }
+}
+
+impl FromStr for Address {
+    type Err = WalletError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let digits = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .ok_or_else(|| {
+                WalletError::InvalidInput(format!("address {value} must start with 0x"))
+            })?;
+        if digits.len() != 40 {
+            return Err(WalletError::InvalidInput(format!(
+                "address {value} must have 40 hex digits"
+            )));
+        }
+        let bytes = hex::decode(digits)
+            .map_err(|_| WalletError::InvalidInput(format!("address {value} is not valid hex")))?;
+        let address = Self::from_slice(&bytes)?;
+        let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper && address.to_checksum()[2..] != *digits {
+            return Err(WalletError::InvalidInput(format!(
+                "address {value} has an invalid EIP-55 checksum"
+            )));
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl From<[u8; 20]> for Address {
+    fn from(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Address> for [u8; 20] {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_checksum())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_eip55_vectors() {
+        for expected in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let address: Address = expected.parse().expect("valid address");
+            assert_eq!(address.to_string(), expected);
+            let lower: Address = expected.to_lowercase().parse().expect("lowercase address");
+            assert_eq!(lower, address);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_checksums_and_lengths() {
+        assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+            .parse::<Address>()
+            .is_err());
+        assert!("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            .parse::<Address>()
+            .is_err());
+        assert!("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea"
+            .parse::<Address>()
+            .is_err());
+    }
+
+    #[test]
+    fn converts_caip10_account_ids() {
+        let address: Address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            .parse()
+            .expect("valid address");
+        let account = address.to_caip10(&CaipChainId::eip155(10));
+        assert_eq!(
+            account.to_string(),
+            "eip155:10:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert_eq!(Address::from_caip10(&account).expect("address"), address);
+        let other: CaipAccountId =
+            "cosmos:cosmoshub-3:cosmos1t2uflqwqe0fsj0shcfkrvpukewcw40yjj6hdc0"
+                .parse()
+                .expect("cosmos account");
+        assert!(Address::from_caip10(&other).is_err());
+    }
+
+    #[test]
+    fn serializes_as_checksummed_string() {
+        let address: Address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            .parse()
+            .expect("valid address");
+        let json = serde_json::to_string(&address).expect("serialize");
+        assert_eq!(json, "\"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed\"");
+        assert_eq!(
+            serde_json::from_str::<Address>(&json).expect("deserialize"),
+            address
+        );
+    }
+}
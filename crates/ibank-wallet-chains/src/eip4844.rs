@@ -0,0 +1,516 @@
+//! EIP-4844 blob transactions and network-form sidecars.
+
+use ibank_wallet_core::{Result, WalletError};
+use rlp::Rlp;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::address::Address;
+use crate::evm::{
+    append_access_list, append_u256, decode_address, decode_at, decode_envelope, decode_item,
+    decode_quantity, item_at, keccak256, with_type_prefix, AccessList,
+};
+use crate::quantity::U256;
+use crate::typed_tx::{EvmSignedTypedTx, EvmTypedTx};
+
+/// EIP-2718 type byte of EIP-4844 transactions.
+pub const EIP4844_TX_TYPE: u8 = 0x03;
+
+/// Size of a blob in bytes (4096 field elements of 32 bytes).
+pub const BYTES_PER_BLOB: usize = 131_072;
+
+/// Blob gas consumed by each blob.
+pub const GAS_PER_BLOB: u64 = 131_072;
+
+/// Version byte of KZG commitment versioned hashes.
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// Unsigned EIP-4844 (type 3) blob transaction.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct EvmEip4844Tx {
+    /// Chain id for replay protection.
+    pub chain_id: u64,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Max priority fee per gas (aka tip).
+    #[serde(with = "crate::quantity")]
+    pub max_priority_fee_per_gas: U256,
+    /// Max fee per gas.
+    #[serde(with = "crate::quantity")]
+    pub max_fee_per_gas: U256,
+    /// Gas limit.
+    #[serde(with = "crate::quantity")]
+    pub gas_limit: U256,
+    /// Recipient address; blob transactions cannot create contracts.
+    pub to: Address,
+    /// Value transferred in wei.
+    #[serde(with = "crate::quantity")]
+    pub value: U256,
+    /// Call data.
+    pub data: Vec<u8>,
+    /// Access list.
+    pub access_list: AccessList,
+    /// Max fee per blob gas.
+    #[serde(with = "crate::quantity")]
+    pub max_fee_per_blob_gas: U256,
+    /// Versioned hashes of the blob KZG commitments.
+    pub blob_versioned_hashes: Vec<[u8; 32]>,
+}
+
+impl EvmEip4844Tx {
+    /// Builds the EIP-4844 signing payload bytes: 0x03 || rlp([...]).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(11);
+        self.append_fields(&mut stream);
+        with_type_prefix(EIP4844_TX_TYPE, &stream.out())
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_payload_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    /// Returns the blob gas consumed by the transaction.
+    pub fn blob_gas(&self) -> u64 {
+        GAS_PER_BLOB * self.blob_versioned_hashes.len() as u64
+    }
+
+    pub(crate) fn append_fields(&self, stream: &mut rlp::RlpStream) {
+        stream.append(&self.chain_id);
+        stream.append(&self.nonce);
+        append_u256(stream, &self.max_priority_fee_per_gas);
+        append_u256(stream, &self.max_fee_per_gas);
+        append_u256(stream, &self.gas_limit);
+        stream.append(&self.to.as_slice());
+        append_u256(stream, &self.value);
+        stream.append(&self.data.as_slice());
+        append_access_list(stream, &self.access_list);
+        append_u256(stream, &self.max_fee_per_blob_gas);
+        stream.begin_list(self.blob_versioned_hashes.len());
+        for hash in &self.blob_versioned_hashes {
+            stream.append(&hash.as_slice());
+        }
+    }
+
+    pub(crate) fn decode_fields(rlp: &Rlp) -> Result<Self> {
+        let hashes = item_at(rlp, 10)?;
+        if !hashes.is_list() {
+            return Err(WalletError::InvalidInput(
+                "blob versioned hashes must be an rlp list".to_string(),
+            ));
+        }
+        let mut blob_versioned_hashes = Vec::new();
+        for hash in hashes.iter() {
+            let bytes: Vec<u8> = decode_item(&hash)?;
+            let hash: [u8; 32] = bytes.as_slice().try_into().map_err(|_| {
+                WalletError::InvalidInput("blob versioned hash must be 32 bytes".to_string())
+            })?;
+            blob_versioned_hashes.push(hash);
+        }
+        Ok(Self {
+            chain_id: decode_at(rlp, 0)?,
+            nonce: decode_at(rlp, 1)?,
+            max_priority_fee_per_gas: decode_quantity(&item_at(rlp, 2)?)?,
+            max_fee_per_gas: decode_quantity(&item_at(rlp, 3)?)?,
+            gas_limit: decode_quantity(&item_at(rlp, 4)?)?,
+            to: decode_address(&item_at(rlp, 5)?)?,
+            value: decode_quantity(&item_at(rlp, 6)?)?,
+            data: decode_at(rlp, 7)?,
+            access_list: AccessList::decode_rlp(&item_at(rlp, 8)?)?,
+            max_fee_per_blob_gas: decode_quantity(&item_at(rlp, 9)?)?,
+            blob_versioned_hashes,
+        })
+    }
+}
+
+/// Computes the versioned hash of a KZG commitment:
+/// `0x01 || sha256(commitment)[1..]`.
+pub fn kzg_to_versioned_hash(commitment: &[u8; 48]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&Sha256::digest(commitment));
+    out[0] = VERSIONED_HASH_VERSION_KZG;
+    out
+}
+
+/// Blobs with their KZG commitments and proofs, carried next to a blob
+/// transaction when it is gossiped or submitted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlobSidecar {
+    /// Blob contents, each exactly [`BYTES_PER_BLOB`] bytes.
+    pub blobs: Vec<Vec<u8>>,
+    /// KZG commitment per blob.
+    pub commitments: Vec<[u8; 48]>,
+    /// KZG blob proof per blob.
+    pub proofs: Vec<[u8; 48]>,
+}
+
+impl BlobSidecar {
+    /// Creates a sidecar from precomputed commitments and proofs.
+    pub fn new(
+        blobs: Vec<Vec<u8>>,
+        commitments: Vec<[u8; 48]>,
+        proofs: Vec<[u8; 48]>,
+    ) -> Result<Self> {
+        let sidecar = Self {
+            blobs,
+            commitments,
+            proofs,
+        };
+        sidecar.check_shape()?;
+        Ok(sidecar)
+    }
+
+    /// Computes commitments and proofs for the blobs using the embedded
+    /// Ethereum trusted setup.
+    #[cfg(feature = "kzg")]
+    pub fn from_blobs(blobs: Vec<Vec<u8>>) -> Result<Self> {
+        let (commitments, proofs) = with_kzg_stack(|| {
+            let settings = c_kzg::ethereum_kzg_settings(0);
+            let mut commitments = Vec::with_capacity(blobs.len());
+            let mut proofs = Vec::with_capacity(blobs.len());
+            for blob in &blobs {
+                let blob = to_kzg_blob(blob)?;
+                let commitment = settings
+                    .blob_to_kzg_commitment(&blob)
+                    .map_err(kzg_error)?
+                    .to_bytes();
+                let proof = settings
+                    .compute_blob_kzg_proof(&blob, &commitment)
+                    .map_err(kzg_error)?
+                    .to_bytes();
+                commitments.push(commitment.into_inner());
+                proofs.push(proof.into_inner());
+            }
+            Ok((commitments, proofs))
+        })?;
+        Self::new(blobs, commitments, proofs)
+    }
+
+    /// Verifies every blob proof against its commitment.
+    #[cfg(feature = "kzg")]
+    pub fn verify(&self) -> Result<()> {
+        self.check_shape()?;
+        let valid = with_kzg_stack(|| {
+            let settings = c_kzg::ethereum_kzg_settings(0);
+            let blobs = self
+                .blobs
+                .iter()
+                .map(|blob| to_kzg_blob(blob))
+                .collect::<Result<Vec<_>>>()?;
+            let commitments: Vec<c_kzg::Bytes48> =
+                self.commitments.iter().map(|c| (*c).into()).collect();
+            let proofs: Vec<c_kzg::Bytes48> = self.proofs.iter().map(|p| (*p).into()).collect();
+            settings
+                .verify_blob_kzg_proof_batch(&blobs, &commitments, &proofs)
+                .map_err(kzg_error)
+        })?;
+        if !valid {
+            return Err(WalletError::InvalidInput(
+                "blob kzg proof verification failed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the versioned hash of every commitment.
+    pub fn versioned_hashes(&self) -> Vec<[u8; 32]> {
+        self.commitments.iter().map(kzg_to_versioned_hash).collect()
+    }
+
+    fn check_shape(&self) -> Result<()> {
+        if self.blobs.len() != self.commitments.len() || self.blobs.len() != self.proofs.len() {
+            return Err(WalletError::InvalidInput(
+                "sidecar must hold one commitment and proof per blob".to_string(),
+            ));
+        }
+        if self.blobs.iter().any(|blob| blob.len() != BYTES_PER_BLOB) {
+            return Err(WalletError::InvalidInput(format!(
+                "blobs must be {BYTES_PER_BLOB} bytes"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Signed blob transaction in network form, as accepted by
+/// `eth_sendRawTransaction`: 0x03 || rlp([tx_payload_body, blobs, commitments, proofs]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvmBlobTxWithSidecar {
+    /// The signed type 3 transaction.
+    pub tx: EvmSignedTypedTx,
+    /// Blobs, commitments and proofs referenced by the transaction.
+    pub sidecar: BlobSidecar,
+}
+
+impl EvmBlobTxWithSidecar {
+    /// Pairs a signed blob transaction with its sidecar, checking that the
+    /// versioned hashes match the commitments.
+    pub fn new(tx: EvmSignedTypedTx, sidecar: BlobSidecar) -> Result<Self> {
+        let EvmTypedTx::Eip4844(blob_tx) = &tx.tx else {
+            return Err(WalletError::InvalidInput(
+                "sidecar requires an EIP-4844 transaction".to_string(),
+            ));
+        };
+        sidecar.check_shape()?;
+        if blob_tx.blob_versioned_hashes != sidecar.versioned_hashes() {
+            return Err(WalletError::InvalidInput(
+                "blob versioned hashes do not match sidecar commitments".to_string(),
+            ));
+        }
+        Ok(Self { tx, sidecar })
+    }
+
+    /// Encodes the network form of the transaction.
+    pub fn encode(&self) -> Vec<u8> {
+        let canonical = self.tx.encode();
+        let mut stream = rlp::RlpStream::new_list(4);
+        stream.append_raw(&canonical[1..], 1);
+        stream.begin_list(self.sidecar.blobs.len());
+        for blob in &self.sidecar.blobs {
+            stream.append(&blob.as_slice());
+        }
+        stream.begin_list(self.sidecar.commitments.len());
+        for commitment in &self.sidecar.commitments {
+            stream.append(&commitment.as_slice());
+        }
+        stream.begin_list(self.sidecar.proofs.len());
+        for proof in &self.sidecar.proofs {
+            stream.append(&proof.as_slice());
+        }
+        with_type_prefix(EIP4844_TX_TYPE, &stream.out())
+    }
+
+    /// Decodes the network form of a blob transaction.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let rlp = decode_envelope(bytes, EIP4844_TX_TYPE, 4)?;
+        let body = item_at(&rlp, 0)?;
+        let mut canonical = Vec::with_capacity(1 + body.as_raw().len());
+        canonical.push(EIP4844_TX_TYPE);
+        canonical.extend_from_slice(body.as_raw());
+        let tx = EvmSignedTypedTx::decode(&canonical)?;
+
+        let blobs = decode_byte_list(&item_at(&rlp, 1)?)?;
+        let commitments = decode_byte_list(&item_at(&rlp, 2)?)?
+            .into_iter()
+            .map(to_bytes48)
+            .collect::<Result<Vec<_>>>()?;
+        let proofs = decode_byte_list(&item_at(&rlp, 3)?)?
+            .into_iter()
+            .map(to_bytes48)
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(tx, BlobSidecar::new(blobs, commitments, proofs)?)
+    }
+}
+
+fn decode_byte_list(rlp: &Rlp) -> Result<Vec<Vec<u8>>> {
+    if !rlp.is_list() {
+        return Err(WalletError::InvalidInput(
+            "sidecar fields must be rlp lists".to_string(),
+        ));
+    }
+    rlp.iter().map(|item| decode_item(&item)).collect()
+}
+
+fn to_bytes48(bytes: Vec<u8>) -> Result<[u8; 48]> {
+    bytes.as_slice().try_into().map_err(|_| {
+        WalletError::InvalidInput("kzg commitments and proofs must be 48 bytes".to_string())
+    })
+}
+
+#[cfg(feature = "kzg")]
+fn to_kzg_blob(blob: &[u8]) -> Result<c_kzg::Blob> {
+    c_kzg::Blob::from_bytes(blob).map_err(kzg_error)
+}
+
+/// Stack size for KZG work; the backend keeps several blob-sized values on
+/// the stack, more than the 2 MiB of test and async runtime worker threads.
+#[cfg(feature = "kzg")]
+const KZG_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Runs `f` on a scoped thread with [`KZG_STACK_SIZE`] of stack, so callers
+/// may be on any thread.
+#[cfg(feature = "kzg")]
+fn with_kzg_stack<T: Send>(f: impl FnOnce() -> Result<T> + Send) -> Result<T> {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("kzg".to_string())
+            .stack_size(KZG_STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .map_err(|err| WalletError::InvalidInput(format!("kzg thread: {err}")))?
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+#[cfg(feature = "kzg")]
+fn kzg_error(err: c_kzg::Error) -> WalletError {
+    WalletError::InvalidInput(format!("kzg error: {err:?}"))
+}
+
+/// Helper builder for EIP-4844 blob transactions.
+#[derive(Clone, Debug, Default)]
+pub struct EvmEip4844TxBuilder {
+    tx: EvmEip4844Tx,
+}
+
+impl EvmEip4844TxBuilder {
+    /// Creates a new builder with required fields.
+    pub fn new(chain_id: u64, nonce: u64, to: Address) -> Self {
+        Self {
+            tx: EvmEip4844Tx {
+                chain_id,
+                nonce,
+                to,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets max priority fee per gas.
+    pub fn max_priority_fee_per_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_priority_fee_per_gas = value.into();
+        self
+    }
+
+    /// Sets max fee per gas.
+    pub fn max_fee_per_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_fee_per_gas = value.into();
+        self
+    }
+
+    /// Sets gas limit.
+    pub fn gas_limit(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_limit = value.into();
+        self
+    }
+
+    /// Sets value.
+    pub fn value(mut self, value: impl Into<U256>) -> Self {
+        self.tx.value = value.into();
+        self
+    }
+
+    /// Sets data.
+    pub fn data(mut self, value: Vec<u8>) -> Self {
+        self.tx.data = value;
+        self
+    }
+
+    /// Sets access list.
+    pub fn access_list(mut self, value: AccessList) -> Self {
+        self.tx.access_list = value;
+        self
+    }
+
+    /// Sets max fee per blob gas.
+    pub fn max_fee_per_blob_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_fee_per_blob_gas = value.into();
+        self
+    }
+
+    /// Sets the blob versioned hashes.
+    pub fn blob_versioned_hashes(mut self, value: Vec<[u8; 32]>) -> Self {
+        self.tx.blob_versioned_hashes = value;
+        self
+    }
+
+    /// Sets the blob versioned hashes from the sidecar commitments.
+    pub fn sidecar(mut self, sidecar: &BlobSidecar) -> Self {
+        self.tx.blob_versioned_hashes = sidecar.versioned_hashes();
+        self
+    }
+
+    /// Returns the built transaction.
+    pub fn build(self) -> EvmEip4844Tx {
+        self.tx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Commitment to the all-zero blob (the point at infinity).
+    fn zero_commitment() -> [u8; 48] {
+        let mut commitment = [0u8; 48];
+        commitment[0] = 0xc0;
+        commitment
+    }
+
+    fn zero_blob_sidecar() -> BlobSidecar {
+        BlobSidecar::new(
+            vec![vec![0u8; BYTES_PER_BLOB]],
+            vec![zero_commitment()],
+            vec![zero_commitment()],
+        )
+        .expect("sidecar")
+    }
+
+    #[test]
+    fn computes_versioned_hash() {
+        let expected =
+            hex::decode("010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014")
+                .expect("valid hex");
+        assert_eq!(
+            kzg_to_versioned_hash(&zero_commitment()).as_slice(),
+            expected.as_slice()
+        );
+    }
+
+    #[test]
+    fn network_form_round_trips() {
+        let sidecar = zero_blob_sidecar();
+        let tx = EvmEip4844TxBuilder::new(1, 0, Address([0x11; 20]))
+            .max_priority_fee_per_gas(1)
+            .max_fee_per_gas(2)
+            .gas_limit(21_000)
+            .max_fee_per_blob_gas(3)
+            .sidecar(&sidecar)
+            .build();
+        assert_eq!(tx.blob_gas(), GAS_PER_BLOB);
+
+        let mut signature = [0x01u8; 65];
+        signature[64] = 0;
+        let signed =
+            EvmSignedTypedTx::from_signature(EvmTypedTx::Eip4844(tx), &signature).expect("signed");
+        assert_eq!(
+            EvmSignedTypedTx::decode(&signed.encode()).expect("decoded"),
+            signed
+        );
+
+        let network = EvmBlobTxWithSidecar::new(signed, sidecar).expect("network form");
+        let raw = network.encode();
+        assert_eq!(raw.first().copied(), Some(EIP4844_TX_TYPE));
+        assert_eq!(
+            EvmBlobTxWithSidecar::decode(&raw).expect("decoded"),
+            network
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_sidecar() {
+        let tx = EvmEip4844TxBuilder::new(1, 0, Address([0x11; 20]))
+            .blob_versioned_hashes(vec![[0x01; 32]])
+            .build();
+        let signed =
+            EvmSignedTypedTx::from_signature(EvmTypedTx::Eip4844(tx), &[0u8; 65]).expect("signed");
+        assert!(matches!(
+            EvmBlobTxWithSidecar::new(signed, zero_blob_sidecar()),
+            Err(WalletError::InvalidInput(_))
+        ));
+    }
+
+    #[cfg(feature = "kzg")]
+    #[test]
+    fn computes_and_verifies_kzg_proofs() {
+        // Runs on the default 2 MiB test thread stack.
+        let mut blob = vec![0u8; BYTES_PER_BLOB];
+        blob[31] = 0x2a;
+        let sidecar = BlobSidecar::from_blobs(vec![blob]).expect("sidecar");
+        sidecar.verify().expect("valid proofs");
+
+        let mut tampered = sidecar.clone();
+        tampered.blobs[0][63] = 0x01;
+        assert!(tampered.verify().is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! EIP-712 typed structured data hashing (`eth_signTypedData_v4`).
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ibank_wallet_core::{Result, WalletError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::evm::keccak256;
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// Fields of the `EIP712Domain` struct in canonical order with their types.
+const DOMAIN_FIELDS: [(&str, &str); 5] = [
+    ("name", "string"),
+    ("version", "string"),
+    ("chainId", "uint256"),
+    ("verifyingContract", "address"),
+    ("salt", "bytes32"),
+];
+
+/// A single member of an EIP-712 struct type.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TypedDataField {
+    /// Member name.
+    pub name: String,
+    /// Solidity type of the member (e.g. `address`, `Person[]`).
+    #[serde(rename = "type")]
+    pub r#type: String,
+}
+
+/// An EIP-712 typed data document as accepted by `eth_signTypedData_v4`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TypedData {
+    /// Struct type definitions keyed by type name.
+    pub types: BTreeMap<String, Vec<TypedDataField>>,
+    /// Name of the struct type of `message`.
+    #[serde(rename = "primaryType")]
+    pub primary_type: String,
+    /// Domain values.
+    pub domain: Map<String, Value>,
+    /// Message values.
+    pub message: Map<String, Value>,
+}
+
+impl TypedData {
+    /// Parses a typed data document from its JSON representation.
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|err| WalletError::InvalidInput(format!("invalid typed data: {err}")))
+    }
+
+    /// Returns the EIP-712 signing hash:
+    /// keccak256(0x19 0x01 || domainSeparator || hashStruct(message)).
+    pub fn signing_hash(&self) -> Result<[u8; 32]> {
+        let mut buffer = Vec::with_capacity(66);
+        buffer.extend_from_slice(&[0x19, 0x01]);
+        buffer.extend_from_slice(&self.domain_separator()?);
+        if self.primary_type != DOMAIN_TYPE {
+            buffer.extend_from_slice(&self.hash_struct(&self.primary_type, &self.message)?);
+        }
+        Ok(keccak256(&buffer))
+    }
+
+    /// Returns `hashStruct(EIP712Domain, domain)`.
+    pub fn domain_separator(&self) -> Result<[u8; 32]> {
+        self.hash_struct(DOMAIN_TYPE, &self.domain)
+    }
+
+    /// Returns `hashStruct(type_name, data)`.
+    pub fn hash_struct(&self, type_name: &str, data: &Map<String, Value>) -> Result<[u8; 32]> {
+        Ok(keccak256(&self.encode_data(type_name, data)?))
+    }
+
+    /// Returns `keccak256(encodeType(type_name))`.
+    pub fn type_hash(&self, type_name: &str) -> Result<[u8; 32]> {
+        Ok(keccak256(self.encode_type(type_name)?.as_bytes()))
+    }
+
+    /// Returns `encodeType(type_name)`: the struct followed by its referenced
+    /// struct types sorted by name.
+    pub fn encode_type(&self, type_name: &str) -> Result<String> {
+        let mut dependencies = BTreeSet::new();
+        self.collect_dependencies(type_name, &mut dependencies)?;
+        dependencies.remove(type_name);
+
+        let mut out = String::new();
+        for name in std::iter::once(type_name).chain(dependencies.iter().map(String::as_str)) {
+            let fields = self.fields(name)?;
+            let members: Vec<String> = fields
+                .iter()
+                .map(|field| format!("{} {}", field.r#type, field.name))
+                .collect();
+            out.push_str(&format!("{}({})", name, members.join(",")));
+        }
+        Ok(out)
+    }
+
+    /// Returns `typeHash || encodeData(data)` for the struct.
+    pub fn encode_data(&self, type_name: &str, data: &Map<String, Value>) -> Result<Vec<u8>> {
+        let fields = self.fields(type_name)?;
+        let mut out = Vec::with_capacity(32 * (fields.len() + 1));
+        out.extend_from_slice(&self.type_hash(type_name)?);
+        for field in fields.iter() {
+            let value = data.get(&field.name).unwrap_or(&Value::Null);
+            out.extend_from_slice(&self.encode_value(&field.r#type, value)?);
+        }
+        Ok(out)
+    }
+
+    fn fields(&self, type_name: &str) -> Result<Vec<TypedDataField>> {
+        if let Some(fields) = self.types.get(type_name) {
+            return Ok(fields.clone());
+        }
+        if type_name == DOMAIN_TYPE {
+            return Ok(self.inferred_domain_fields());
+        }
+        Err(WalletError::InvalidInput(format!(
+            "undefined typed data type: {type_name}"
+        )))
+    }
+
+    /// Derives the `EIP712Domain` members from the domain values when the
+    /// document omits the type definition.
+    fn inferred_domain_fields(&self) -> Vec<TypedDataField> {
+        DOMAIN_FIELDS
+            .iter()
+            .filter(|(name, _)| self.domain.contains_key(*name))
+            .map(|(name, r#type)| TypedDataField {
+                name: name.to_string(),
+                r#type: r#type.to_string(),
+            })
+            .collect()
+    }
+
+    fn collect_dependencies(&self, type_name: &str, found: &mut BTreeSet<String>) -> Result<()> {
+        let base = strip_array_suffixes(type_name);
+        if found.contains(base) || !self.is_struct(base) {
+            return Ok(());
+        }
+        found.insert(base.to_string());
+        for field in self.fields(base)? {
+            self.collect_dependencies(&field.r#type, found)?;
+        }
+        Ok(())
+    }
+
+    fn is_struct(&self, type_name: &str) -> bool {
+        self.types.contains_key(type_name) || type_name == DOMAIN_TYPE
+    }
+
+    fn encode_value(&self, type_name: &str, value: &Value) -> Result<[u8; 32]> {
+        if let Some((element_type, length)) = split_array_type(type_name) {
+            let items = value.as_array().ok_or_else(|| {
+                WalletError::InvalidInput(format!("expected array for {type_name}"))
+            })?;
+            if let Some(length) = length {
+                if items.len() != length {
+                    return Err(WalletError::InvalidInput(format!(
+                        "expected {length} items for {type_name}, got {}",
+                        items.len()
+                    )));
+                }
+            }
+            let mut buffer = Vec::with_capacity(32 * items.len());
+            for item in items {
+                buffer.extend_from_slice(&self.encode_value(element_type, item)?);
+            }
+            return Ok(keccak256(&buffer));
+        }
+
+        if self.types.contains_key(type_name) {
+            let data = value.as_object().ok_or_else(|| {
+                WalletError::InvalidInput(format!("expected object for {type_name}"))
+            })?;
+            return self.hash_struct(type_name, data);
+        }
+
+        encode_atomic(type_name, value)
+    }
+}
+
+/// Encodes an atomic or dynamic (non-struct, non-array) value to its 32-byte word.
+fn encode_atomic(type_name: &str, value: &Value) -> Result<[u8; 32]> {
+    match type_name {
+        "string" => {
+            let text = value
+                .as_str()
+                .ok_or_else(|| invalid_value(type_name, value))?;
+            Ok(keccak256(text.as_bytes()))
+        }
+        "bytes" => Ok(keccak256(&parse_bytes(type_name, value)?)),
+        "bool" => {
+            let flag = match value {
+                Value::Bool(flag) => *flag,
+                Value::String(text) if text == "true" => true,
+                Value::String(text) if text == "false" => false,
+                _ => return Err(invalid_value(type_name, value)),
+            };
+            let mut out = [0u8; 32];
+            out[31] = flag as u8;
+            Ok(out)
+        }
+        "address" => {
+            let bytes = parse_bytes(type_name, value)?;
+            if bytes.len() != 20 {
+                return Err(invalid_value(type_name, value));
+            }
+            let mut out = [0u8; 32];
+            out[12..].copy_from_slice(&bytes);
+            Ok(out)
+        }
+        _ => {
+            if let Some(size) = type_name.strip_prefix("bytes") {
+                let size = parse_size(type_name, size, 1, 32, 1)?;
+                let bytes = parse_bytes(type_name, value)?;
+                if bytes.len() != size {
+                    return Err(invalid_value(type_name, value));
+                }
+                let mut out = [0u8; 32];
+                out[..size].copy_from_slice(&bytes);
+                return Ok(out);
+            }
+            if let Some(bits) = type_name.strip_prefix("uint") {
+                let bits = parse_size(type_name, bits, 8, 256, 8)?;
+                let (negative, magnitude) = parse_integer(type_name, value)?;
+                if negative && magnitude != [0u8; 32] {
+                    return Err(invalid_value(type_name, value));
+                }
+                if !fits_unsigned(&magnitude, bits) {
+                    return Err(invalid_value(type_name, value));
+                }
+                return Ok(magnitude);
+            }
+            if let Some(bits) = type_name.strip_prefix("int") {
+                let bits = parse_size(type_name, bits, 8, 256, 8)?;
+                let (negative, magnitude) = parse_integer(type_name, value)?;
+                if !fits_signed(&magnitude, negative, bits) {
+                    return Err(invalid_value(type_name, value));
+                }
+                return Ok(if negative {
+                    twos_complement(&magnitude)
+                } else {
+                    magnitude
+                });
+            }
+            Err(WalletError::InvalidInput(format!(
+                "unsupported typed data type: {type_name}"
+            )))
+        }
+    }
+}
+
+fn invalid_value(type_name: &str, value: &Value) -> WalletError {
+    WalletError::InvalidInput(format!("invalid {type_name} value: {value}"))
+}
+
+fn strip_array_suffixes(type_name: &str) -> &str {
+    type_name.split('[').next().unwrap_or(type_name)
+}
+
+/// Splits `T[]` / `T[n]` into the element type and optional fixed length.
+fn split_array_type(type_name: &str) -> Option<(&str, Option<usize>)> {
+    let inner = type_name.strip_suffix(']')?;
+    let open = inner.rfind('[')?;
+    let length = &inner[open + 1..];
+    let length = if length.is_empty() {
+        None
+    } else {
+        Some(length.parse().ok()?)
+    };
+    Some((&inner[..open], length))
+}
+
+fn parse_size(type_name: &str, size: &str, min: usize, max: usize, step: usize) -> Result<usize> {
+    if size.is_empty() && max == 256 {
+        return Ok(256);
+    }
+    match size.parse::<usize>() {
+        Ok(parsed)
+            if (min..=max).contains(&parsed) && parsed % step == 0 && !size.starts_with('0') =>
+        {
+            Ok(parsed)
+        }
+        _ => Err(WalletError::InvalidInput(format!(
+            "unsupported typed data type: {type_name}"
+        ))),
+    }
+}
+
+fn parse_bytes(type_name: &str, value: &Value) -> Result<Vec<u8>> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| invalid_value(type_name, value))?;
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .ok_or_else(|| invalid_value(type_name, value))?;
+    hex::decode(digits).map_err(|_| invalid_value(type_name, value))
+}
+
+/// Parses a JSON number or decimal/`0x` hex string into a sign and 256-bit magnitude.
+fn parse_integer(type_name: &str, value: &Value) -> Result<(bool, [u8; 32])> {
+    let text = match value {
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => text.trim().to_string(),
+        _ => return Err(invalid_value(type_name, value)),
+    };
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.as_str()),
+    };
+
+    let mut out = [0u8; 32];
+    let (radix, digits) = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex_digits) => (16u32, hex_digits),
+        None => (10u32, digits),
+    };
+    if digits.is_empty() {
+        return Err(invalid_value(type_name, value));
+    }
+    for c in digits.chars() {
+        let digit = c
+            .to_digit(radix)
+            .ok_or_else(|| invalid_value(type_name, value))?;
+        let mut carry = digit;
+        for byte in out.iter_mut().rev() {
+            let next = (*byte as u32) * radix + carry;
+            *byte = (next & 0xff) as u8;
+            carry = next >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid_value(type_name, value));
+        }
+    }
+    Ok((negative, out))
+}
+
+fn fits_unsigned(magnitude: &[u8; 32], bits: usize) -> bool {
+    let used = 32 - bits / 8;
+    magnitude[..used].iter().all(|byte| *byte == 0)
+}
+
+fn fits_signed(magnitude: &[u8; 32], negative: bool, bits: usize) -> bool {
+    // |min| = 2^(bits-1), max = 2^(bits-1) - 1.
+    let mut limit = [0u8; 32];
+    let bit = bits - 1;
+    limit[31 - bit / 8] = 1 << (bit % 8);
+    if negative {
+        magnitude <= &limit
+    } else {
+        magnitude < &limit
+    }
+}
+
+fn twos_complement(magnitude: &[u8; 32]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    let mut carry = 1u16;
+    for i in (0..32).rev() {
+        let next = (!magnitude[i]) as u16 + carry;
+        out[i] = (next & 0xff) as u8;
+        carry = next >> 8;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallet", "type": "address"}
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "string"}
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+            "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    fn hex32(value: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&hex::decode(value).expect("valid hex"));
+        out
+    }
+
+    #[test]
+    fn mail_example_matches_eip_vectors() {
+        let typed = TypedData::from_json(MAIL).expect("typed data");
+        assert_eq!(
+            typed.encode_type("Mail").expect("encode type"),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            typed.type_hash("Mail").expect("type hash"),
+            hex32("a0cedeb2dc280ba39b857546d74f5549c3a1d7bdc2dd96bf881f76108e23dac2")
+        );
+        assert_eq!(
+            typed.domain_separator().expect("domain separator"),
+            hex32("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        assert_eq!(
+            typed
+                .hash_struct("Mail", &typed.message)
+                .expect("hash struct"),
+            hex32("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+        );
+        assert_eq!(
+            typed.signing_hash().expect("signing hash"),
+            hex32("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn infers_domain_type_when_omitted() {
+        let mut typed = TypedData::from_json(MAIL).expect("typed data");
+        typed.types.remove(DOMAIN_TYPE);
+        assert_eq!(
+            typed.domain_separator().expect("domain separator"),
+            hex32("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+    }
+
+    #[test]
+    fn encodes_arrays_and_signed_integers() {
+        let typed = TypedData::from_json(
+            r#"{
+                "types": {
+                    "Group": [
+                        {"name": "members", "type": "Person[]"},
+                        {"name": "scores", "type": "int8[2]"}
+                    ],
+                    "Person": [{"name": "wallets", "type": "address[]"}]
+                },
+                "primaryType": "Group",
+                "domain": {"chainId": "0x1"},
+                "message": {
+                    "members": [{"wallets": ["0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"]}],
+                    "scores": [-128, 127]
+                }
+            }"#,
+        )
+        .expect("typed data");
+        assert_eq!(
+            typed.encode_type("Group").expect("encode type"),
+            "Group(Person[] members,int8[2] scores)Person(address[] wallets)"
+        );
+        typed.signing_hash().expect("signing hash");
+
+        let minus_one = encode_atomic("int256", &Value::from(-1)).expect("int256");
+        assert_eq!(minus_one, [0xff; 32]);
+        assert!(encode_atomic("int8", &Value::from(-129)).is_err());
+        assert!(encode_atomic("uint8", &Value::from(256)).is_err());
+    }
+}
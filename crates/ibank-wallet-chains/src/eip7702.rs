@@ -0,0 +1,328 @@
+//! EIP-7702 set-code authorizations and type 4 transactions.
+
+use ibank_wallet_core::{Result, WalletError};
+use rlp::Rlp;
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+use crate::evm::{
+    append_access_list, append_u256, decode_address, decode_at, decode_quantity, decode_word,
+    expect_list_len, item_at, keccak256, split_signature, trim_leading_zeros, with_type_prefix,
+    AccessList, EvmUnsignedTx,
+};
+use crate::quantity::U256;
+
+/// EIP-2718 type byte of EIP-7702 transactions.
+pub const EIP7702_TX_TYPE: u8 = 0x04;
+
+/// Magic prefix of authorization signing payloads.
+pub const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// Authorization for an account to delegate its code to `address`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Authorization {
+    /// Chain id the authorization is valid on, or 0 for every chain.
+    pub chain_id: u64,
+    /// Contract whose code the account delegates to.
+    pub address: Address,
+    /// Nonce of the authorizing account.
+    pub nonce: u64,
+}
+
+impl Authorization {
+    /// Builds the signing payload: 0x05 || rlp([chain_id, address, nonce]).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(3);
+        self.append_fields(&mut stream);
+        with_type_prefix(AUTHORIZATION_MAGIC, &stream.out())
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    fn append_fields(&self, stream: &mut rlp::RlpStream) {
+        stream.append(&self.chain_id);
+        stream.append(&self.address.as_slice());
+        stream.append(&self.nonce);
+    }
+}
+
+/// Authorization with the authorizing account's signature.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedAuthorization {
+    /// The signed authorization.
+    pub authorization: Authorization,
+    /// Signature y-parity (0 or 1).
+    pub y_parity: u8,
+    /// Signature r value (big-endian, left-padded).
+    pub r: [u8; 32],
+    /// Signature s value (big-endian, left-padded).
+    pub s: [u8; 32],
+}
+
+impl SignedAuthorization {
+    /// Builds a signed authorization from a 65-byte r || s || v signature over
+    /// [`Authorization::signing_hash`], with v in {0, 1} or {27, 28}.
+    pub fn from_signature(authorization: Authorization, signature: &[u8]) -> Result<Self> {
+        let (y_parity, r, s) = split_signature(signature)?;
+        Ok(Self {
+            authorization,
+            y_parity,
+            r,
+            s,
+        })
+    }
+
+    fn append(&self, stream: &mut rlp::RlpStream) {
+        stream.begin_list(6);
+        self.authorization.append_fields(stream);
+        stream.append(&self.y_parity);
+        stream.append(&trim_leading_zeros(&self.r));
+        stream.append(&trim_leading_zeros(&self.s));
+    }
+
+    fn decode(rlp: &Rlp) -> Result<Self> {
+        expect_list_len(rlp, 6)?;
+        let y_parity: u8 = decode_at(rlp, 3)?;
+        if y_parity > 1 {
+            return Err(WalletError::InvalidInput(format!(
+                "invalid y_parity: {y_parity}"
+            )));
+        }
+        Ok(Self {
+            authorization: Authorization {
+                chain_id: decode_at(rlp, 0)?,
+                address: decode_address(&item_at(rlp, 1)?)?,
+                nonce: decode_at(rlp, 2)?,
+            },
+            y_parity,
+            r: decode_word(&item_at(rlp, 4)?)?,
+            s: decode_word(&item_at(rlp, 5)?)?,
+        })
+    }
+}
+
+/// Unsigned EIP-7702 (type 4) set-code transaction.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct EvmEip7702Tx {
+    /// Chain id for replay protection.
+    pub chain_id: u64,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Max priority fee per gas (aka tip).
+    #[serde(with = "crate::quantity")]
+    pub max_priority_fee_per_gas: U256,
+    /// Max fee per gas.
+    #[serde(with = "crate::quantity")]
+    pub max_fee_per_gas: U256,
+    /// Gas limit.
+    #[serde(with = "crate::quantity")]
+    pub gas_limit: U256,
+    /// Recipient address; set-code transactions cannot create contracts.
+    pub to: Address,
+    /// Value transferred in wei.
+    #[serde(with = "crate::quantity")]
+    pub value: U256,
+    /// Call data.
+    pub data: Vec<u8>,
+    /// Access list.
+    pub access_list: AccessList,
+    /// Signed delegations applied before execution.
+    pub authorization_list: Vec<SignedAuthorization>,
+}
+
+impl EvmEip7702Tx {
+    /// Builds the EIP-7702 signing payload bytes: 0x04 || rlp([...]).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(10);
+        self.append_fields(&mut stream);
+        with_type_prefix(EIP7702_TX_TYPE, &stream.out())
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_payload_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    /// Returns the delegation targets of the authorization list.
+    pub fn delegates(&self) -> Vec<Address> {
+        self.authorization_list
+            .iter()
+            .map(|signed| signed.authorization.address)
+            .collect()
+    }
+
+    /// Returns the call this transaction makes as an EIP-1559 transaction,
+    /// without the authorization list, for policies that inspect calls.
+    pub fn call(&self) -> EvmUnsignedTx {
+        EvmUnsignedTx {
+            chain_id: self.chain_id,
+            nonce: self.nonce,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            max_fee_per_gas: self.max_fee_per_gas,
+            gas_limit: self.gas_limit,
+            to: Some(self.to),
+            value: self.value,
+            data: self.data.clone(),
+            access_list: self.access_list.clone(),
+        }
+    }
+
+    pub(crate) fn append_fields(&self, stream: &mut rlp::RlpStream) {
+        stream.append(&self.chain_id);
+        stream.append(&self.nonce);
+        append_u256(stream, &self.max_priority_fee_per_gas);
+        append_u256(stream, &self.max_fee_per_gas);
+        append_u256(stream, &self.gas_limit);
+        stream.append(&self.to.as_slice());
+        append_u256(stream, &self.value);
+        stream.append(&self.data.as_slice());
+        append_access_list(stream, &self.access_list);
+        stream.begin_list(self.authorization_list.len());
+        for signed in &self.authorization_list {
+            signed.append(stream);
+        }
+    }
+
+    pub(crate) fn decode_fields(rlp: &Rlp) -> Result<Self> {
+        let list = item_at(rlp, 9)?;
+        if !list.is_list() {
+            return Err(WalletError::InvalidInput(
+                "authorization list must be an rlp list".to_string(),
+            ));
+        }
+        let authorization_list = list
+            .iter()
+            .map(|item| SignedAuthorization::decode(&item))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            chain_id: decode_at(rlp, 0)?,
+            nonce: decode_at(rlp, 1)?,
+            max_priority_fee_per_gas: decode_quantity(&item_at(rlp, 2)?)?,
+            max_fee_per_gas: decode_quantity(&item_at(rlp, 3)?)?,
+            gas_limit: decode_quantity(&item_at(rlp, 4)?)?,
+            to: decode_address(&item_at(rlp, 5)?)?,
+            value: decode_quantity(&item_at(rlp, 6)?)?,
+            data: decode_at(rlp, 7)?,
+            access_list: AccessList::decode_rlp(&item_at(rlp, 8)?)?,
+            authorization_list,
+        })
+    }
+}
+
+/// Helper builder for EIP-7702 transactions.
+#[derive(Clone, Debug, Default)]
+pub struct EvmEip7702TxBuilder {
+    tx: EvmEip7702Tx,
+}
+
+impl EvmEip7702TxBuilder {
+    /// Creates a new builder with required fields.
+    pub fn new(chain_id: u64, nonce: u64, to: Address) -> Self {
+        Self {
+            tx: EvmEip7702Tx {
+                chain_id,
+                nonce,
+                to,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets max priority fee per gas.
+    pub fn max_priority_fee_per_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_priority_fee_per_gas = value.into();
+        self
+    }
+
+    /// Sets max fee per gas.
+    pub fn max_fee_per_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_fee_per_gas = value.into();
+        self
+    }
+
+    /// Sets gas limit.
+    pub fn gas_limit(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_limit = value.into();
+        self
+    }
+
+    /// Sets value.
+    pub fn value(mut self, value: impl Into<U256>) -> Self {
+        self.tx.value = value.into();
+        self
+    }
+
+    /// Sets data.
+    pub fn data(mut self, value: Vec<u8>) -> Self {
+        self.tx.data = value;
+        self
+    }
+
+    /// Sets access list.
+    pub fn access_list(mut self, value: AccessList) -> Self {
+        self.tx.access_list = value;
+        self
+    }
+
+    /// Appends a signed authorization.
+    pub fn authorization(mut self, value: SignedAuthorization) -> Self {
+        self.tx.authorization_list.push(value);
+        self
+    }
+
+    /// Returns the built transaction.
+    pub fn build(self) -> EvmEip7702Tx {
+        self.tx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typed_tx::{EvmSignedTypedTx, EvmTypedTx};
+
+    #[test]
+    fn authorization_signing_payload_has_magic_prefix() {
+        let authorization = Authorization {
+            chain_id: 1,
+            address: Address([0x42; 20]),
+            nonce: 0,
+        };
+        let mut expected = vec![AUTHORIZATION_MAGIC, 0xd7, 0x01, 0x94];
+        expected.extend_from_slice(&[0x42; 20]);
+        expected.push(0x80);
+        assert_eq!(authorization.signing_payload(), expected);
+        assert_eq!(authorization.signing_hash(), keccak256(&expected));
+    }
+
+    #[test]
+    fn set_code_transaction_round_trips() {
+        let mut signature = [0x07u8; 65];
+        signature[64] = 28;
+        let authorization = SignedAuthorization::from_signature(
+            Authorization {
+                chain_id: 0,
+                address: Address([0x42; 20]),
+                nonce: 5,
+            },
+            &signature,
+        )
+        .expect("signed authorization");
+        let tx = EvmEip7702TxBuilder::new(1, 4, Address([0x99; 20]))
+            .max_priority_fee_per_gas(1)
+            .max_fee_per_gas(2)
+            .gas_limit(60_000)
+            .authorization(authorization)
+            .build();
+        assert_eq!(tx.delegates(), vec![Address([0x42; 20])]);
+
+        let signed = EvmSignedTypedTx::from_signature(EvmTypedTx::Eip7702(tx), &signature)
+            .expect("signed transaction");
+        let raw = signed.encode();
+        assert_eq!(raw.first().copied(), Some(EIP7702_TX_TYPE));
+        assert_eq!(EvmSignedTypedTx::decode(&raw).expect("decoded"), signed);
+    }
+}
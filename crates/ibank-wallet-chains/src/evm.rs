@@ -1,13 +1,21 @@
 //! EVM chain types and signing payload builder.
 
+use ibank_wallet_core::{Result, WalletError};
+use rlp::{Decodable, Rlp};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 
+use crate::address::Address;
+use crate::quantity::U256;
+
+/// EIP-2718 type byte of EIP-1559 transactions.
+pub const EIP1559_TX_TYPE: u8 = 0x02;
+
 /// An EVM access list item.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AccessListItem {
     /// The accessed address.
-    pub address: [u8; 20],
+    pub address: Address,
     /// The storage keys accessed for the address.
     pub storage_keys: Vec<[u8; 32]>,
 }
@@ -30,6 +38,38 @@ impl AccessList {
         }
         stream.out().to_vec()
     }
+
+    pub(crate) fn decode_rlp(rlp: &Rlp) -> Result<Self> {
+        if !rlp.is_list() {
+            return Err(WalletError::InvalidInput(
+                "access list must be an rlp list".to_string(),
+            ));
+        }
+        let mut items = Vec::new();
+        for item in rlp.iter() {
+            expect_list_len(&item, 2)?;
+            let address = decode_address(&item_at(&item, 0)?)?;
+            let keys = item_at(&item, 1)?;
+            if !keys.is_list() {
+                return Err(WalletError::InvalidInput(
+                    "access list storage keys must be an rlp list".to_string(),
+                ));
+            }
+            let mut storage_keys = Vec::new();
+            for key in keys.iter() {
+                let bytes: Vec<u8> = decode_item(&key)?;
+                let key: [u8; 32] = bytes.as_slice().try_into().map_err(|_| {
+                    WalletError::InvalidInput("storage key must be 32 bytes".to_string())
+                })?;
+                storage_keys.push(key);
+            }
+            items.push(AccessListItem {
+                address,
+                storage_keys,
+            });
+        }
+        Ok(Self(items))
+    }
 }
 
 /// Unsigned EVM transaction for EIP-1559 signing.
@@ -40,15 +80,19 @@ pub struct EvmUnsignedTx {
     /// Sender nonce.
     pub nonce: u64,
     /// Max priority fee per gas (aka tip).
-    pub max_priority_fee_per_gas: u128,
+    #[serde(with = "crate::quantity")]
+    pub max_priority_fee_per_gas: U256,
     /// Max fee per gas.
-    pub max_fee_per_gas: u128,
+    #[serde(with = "crate::quantity")]
+    pub max_fee_per_gas: U256,
     /// Gas limit.
-    pub gas_limit: u128,
+    #[serde(with = "crate::quantity")]
+    pub gas_limit: U256,
     /// Recipient address, or None for contract creation.
-    pub to: Option<[u8; 20]>,
+    pub to: Option<Address>,
     /// Value transferred in wei.
-    pub value: u128,
+    #[serde(with = "crate::quantity")]
+    pub value: U256,
     /// Call data.
     pub data: Vec<u8>,
     /// Access list.
@@ -59,34 +103,97 @@ impl EvmUnsignedTx {
     /// Builds the EIP-1559 signing payload bytes: 0x02 || rlp([...]).
     pub fn signing_payload(&self) -> Vec<u8> {
         let mut stream = rlp::RlpStream::new_list(9);
+        self.append_fields(&mut stream);
+        with_type_prefix(EIP1559_TX_TYPE, &stream.out())
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_payload_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    /// Encodes the access list portion as RLP.
+    pub fn access_list_rlp(&self) -> Vec<u8> {
+        self.access_list.rlp_bytes()
+    }
+
+    /// Decodes an EIP-1559 signing payload (0x02 || rlp([...])) as produced by
+    /// [`EvmUnsignedTx::signing_payload`].
+    pub fn decode_signing_payload(bytes: &[u8]) -> Result<Self> {
+        let rlp = decode_envelope(bytes, EIP1559_TX_TYPE, 9)?;
+        Self::decode_fields(&rlp)
+    }
+
+    pub(crate) fn append_fields(&self, stream: &mut rlp::RlpStream) {
         stream.append(&self.chain_id);
         stream.append(&self.nonce);
-        append_u128(&mut stream, self.max_priority_fee_per_gas);
-        append_u128(&mut stream, self.max_fee_per_gas);
-        append_u128(&mut stream, self.gas_limit);
+        append_u256(stream, &self.max_priority_fee_per_gas);
+        append_u256(stream, &self.max_fee_per_gas);
+        append_u256(stream, &self.gas_limit);
         match self.to {
             Some(address) => stream.append(&address.as_slice()),
             None => stream.append(&Vec::<u8>::new()),
         };
-        append_u128(&mut stream, self.value);
+        append_u256(stream, &self.value);
         stream.append(&self.data.as_slice());
-        append_access_list(&mut stream, &self.access_list);
+        append_access_list(stream, &self.access_list);
+    }
 
-        let rlp_bytes = stream.out(); // moves stream once
-        let mut out = Vec::with_capacity(1 + rlp_bytes.len());
-        out.push(0x02);
-        out.extend_from_slice(rlp_bytes.as_ref());
-        out
+    pub(crate) fn decode_fields(rlp: &Rlp) -> Result<Self> {
+        Ok(Self {
+            chain_id: decode_at(rlp, 0)?,
+            nonce: decode_at(rlp, 1)?,
+            max_priority_fee_per_gas: decode_quantity(&item_at(rlp, 2)?)?,
+            max_fee_per_gas: decode_quantity(&item_at(rlp, 3)?)?,
+            gas_limit: decode_quantity(&item_at(rlp, 4)?)?,
+            to: decode_to(&item_at(rlp, 5)?)?,
+            value: decode_quantity(&item_at(rlp, 6)?)?,
+            data: decode_at(rlp, 7)?,
+            access_list: AccessList::decode_rlp(&item_at(rlp, 8)?)?,
+        })
     }
+}
 
-    /// Hashes the signing payload with keccak256.
-    pub fn signing_payload_hash(&self) -> [u8; 32] {
-        keccak256(&self.signing_payload())
+/// Signed EIP-1559 transaction decoded from its raw `0x02 || rlp([...])` form.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EvmSignedTx {
+    /// The unsigned transaction fields.
+    pub tx: EvmUnsignedTx,
+    /// Signature y-parity (0 or 1).
+    pub y_parity: u8,
+    /// Signature r value (big-endian, left-padded).
+    pub r: [u8; 32],
+    /// Signature s value (big-endian, left-padded).
+    pub s: [u8; 32],
+}
+
+impl EvmSignedTx {
+    /// Decodes a raw signed EIP-1559 transaction.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let rlp = decode_envelope(bytes, EIP1559_TX_TYPE, 12)?;
+        let tx = EvmUnsignedTx::decode_fields(&rlp)?;
+        let y_parity: u8 = decode_at(&rlp, 9)?;
+        if y_parity > 1 {
+            return Err(WalletError::InvalidInput(format!(
+                "invalid y_parity: {y_parity}"
+            )));
+        }
+        Ok(Self {
+            tx,
+            y_parity,
+            r: decode_word(&item_at(&rlp, 10)?)?,
+            s: decode_word(&item_at(&rlp, 11)?)?,
+        })
     }
 
-    /// Encodes the access list portion as RLP.
-    pub fn access_list_rlp(&self) -> Vec<u8> {
-        self.access_list.rlp_bytes()
+    /// Encodes the signed transaction: 0x02 || rlp([..., y_parity, r, s]).
+    pub fn encode(&self) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(12);
+        self.tx.append_fields(&mut stream);
+        stream.append(&self.y_parity);
+        stream.append(&trim_leading_zeros(&self.r));
+        stream.append(&trim_leading_zeros(&self.s));
+        with_type_prefix(EIP1559_TX_TYPE, &stream.out())
     }
 }
 
@@ -103,11 +210,11 @@ impl EvmUnsignedTxBuilder {
             tx: EvmUnsignedTx {
                 chain_id,
                 nonce,
-                max_priority_fee_per_gas: 0,
-                max_fee_per_gas: 0,
-                gas_limit: 0,
+                max_priority_fee_per_gas: U256::zero(),
+                max_fee_per_gas: U256::zero(),
+                gas_limit: U256::zero(),
                 to: None,
-                value: 0,
+                value: U256::zero(),
                 data: Vec::new(),
                 access_list: AccessList::default(),
             },
@@ -115,32 +222,32 @@ impl EvmUnsignedTxBuilder {
     }
 
     /// Sets max priority fee per gas.
-    pub fn max_priority_fee_per_gas(mut self, value: u128) -> Self {
-        self.tx.max_priority_fee_per_gas = value;
+    pub fn max_priority_fee_per_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_priority_fee_per_gas = value.into();
         self
     }
 
     /// Sets max fee per gas.
-    pub fn max_fee_per_gas(mut self, value: u128) -> Self {
-        self.tx.max_fee_per_gas = value;
+    pub fn max_fee_per_gas(mut self, value: impl Into<U256>) -> Self {
+        self.tx.max_fee_per_gas = value.into();
         self
     }
 
     /// Sets gas limit.
-    pub fn gas_limit(mut self, value: u128) -> Self {
-        self.tx.gas_limit = value;
+    pub fn gas_limit(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_limit = value.into();
         self
     }
 
     /// Sets recipient.
-    pub fn to(mut self, value: [u8; 20]) -> Self {
+    pub fn to(mut self, value: Address) -> Self {
         self.tx.to = Some(value);
         self
     }
 
     /// Sets value.
-    pub fn value(mut self, value: u128) -> Self {
-        self.tx.value = value;
+    pub fn value(mut self, value: impl Into<U256>) -> Self {
+        self.tx.value = value.into();
         self
     }
 
@@ -172,21 +279,148 @@ pub fn keccak256(data: &[u8]) -> [u8; 32] {
     out
 }
 
-fn append_u128(stream: &mut rlp::RlpStream, value: u128) {
-    let bytes = u128_to_bytes(value);
-    stream.append(&bytes);
+/// Hashes a message per EIP-191 `personal_sign`:
+/// keccak256("\x19Ethereum Signed Message:\n" || len(message) || message).
+pub fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut buffer = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    buffer.extend_from_slice(message);
+    keccak256(&buffer)
 }
 
-fn u128_to_bytes(value: u128) -> Vec<u8> {
-    if value == 0 {
-        return Vec::new();
-    }
-    let bytes = value.to_be_bytes();
+pub(crate) fn append_u256(stream: &mut rlp::RlpStream, value: &U256) {
+    stream.append(&trim_leading_zeros(&value.to_big_endian()));
+}
+
+pub(crate) fn with_type_prefix(tx_type: u8, rlp_bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + rlp_bytes.len());
+    out.push(tx_type);
+    out.extend_from_slice(rlp_bytes);
+    out
+}
+
+pub(crate) fn trim_leading_zeros(bytes: &[u8]) -> &[u8] {
     let first_nonzero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
-    bytes[first_nonzero..].to_vec()
+    &bytes[first_nonzero..]
+}
+
+/// Splits a 65-byte r || s || v signature into (y_parity, r, s), accepting
+/// v in {0, 1} or {27, 28}.
+pub(crate) fn split_signature(signature: &[u8]) -> Result<(u8, [u8; 32], [u8; 32])> {
+    if signature.len() != 65 {
+        return Err(WalletError::InvalidInput(
+            "signature must be 65 bytes".to_string(),
+        ));
+    }
+    let y_parity = match signature[64] {
+        0 | 27 => 0,
+        1 | 28 => 1,
+        v => {
+            return Err(WalletError::InvalidInput(format!(
+                "invalid signature recovery id: {v}"
+            )))
+        }
+    };
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    r.copy_from_slice(&signature[..32]);
+    s.copy_from_slice(&signature[32..64]);
+    Ok((y_parity, r, s))
+}
+
+pub(crate) fn rlp_error(err: rlp::DecoderError) -> WalletError {
+    WalletError::InvalidInput(format!("invalid rlp: {err}"))
+}
+
+/// Strips the EIP-2718 type byte and returns the RLP list, rejecting trailing
+/// bytes and unexpected field counts.
+pub(crate) fn decode_envelope(bytes: &[u8], tx_type: u8, fields: usize) -> Result<Rlp<'_>> {
+    match bytes.split_first() {
+        Some((first, body)) if *first == tx_type => decode_list(body, fields),
+        Some((first, _)) => Err(WalletError::InvalidInput(format!(
+            "unexpected transaction type: 0x{first:02x}"
+        ))),
+        None => Err(WalletError::InvalidInput(
+            "empty transaction bytes".to_string(),
+        )),
+    }
 }
 
-fn append_access_list(stream: &mut rlp::RlpStream, access_list: &AccessList) {
+/// Returns the RLP list spanning all of `bytes` with exactly `fields` items.
+pub(crate) fn decode_list(bytes: &[u8], fields: usize) -> Result<Rlp<'_>> {
+    let rlp = Rlp::new(bytes);
+    let info = rlp.payload_info().map_err(rlp_error)?;
+    if info.total() != bytes.len() {
+        return Err(WalletError::InvalidInput(
+            "trailing bytes after transaction".to_string(),
+        ));
+    }
+    expect_list_len(&rlp, fields)?;
+    Ok(rlp)
+}
+
+pub(crate) fn expect_list_len(rlp: &Rlp, expected: usize) -> Result<()> {
+    if !rlp.is_list() {
+        return Err(WalletError::InvalidInput(
+            "expected an rlp list".to_string(),
+        ));
+    }
+    let count = rlp.item_count().map_err(rlp_error)?;
+    if count != expected {
+        return Err(WalletError::InvalidInput(format!(
+            "expected {expected} rlp items, got {count}"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn item_at<'a>(rlp: &Rlp<'a>, index: usize) -> Result<Rlp<'a>> {
+    rlp.at(index).map_err(rlp_error)
+}
+
+pub(crate) fn decode_item<T: Decodable>(rlp: &Rlp) -> Result<T> {
+    rlp.as_val().map_err(rlp_error)
+}
+
+pub(crate) fn decode_at<T: Decodable>(rlp: &Rlp, index: usize) -> Result<T> {
+    decode_item(&item_at(rlp, index)?)
+}
+
+pub(crate) fn decode_address(rlp: &Rlp) -> Result<Address> {
+    let bytes: Vec<u8> = decode_item(rlp)?;
+    Address::from_slice(&bytes)
+}
+
+pub(crate) fn decode_to(rlp: &Rlp) -> Result<Option<Address>> {
+    let bytes: Vec<u8> = decode_item(rlp)?;
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    decode_address(rlp).map(Some)
+}
+
+/// Decodes a canonical big-endian integer of at most 32 bytes into a padded word.
+pub(crate) fn decode_word(rlp: &Rlp) -> Result<[u8; 32]> {
+    let bytes: Vec<u8> = decode_item(rlp)?;
+    if bytes.len() > 32 {
+        return Err(WalletError::InvalidInput(
+            "integer exceeds 32 bytes".to_string(),
+        ));
+    }
+    if bytes.first() == Some(&0) {
+        return Err(WalletError::InvalidInput(
+            "non-canonical integer: leading zero bytes".to_string(),
+        ));
+    }
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(out)
+}
+
+pub(crate) fn decode_quantity(rlp: &Rlp) -> Result<U256> {
+    decode_word(rlp).map(|word| U256::from_big_endian(&word))
+}
+
+pub(crate) fn append_access_list(stream: &mut rlp::RlpStream, access_list: &AccessList) {
     stream.begin_list(access_list.0.len());
     for item in &access_list.0 {
         stream.begin_list(2);
@@ -207,11 +441,11 @@ mod tests {
         let tx = EvmUnsignedTx {
             chain_id: 1,
             nonce: 0,
-            max_priority_fee_per_gas: 1,
-            max_fee_per_gas: 2,
-            gas_limit: 21_000,
-            to: Some([0u8; 20]),
-            value: 1,
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(2),
+            gas_limit: U256::from(21_000),
+            to: Some(Address([0u8; 20])),
+            value: U256::from(1),
             data: Vec::new(),
             access_list: AccessList::default(),
         };
@@ -222,4 +456,90 @@ mod tests {
                 .expect("valid hex");
         assert_eq!(payload, expected);
     }
+
+    #[test]
+    fn decodes_signing_payload_round_trip() {
+        let tx = EvmUnsignedTxBuilder::new(5, 7)
+            .max_priority_fee_per_gas(1_500_000_000)
+            .max_fee_per_gas(30_000_000_000u64)
+            .gas_limit(50_000)
+            .to(Address([0x11; 20]))
+            .value(U256::MAX)
+            .data(vec![0xde, 0xad, 0xbe, 0xef])
+            .access_list(AccessList(vec![AccessListItem {
+                address: Address([0x22; 20]),
+                storage_keys: vec![[0x33; 32]],
+            }]))
+            .build();
+        let decoded =
+            EvmUnsignedTx::decode_signing_payload(&tx.signing_payload()).expect("decoded");
+        assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn serializes_quantities_as_hex_strings() {
+        let tx = EvmUnsignedTxBuilder::new(1, 0)
+            .gas_limit(21_000)
+            .value(U256::from(u128::MAX) + 1)
+            .build();
+        let json = serde_json::to_value(&tx).expect("serialize");
+        assert_eq!(json["gas_limit"], "0x5208");
+        assert_eq!(json["value"], "0x100000000000000000000000000000000");
+        assert_eq!(json["max_fee_per_gas"], "0x0");
+        let decoded: EvmUnsignedTx = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn decodes_signed_transaction() {
+        let mut r = [0u8; 32];
+        r[31] = 0x05;
+        let signed = EvmSignedTx {
+            tx: EvmUnsignedTx {
+                chain_id: 1,
+                gas_limit: U256::from(21_000),
+                to: Some(Address([0u8; 20])),
+                ..Default::default()
+            },
+            y_parity: 1,
+            r,
+            s: [0x7f; 32],
+        };
+        let raw = signed.encode();
+        assert_eq!(EvmSignedTx::decode(&raw).expect("decoded"), signed);
+
+        let mut trailing = raw.clone();
+        trailing.push(0x00);
+        assert!(matches!(
+            EvmSignedTx::decode(&trailing),
+            Err(WalletError::InvalidInput(_))
+        ));
+        assert!(EvmUnsignedTx::decode_signing_payload(&raw).is_err());
+    }
+
+    #[test]
+    fn rejects_non_canonical_integers() {
+        // nonce encoded as 0x00 instead of the empty string.
+        let payload =
+            hex::decode("02df010001028252089400000000000000000000000000000000000000000180c0")
+                .expect("valid hex");
+        assert!(matches!(
+            EvmUnsignedTx::decode_signing_payload(&payload),
+            Err(WalletError::InvalidInput(_))
+        ));
+        // gas limit with a leading zero byte.
+        let payload =
+            hex::decode("02e00180010283005208940000000000000000000000000000000000000000000180c0")
+                .expect("valid hex");
+        assert!(EvmUnsignedTx::decode_signing_payload(&payload).is_err());
+    }
+
+    #[test]
+    fn personal_message_hash_matches_expected() {
+        let hash = personal_message_hash(b"hello");
+        let expected =
+            hex::decode("50b2c43fd39106bafbba0da34fc430e1f91e3c96ea2acee2bc34119f92b37750")
+                .expect("valid hex");
+        assert_eq!(hash.as_slice(), expected.as_slice());
+    }
 }
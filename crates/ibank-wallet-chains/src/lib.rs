@@ -1,5 +1,31 @@
 //! Chain adapters and EVM utilities.
 
+pub mod abi;
+pub mod address;
+pub mod eip4844;
+pub mod eip712;
+pub mod eip7702;
 pub mod evm;
+pub mod quantity;
+pub mod recovery;
+pub mod token;
+pub mod typed_tx;
 
-pub use evm::{AccessList, AccessListItem, EvmUnsignedTx, EvmUnsignedTxBuilder};
+pub use abi::{Abi, AbiError, Event, Function, ParamType, Revert, Token};
+pub use address::Address;
+pub use eip4844::{
+    kzg_to_versioned_hash, BlobSidecar, EvmBlobTxWithSidecar, EvmEip4844Tx, EvmEip4844TxBuilder,
+};
+pub use eip712::{TypedData, TypedDataField};
+pub use eip7702::{Authorization, EvmEip7702Tx, EvmEip7702TxBuilder, SignedAuthorization};
+pub use evm::{
+    personal_message_hash, AccessList, AccessListItem, EvmSignedTx, EvmUnsignedTx,
+    EvmUnsignedTxBuilder,
+};
+pub use quantity::U256;
+pub use recovery::{recover_address, recover_sender, transaction_hash};
+pub use token::{TokenCall, PERMIT2_ADDRESS};
+pub use typed_tx::{
+    EvmEip2930Tx, EvmEip2930TxBuilder, EvmLegacyTx, EvmLegacyTxBuilder, EvmSignedTypedTx,
+    EvmTypedTx,
+};
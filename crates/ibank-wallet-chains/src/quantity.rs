@@ -0,0 +1,283 @@
+//! 256-bit quantities and their JSON representations.
+//!
+//! [`U256`] has no serde implementation of its own; fields pick a
+//! representation with `#[serde(with = "...")]`:
+//!
+//! - `ibank_wallet_chains::quantity` emits Ethereum JSON-RPC quantities
+//!   (`"0x1a"`, `"0x0"`) and only accepts that form.
+//! - `ibank_wallet_chains::quantity::decimal` emits decimal strings
+//!   (`"26"`) and accepts decimal strings, `0x` quantities or JSON integers.
+
+use std::fmt;
+
+use ibank_wallet_core::{Result, WalletError};
+use serde::de::{self, Visitor};
+use serde::{Deserializer, Serializer};
+
+pub use primitive_types::U256;
+
+/// Formats a value as a JSON-RPC quantity: `0x`-prefixed hex without leading zeros.
+pub fn to_hex(value: &U256) -> String {
+    format!("{value:#x}")
+}
+
+/// Parses a JSON-RPC quantity. Requires the `0x` prefix, at least one digit
+/// and no leading zeros.
+pub fn from_hex(value: &str) -> Result<U256> {
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or_else(|| WalletError::InvalidInput(format!("quantity {value} must start with 0x")))?;
+    if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return Err(WalletError::InvalidInput(format!(
+            "quantity {value} is not canonical"
+        )));
+    }
+    if digits.len() > 64 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(WalletError::InvalidInput(format!(
+            "quantity {value} is not a 256-bit hex value"
+        )));
+    }
+    U256::from_str_radix(digits, 16)
+        .map_err(|_| WalletError::InvalidInput(format!("quantity {value} is not valid hex")))
+}
+
+/// Parses a decimal string.
+pub fn from_decimal(value: &str) -> Result<U256> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(WalletError::InvalidInput(format!(
+            "quantity {value} is not a decimal integer"
+        )));
+    }
+    U256::from_dec_str(value)
+        .map_err(|_| WalletError::InvalidInput(format!("quantity {value} overflows 256 bits")))
+}
+
+/// Converts a human amount such as `"1.5"` into base units of a token with
+/// `decimals` decimals. Rejects signs, exponents and excess fractional digits.
+pub fn parse_units(amount: &str, decimals: u8) -> Result<U256> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(WalletError::InvalidInput(format!(
+            "amount {amount:?} is not a decimal number"
+        )));
+    }
+    if fraction.len() > usize::from(decimals) {
+        return Err(WalletError::InvalidInput(format!(
+            "amount {amount} has more than {decimals} fractional digits"
+        )));
+    }
+    let digits = format!(
+        "{}{fraction:0<width$}",
+        if whole.is_empty() { "0" } else { whole },
+        width = usize::from(decimals)
+    );
+    from_decimal(&digits)
+        .map_err(|_| WalletError::InvalidInput(format!("amount {amount:?} is not a valid amount")))
+}
+
+/// Formats base units of a token with `decimals` decimals as a human amount,
+/// without trailing fractional zeros.
+pub fn format_units(value: &U256, decimals: u8) -> String {
+    let digits = format!(
+        "{:0>width$}",
+        value.to_string(),
+        width = usize::from(decimals) + 1
+    );
+    let (whole, fraction) = digits.split_at(digits.len() - usize::from(decimals));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{whole}.{fraction}")
+    }
+}
+
+/// Serializes a value as a JSON-RPC hex quantity.
+pub fn serialize<S: Serializer>(
+    value: &U256,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_hex(value))
+}
+
+/// Deserializes a JSON-RPC hex quantity.
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<U256, D::Error> {
+    deserializer.deserialize_str(QuantityVisitor { decimal: false })
+}
+
+/// Decimal string representation.
+pub mod decimal {
+    use super::*;
+
+    /// Serializes a value as a decimal string.
+    pub fn serialize<S: Serializer>(
+        value: &U256,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    /// Deserializes a decimal string, hex quantity or JSON integer.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<U256, D::Error> {
+        deserializer.deserialize_any(QuantityVisitor { decimal: true })
+    }
+
+    /// Optional decimal quantities; `None` is `null`.
+    pub mod option {
+        use super::*;
+        use serde::Deserialize;
+
+        /// Serializes an optional value as a decimal string or `null`.
+        pub fn serialize<S: Serializer>(
+            value: &Option<U256>,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        /// Deserializes an optional decimal string, hex quantity or integer.
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Option<U256>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] U256);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+        }
+    }
+}
+
+struct QuantityVisitor {
+    decimal: bool,
+}
+
+impl Visitor<'_> for QuantityVisitor {
+    type Value = U256;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.decimal {
+            formatter.write_str("a decimal string, 0x-prefixed hex quantity or integer")
+        } else {
+            formatter.write_str("a 0x-prefixed hex quantity")
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<U256, E> {
+        let parsed = if self.decimal && !value.starts_with("0x") {
+            from_decimal(value)
+        } else {
+            from_hex(value)
+        };
+        parsed.map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<U256, E> {
+        if self.decimal {
+            Ok(U256::from(value))
+        } else {
+            Err(E::invalid_type(de::Unexpected::Unsigned(value), &self))
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<U256, E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+        }
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> std::result::Result<U256, E> {
+        if self.decimal {
+            Ok(U256::from(value))
+        } else {
+            Err(E::custom("expected a 0x-prefixed hex quantity"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Amounts {
+        #[serde(with = "crate::quantity")]
+        hex: U256,
+        #[serde(with = "crate::quantity::decimal")]
+        decimal: U256,
+    }
+
+    #[test]
+    fn serializes_hex_and_decimal_forms() {
+        let amounts = Amounts {
+            hex: U256::from(26u64),
+            decimal: U256::MAX,
+        };
+        let json = serde_json::to_string(&amounts).expect("serialize");
+        assert_eq!(
+            json,
+            format!(
+                "{{\"hex\":\"0x1a\",\"decimal\":\"{}\"}}",
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+            )
+        );
+        assert_eq!(
+            serde_json::from_str::<Amounts>(&json).expect("deserialize"),
+            amounts
+        );
+        assert_eq!(to_hex(&U256::zero()), "0x0");
+    }
+
+    #[test]
+    fn rejects_non_canonical_quantities() {
+        for input in ["\"0x\"", "\"0x01\"", "\"1a\"", "26", "\"0xzz\""] {
+            assert!(
+                serde_json::from_str::<Amounts>(&format!("{{\"hex\":{input},\"decimal\":\"1\"}}"))
+                    .is_err(),
+                "{input}"
+            );
+        }
+        let lenient: Amounts =
+            serde_json::from_str("{\"hex\":\"0x1\",\"decimal\":26}").expect("integer");
+        assert_eq!(lenient.decimal, U256::from(26u64));
+        let hex: Amounts =
+            serde_json::from_str("{\"hex\":\"0x1\",\"decimal\":\"0x1a\"}").expect("hex");
+        assert_eq!(hex.decimal, U256::from(26u64));
+        assert!(serde_json::from_str::<Amounts>("{\"hex\":\"0x1\",\"decimal\":-1}").is_err());
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Optional {
+            #[serde(default, with = "crate::quantity::decimal::option")]
+            cap: Option<U256>,
+        }
+        let set: Optional = serde_json::from_str("{\"cap\":\"10\"}").expect("set");
+        assert_eq!(set.cap, Some(U256::from(10u64)));
+        let unset: Optional = serde_json::from_str("{}").expect("unset");
+        assert_eq!(unset.cap, None);
+    }
+
+    #[test]
+    fn converts_human_amounts() {
+        assert_eq!(
+            parse_units("1.5", 6).expect("amount"),
+            U256::from(1_500_000u64)
+        );
+        assert_eq!(parse_units(".25", 2).expect("amount"), U256::from(25u64));
+        assert_eq!(parse_units("7", 0).expect("amount"), U256::from(7u64));
+        for bad in ["", ".", "-1", "1e3", "1.2345678", "1,5", "1.2.3"] {
+            assert!(parse_units(bad, 6).is_err(), "{bad}");
+        }
+        assert_eq!(format_units(&U256::from(1_500_000u64), 6), "1.5");
+        assert_eq!(format_units(&U256::from(5u64), 6), "0.000005");
+        assert_eq!(format_units(&U256::from(42u64), 0), "42");
+        assert_eq!(format_units(&U256::zero(), 18), "0");
+    }
+}
@@ -0,0 +1,180 @@
+//! Transaction hashes and secp256k1 sender recovery.
+
+use ibank_wallet_core::{Result, WalletError};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+use crate::address::Address;
+use crate::eip4844::{EvmBlobTxWithSidecar, EIP4844_TX_TYPE};
+use crate::eip7702::SignedAuthorization;
+use crate::evm::{keccak256, EvmSignedTx};
+use crate::typed_tx::EvmSignedTypedTx;
+
+/// Recovers the address that produced a signature over a 32-byte digest.
+///
+/// Signatures with a high `s` value are rejected, as they are by Ethereum
+/// consensus rules (EIP-2).
+pub fn recover_address(
+    digest: &[u8; 32],
+    y_parity: u8,
+    r: &[u8; 32],
+    s: &[u8; 32],
+) -> Result<Address> {
+    let signature = Signature::from_scalars(*r, *s)
+        .map_err(|_| WalletError::InvalidInput("invalid signature scalars".to_string()))?;
+    if signature.normalize_s().is_some() {
+        return Err(WalletError::InvalidInput(
+            "signature s value is not in the lower half order".to_string(),
+        ));
+    }
+    let recovery_id = RecoveryId::from_byte(y_parity).filter(|id| !id.is_x_reduced());
+    let recovery_id = recovery_id.ok_or_else(|| {
+        WalletError::InvalidInput(format!("invalid signature y_parity: {y_parity}"))
+    })?;
+    let key = VerifyingKey::recover_from_prehash(digest, &signature, recovery_id)
+        .map_err(|_| WalletError::InvalidInput("signature recovery failed".to_string()))?;
+    Address::from_public_key(key.to_encoded_point(false).as_bytes())
+}
+
+/// Returns the transaction hash of raw signed transaction bytes, as accepted by
+/// `eth_sendRawTransaction`. Blob transactions may be in network form.
+pub fn transaction_hash(raw: &[u8]) -> Result<[u8; 32]> {
+    decode_raw(raw).map(|signed| signed.hash())
+}
+
+/// Recovers the sender of raw signed transaction bytes.
+pub fn recover_sender(raw: &[u8]) -> Result<Address> {
+    decode_raw(raw)?.recover_sender()
+}
+
+impl EvmSignedTypedTx {
+    /// Returns the transaction hash: keccak256 of the canonical envelope.
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(&self.encode())
+    }
+
+    /// Recovers the sender address from the signature.
+    pub fn recover_sender(&self) -> Result<Address> {
+        recover_address(
+            &self.tx.signing_payload_hash(),
+            self.y_parity,
+            &self.r,
+            &self.s,
+        )
+    }
+}
+
+impl EvmSignedTx {
+    /// Returns the transaction hash: keccak256 of the signed envelope.
+    pub fn hash(&self) -> [u8; 32] {
+        keccak256(&self.encode())
+    }
+
+    /// Recovers the sender address from the signature.
+    pub fn recover_sender(&self) -> Result<Address> {
+        recover_address(
+            &self.tx.signing_payload_hash(),
+            self.y_parity,
+            &self.r,
+            &self.s,
+        )
+    }
+}
+
+impl SignedAuthorization {
+    /// Recovers the address of the account that signed the authorization.
+    pub fn recover_authority(&self) -> Result<Address> {
+        recover_address(
+            &self.authorization.signing_hash(),
+            self.y_parity,
+            &self.r,
+            &self.s,
+        )
+    }
+}
+
+fn decode_raw(raw: &[u8]) -> Result<EvmSignedTypedTx> {
+    match EvmSignedTypedTx::decode(raw) {
+        Ok(signed) => Ok(signed),
+        Err(err) if raw.first() == Some(&EIP4844_TX_TYPE) => EvmBlobTxWithSidecar::decode(raw)
+            .map(|network| network.tx)
+            .map_err(|_| err),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eip7702::Authorization;
+    use crate::evm::EvmUnsignedTxBuilder;
+    use crate::typed_tx::EvmTypedTx;
+    use k256::ecdsa::SigningKey;
+
+    const EIP155_RAW: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    fn sign(key: &SigningKey, digest: &[u8; 32]) -> Vec<u8> {
+        let (signature, recovery_id) = key.sign_prehash_recoverable(digest).expect("signature");
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(recovery_id.to_byte() + 27);
+        bytes
+    }
+
+    #[test]
+    fn recovers_eip155_example_sender() {
+        let raw = hex::decode(EIP155_RAW).expect("valid hex");
+        assert_eq!(
+            recover_sender(&raw).expect("sender").to_string(),
+            "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F"
+        );
+        assert_eq!(
+            hex::encode(transaction_hash(&raw).expect("hash")),
+            "33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+        );
+    }
+
+    #[test]
+    fn recovers_eip1559_and_authorization_signers() {
+        let key = SigningKey::from_slice(&[0x11; 32]).expect("key");
+        let expected =
+            Address::from_public_key(key.verifying_key().to_encoded_point(false).as_bytes())
+                .expect("address");
+
+        let tx = EvmUnsignedTxBuilder::new(1, 0)
+            .max_priority_fee_per_gas(1)
+            .max_fee_per_gas(2)
+            .gas_limit(21_000)
+            .to(Address([0x22; 20]))
+            .build();
+        let signature = sign(&key, &tx.signing_payload_hash());
+        let signed =
+            EvmSignedTypedTx::from_signature(EvmTypedTx::Eip1559(tx), &signature).expect("signed");
+        let raw = signed.encode();
+        assert_eq!(recover_sender(&raw).expect("sender"), expected);
+        assert_eq!(transaction_hash(&raw).expect("hash"), keccak256(&raw));
+
+        let authorization = Authorization {
+            chain_id: 1,
+            address: Address([0x42; 20]),
+            nonce: 0,
+        };
+        let signature = sign(&key, &authorization.signing_hash());
+        let signed = SignedAuthorization::from_signature(authorization, &signature)
+            .expect("signed authorization");
+        assert_eq!(signed.recover_authority().expect("authority"), expected);
+    }
+
+    #[test]
+    fn rejects_high_s_signatures() {
+        let raw = hex::decode(EIP155_RAW).expect("valid hex");
+        let mut signed = EvmSignedTypedTx::decode(&raw).expect("decoded");
+        // Flip s to n - s and the parity to keep the same public key.
+        let signature = Signature::from_scalars(signed.r, signed.s).expect("signature");
+        let flipped = Signature::from_scalars(signed.r, -*signature.s()).expect("flipped");
+        signed.s.copy_from_slice(&flipped.s().to_bytes());
+        signed.y_parity ^= 1;
+        assert!(matches!(
+            signed.recover_sender(),
+            Err(WalletError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,514 @@
+//! ERC-20, ERC-721 and ERC-1155 token calls.
+
+use ibank_wallet_core::{Result, WalletError};
+
+use crate::abi::{Function, Token};
+use crate::address::Address;
+use crate::evm::EvmUnsignedTx;
+use crate::quantity::U256;
+
+const TRANSFER: &str = "transfer(address,uint256)";
+const TRANSFER_FROM: &str = "transferFrom(address,address,uint256)";
+const APPROVE: &str = "approve(address,uint256)";
+const SAFE_TRANSFER_FROM: &str = "safeTransferFrom(address,address,uint256)";
+const SAFE_TRANSFER_FROM_DATA: &str = "safeTransferFrom(address,address,uint256,bytes)";
+const ERC1155_TRANSFER: &str = "safeTransferFrom(address,address,uint256,uint256,bytes)";
+const ERC1155_BATCH_TRANSFER: &str =
+    "safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)";
+const SET_APPROVAL_FOR_ALL: &str = "setApprovalForAll(address,bool)";
+const INCREASE_ALLOWANCE: &str = "increaseAllowance(address,uint256)";
+const PERMIT2_APPROVE: &str = "approve(address,address,uint160,uint48)";
+const PERMIT2_PERMIT: &str =
+    "permit(address,((address,uint160,uint48,uint48),address,uint256),bytes)";
+
+/// Canonical Permit2 deployment, identical on every chain.
+pub const PERMIT2_ADDRESS: Address = Address([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x22, 0xd4, 0x73, 0x03, 0x0f, 0x11, 0x6d, 0xde, 0xe9, 0xf6, 0xb4,
+    0x3a, 0xc7, 0x8b, 0xa3,
+]);
+
+/// A call to a standard token contract.
+///
+/// ERC-20 and ERC-721 share the `transferFrom` and `approve` selectors, so
+/// `amount` in those variants is a token amount or a token id depending on
+/// the contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenCall {
+    /// ERC-20 `transfer`.
+    Transfer { to: Address, amount: U256 },
+    /// ERC-20 or ERC-721 `transferFrom`.
+    TransferFrom {
+        from: Address,
+        to: Address,
+        amount: U256,
+    },
+    /// ERC-20 or ERC-721 `approve`.
+    Approve { spender: Address, amount: U256 },
+    /// ERC-721 `safeTransferFrom`, with or without data.
+    SafeTransferFrom {
+        from: Address,
+        to: Address,
+        token_id: U256,
+        data: Vec<u8>,
+    },
+    /// ERC-1155 `safeTransferFrom`.
+    Erc1155Transfer {
+        from: Address,
+        to: Address,
+        id: U256,
+        amount: U256,
+        data: Vec<u8>,
+    },
+    /// ERC-1155 `safeBatchTransferFrom`.
+    Erc1155BatchTransfer {
+        from: Address,
+        to: Address,
+        ids: Vec<U256>,
+        amounts: Vec<U256>,
+        data: Vec<u8>,
+    },
+    /// ERC-721 or ERC-1155 `setApprovalForAll`.
+    SetApprovalForAll { operator: Address, approved: bool },
+    /// ERC-20 `increaseAllowance` (OpenZeppelin extension).
+    IncreaseAllowance { spender: Address, amount: U256 },
+    /// Permit2 `approve`, granting `spender` an allowance on `token`.
+    Permit2Approve {
+        token: Address,
+        spender: Address,
+        amount: U256,
+        expiration: u64,
+    },
+    /// Permit2 `permit` with a signed `PermitSingle`.
+    Permit2Permit {
+        owner: Address,
+        token: Address,
+        spender: Address,
+        amount: U256,
+        expiration: u64,
+        nonce: u64,
+        sig_deadline: U256,
+        signature: Vec<u8>,
+    },
+}
+
+impl TokenCall {
+    /// Encodes the call as transaction calldata.
+    pub fn encode(&self) -> Vec<u8> {
+        let (signature, args) = match self {
+            Self::Transfer { to, amount } => {
+                (TRANSFER, vec![Token::Address(*to), Token::Uint(*amount)])
+            }
+            Self::TransferFrom { from, to, amount } => (
+                TRANSFER_FROM,
+                vec![
+                    Token::Address(*from),
+                    Token::Address(*to),
+                    Token::Uint(*amount),
+                ],
+            ),
+            Self::Approve { spender, amount } => (
+                APPROVE,
+                vec![Token::Address(*spender), Token::Uint(*amount)],
+            ),
+            Self::SafeTransferFrom {
+                from,
+                to,
+                token_id,
+                data,
+            } => {
+                let mut args = vec![
+                    Token::Address(*from),
+                    Token::Address(*to),
+                    Token::Uint(*token_id),
+                ];
+                if data.is_empty() {
+                    (SAFE_TRANSFER_FROM, args)
+                } else {
+                    args.push(Token::Bytes(data.clone()));
+                    (SAFE_TRANSFER_FROM_DATA, args)
+                }
+            }
+            Self::Erc1155Transfer {
+                from,
+                to,
+                id,
+                amount,
+                data,
+            } => (
+                ERC1155_TRANSFER,
+                vec![
+                    Token::Address(*from),
+                    Token::Address(*to),
+                    Token::Uint(*id),
+                    Token::Uint(*amount),
+                    Token::Bytes(data.clone()),
+                ],
+            ),
+            Self::Erc1155BatchTransfer {
+                from,
+                to,
+                ids,
+                amounts,
+                data,
+            } => (
+                ERC1155_BATCH_TRANSFER,
+                vec![
+                    Token::Address(*from),
+                    Token::Address(*to),
+                    Token::Array(ids.iter().copied().map(Token::Uint).collect()),
+                    Token::Array(amounts.iter().copied().map(Token::Uint).collect()),
+                    Token::Bytes(data.clone()),
+                ],
+            ),
+            Self::SetApprovalForAll { operator, approved } => (
+                SET_APPROVAL_FOR_ALL,
+                vec![Token::Address(*operator), Token::Bool(*approved)],
+            ),
+            Self::IncreaseAllowance { spender, amount } => (
+                INCREASE_ALLOWANCE,
+                vec![Token::Address(*spender), Token::Uint(*amount)],
+            ),
+            Self::Permit2Approve {
+                token,
+                spender,
+                amount,
+                expiration,
+            } => (
+                PERMIT2_APPROVE,
+                vec![
+                    Token::Address(*token),
+                    Token::Address(*spender),
+                    Token::Uint(*amount),
+                    Token::Uint(U256::from(*expiration)),
+                ],
+            ),
+            Self::Permit2Permit {
+                owner,
+                token,
+                spender,
+                amount,
+                expiration,
+                nonce,
+                sig_deadline,
+                signature,
+            } => (
+                PERMIT2_PERMIT,
+                vec![
+                    Token::Address(*owner),
+                    Token::Tuple(vec![
+                        Token::Tuple(vec![
+                            Token::Address(*token),
+                            Token::Uint(*amount),
+                            Token::Uint(U256::from(*expiration)),
+                            Token::Uint(U256::from(*nonce)),
+                        ]),
+                        Token::Address(*spender),
+                        Token::Uint(*sig_deadline),
+                    ]),
+                    Token::Bytes(signature.clone()),
+                ],
+            ),
+        };
+        function(signature)
+            .encode_input(&args)
+            .expect("token call arguments match their signature")
+    }
+
+    /// Decodes calldata. Returns `None` when the selector is not a token call
+    /// and an error when it is but the arguments are malformed.
+    pub fn decode(calldata: &[u8]) -> Result<Option<Self>> {
+        let Some(selector) = calldata.get(..4) else {
+            return Ok(None);
+        };
+        let signature = [
+            TRANSFER,
+            TRANSFER_FROM,
+            APPROVE,
+            SAFE_TRANSFER_FROM,
+            SAFE_TRANSFER_FROM_DATA,
+            ERC1155_TRANSFER,
+            ERC1155_BATCH_TRANSFER,
+            SET_APPROVAL_FOR_ALL,
+            INCREASE_ALLOWANCE,
+            PERMIT2_APPROVE,
+            PERMIT2_PERMIT,
+        ]
+        .into_iter()
+        .find(|signature| function(signature).selector() == selector);
+        let Some(signature) = signature else {
+            return Ok(None);
+        };
+        let args = function(signature).decode_input(calldata)?;
+        let address = |index: usize| args[index].as_address().ok_or_else(malformed);
+        let uint = |index: usize| args[index].as_uint().ok_or_else(malformed);
+        let bytes = |index: usize| {
+            args[index]
+                .as_bytes()
+                .map(<[u8]>::to_vec)
+                .ok_or_else(malformed)
+        };
+        let uints = |index: usize| {
+            args[index]
+                .as_list()
+                .ok_or_else(malformed)?
+                .iter()
+                .map(|token| token.as_uint().ok_or_else(malformed))
+                .collect::<Result<Vec<_>>>()
+        };
+        let call = match signature {
+            TRANSFER => Self::Transfer {
+                to: address(0)?,
+                amount: uint(1)?,
+            },
+            TRANSFER_FROM => Self::TransferFrom {
+                from: address(0)?,
+                to: address(1)?,
+                amount: uint(2)?,
+            },
+            APPROVE => Self::Approve {
+                spender: address(0)?,
+                amount: uint(1)?,
+            },
+            SAFE_TRANSFER_FROM | SAFE_TRANSFER_FROM_DATA => Self::SafeTransferFrom {
+                from: address(0)?,
+                to: address(1)?,
+                token_id: uint(2)?,
+                data: if args.len() > 3 {
+                    bytes(3)?
+                } else {
+                    Vec::new()
+                },
+            },
+            ERC1155_TRANSFER => Self::Erc1155Transfer {
+                from: address(0)?,
+                to: address(1)?,
+                id: uint(2)?,
+                amount: uint(3)?,
+                data: bytes(4)?,
+            },
+            ERC1155_BATCH_TRANSFER => Self::Erc1155BatchTransfer {
+                from: address(0)?,
+                to: address(1)?,
+                ids: uints(2)?,
+                amounts: uints(3)?,
+                data: bytes(4)?,
+            },
+            SET_APPROVAL_FOR_ALL => Self::SetApprovalForAll {
+                operator: address(0)?,
+                approved: args[1].as_bool().ok_or_else(malformed)?,
+            },
+            INCREASE_ALLOWANCE => Self::IncreaseAllowance {
+                spender: address(0)?,
+                amount: uint(1)?,
+            },
+            PERMIT2_APPROVE => Self::Permit2Approve {
+                token: address(0)?,
+                spender: address(1)?,
+                amount: uint(2)?,
+                expiration: uint(3)?.low_u64(),
+            },
+            _ => {
+                let single = args[1].as_list().ok_or_else(malformed)?;
+                let details = single[0].as_list().ok_or_else(malformed)?;
+                let field = |token: &Token| token.as_uint().ok_or_else(malformed);
+                Self::Permit2Permit {
+                    owner: address(0)?,
+                    token: details[0].as_address().ok_or_else(malformed)?,
+                    spender: single[1].as_address().ok_or_else(malformed)?,
+                    amount: field(&details[1])?,
+                    expiration: field(&details[2])?.low_u64(),
+                    nonce: field(&details[3])?.low_u64(),
+                    sig_deadline: field(&single[2])?,
+                    signature: bytes(2)?,
+                }
+            }
+        };
+        Ok(Some(call))
+    }
+
+    /// Returns the account that receives the tokens, or the spender/operator
+    /// granted access to them.
+    pub fn recipient(&self) -> Address {
+        match self {
+            Self::Transfer { to, .. }
+            | Self::TransferFrom { to, .. }
+            | Self::SafeTransferFrom { to, .. }
+            | Self::Erc1155Transfer { to, .. }
+            | Self::Erc1155BatchTransfer { to, .. } => *to,
+            Self::Approve { spender, .. }
+            | Self::IncreaseAllowance { spender, .. }
+            | Self::Permit2Approve { spender, .. }
+            | Self::Permit2Permit { spender, .. } => *spender,
+            Self::SetApprovalForAll { operator, .. } => *operator,
+        }
+    }
+
+    /// Returns the token the call acts on, given the contract it is sent to.
+    /// Permit2 calls name the token as an argument.
+    pub fn token(&self, contract: Address) -> Address {
+        match self {
+            Self::Permit2Approve { token, .. } | Self::Permit2Permit { token, .. } => *token,
+            _ => contract,
+        }
+    }
+
+    /// Returns true for calls that grant allowances rather than move tokens.
+    pub fn is_approval(&self) -> bool {
+        matches!(
+            self,
+            Self::Approve { .. }
+                | Self::SetApprovalForAll { .. }
+                | Self::IncreaseAllowance { .. }
+                | Self::Permit2Approve { .. }
+                | Self::Permit2Permit { .. }
+        )
+    }
+}
+
+impl EvmUnsignedTx {
+    /// Decodes the calldata as a token call on the `to` contract.
+    pub fn token_call(&self) -> Result<Option<TokenCall>> {
+        if self.to.is_none() {
+            return Ok(None);
+        }
+        TokenCall::decode(&self.data)
+    }
+}
+
+fn function(signature: &str) -> Function {
+    Function::parse(signature).expect("token function signatures are valid")
+}
+
+fn malformed() -> WalletError {
+    WalletError::InvalidInput("malformed token call".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_decodes_token_calls() {
+        let transfer = TokenCall::Transfer {
+            to: Address([0x22; 20]),
+            amount: U256::from(1_500_000u64),
+        };
+        let calldata = transfer.encode();
+        assert_eq!(hex::encode(&calldata[..4]), "a9059cbb");
+        assert_eq!(calldata.len(), 68);
+        assert_eq!(
+            TokenCall::decode(&calldata).expect("decode"),
+            Some(transfer)
+        );
+
+        let calls = [
+            TokenCall::Approve {
+                spender: Address([0x33; 20]),
+                amount: U256::MAX,
+            },
+            TokenCall::SafeTransferFrom {
+                from: Address([0x11; 20]),
+                to: Address([0x22; 20]),
+                token_id: U256::from(771_769u64),
+                data: Vec::new(),
+            },
+            TokenCall::SafeTransferFrom {
+                from: Address([0x11; 20]),
+                to: Address([0x22; 20]),
+                token_id: U256::from(1u64),
+                data: vec![1, 2, 3],
+            },
+            TokenCall::Erc1155BatchTransfer {
+                from: Address([0x11; 20]),
+                to: Address([0x22; 20]),
+                ids: vec![U256::from(1u64), U256::from(2u64)],
+                amounts: vec![U256::from(10u64), U256::from(20u64)],
+                data: Vec::new(),
+            },
+            TokenCall::SetApprovalForAll {
+                operator: Address([0x44; 20]),
+                approved: true,
+            },
+            TokenCall::IncreaseAllowance {
+                spender: Address([0x33; 20]),
+                amount: U256::from(5u64),
+            },
+            TokenCall::Permit2Approve {
+                token: Address([0x55; 20]),
+                spender: Address([0x33; 20]),
+                amount: (U256::one() << 160) - 1,
+                expiration: 1_700_000_000,
+            },
+            TokenCall::Permit2Permit {
+                owner: Address([0x11; 20]),
+                token: Address([0x55; 20]),
+                spender: Address([0x33; 20]),
+                amount: U256::from(10u64),
+                expiration: 1_700_000_000,
+                nonce: 7,
+                sig_deadline: U256::from(1_700_000_100u64),
+                signature: vec![0xaa; 65],
+            },
+        ];
+        for call in calls {
+            assert_eq!(
+                TokenCall::decode(&call.encode()).expect("decode"),
+                Some(call)
+            );
+        }
+        assert_eq!(
+            hex::encode(
+                &TokenCall::SetApprovalForAll {
+                    operator: Address::ZERO,
+                    approved: false
+                }
+                .encode()[..4]
+            ),
+            "a22cb465"
+        );
+    }
+
+    #[test]
+    fn decodes_permit2_calls() {
+        assert_eq!(
+            PERMIT2_ADDRESS.to_string(),
+            "0x000000000022D473030F116dDEE9F6B43aC78BA3"
+        );
+        let approve = TokenCall::Permit2Approve {
+            token: Address([0x55; 20]),
+            spender: Address([0x33; 20]),
+            amount: U256::one(),
+            expiration: 0,
+        };
+        assert_eq!(hex::encode(&approve.encode()[..4]), "87517c45");
+        assert_eq!(approve.token(PERMIT2_ADDRESS), Address([0x55; 20]));
+        assert_eq!(approve.recipient(), Address([0x33; 20]));
+        assert!(approve.is_approval());
+        let permit = TokenCall::Permit2Permit {
+            owner: Address::ZERO,
+            token: Address::ZERO,
+            spender: Address::ZERO,
+            amount: U256::zero(),
+            expiration: 0,
+            nonce: 0,
+            sig_deadline: U256::zero(),
+            signature: Vec::new(),
+        };
+        assert_eq!(hex::encode(&permit.encode()[..4]), "2b67b570");
+    }
+
+    #[test]
+    fn ignores_unknown_and_rejects_malformed_calls() {
+        assert_eq!(TokenCall::decode(&[]).expect("empty"), None);
+        assert_eq!(
+            TokenCall::decode(&[0xde, 0xad, 0xbe, 0xef]).expect("unknown"),
+            None
+        );
+        let truncated = &TokenCall::Transfer {
+            to: Address([0x22; 20]),
+            amount: U256::one(),
+        }
+        .encode()[..40];
+        assert!(TokenCall::decode(truncated).is_err());
+    }
+}
@@ -0,0 +1,641 @@
+//! Legacy (EIP-155) and EIP-2930 transactions and the typed transaction envelope.
+
+use ibank_wallet_core::{Result, WalletError};
+use rlp::Rlp;
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+use crate::eip4844::{EvmEip4844Tx, EIP4844_TX_TYPE};
+use crate::eip7702::{EvmEip7702Tx, EIP7702_TX_TYPE};
+use crate::evm::{
+    append_access_list, append_u256, decode_at, decode_envelope, decode_list, decode_quantity,
+    decode_to, decode_word, item_at, keccak256, rlp_error, split_signature, trim_leading_zeros,
+    with_type_prefix, AccessList, EvmUnsignedTx, EIP1559_TX_TYPE,
+};
+use crate::quantity::U256;
+
+/// EIP-2718 type byte of EIP-2930 transactions.
+pub const EIP2930_TX_TYPE: u8 = 0x01;
+
+/// Unsigned legacy EVM transaction with EIP-155 replay protection.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct EvmLegacyTx {
+    /// Chain id for replay protection.
+    pub chain_id: u64,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Gas price.
+    #[serde(with = "crate::quantity")]
+    pub gas_price: U256,
+    /// Gas limit.
+    #[serde(with = "crate::quantity")]
+    pub gas_limit: U256,
+    /// Recipient address, or None for contract creation.
+    pub to: Option<Address>,
+    /// Value transferred in wei.
+    #[serde(with = "crate::quantity")]
+    pub value: U256,
+    /// Call data.
+    pub data: Vec<u8>,
+}
+
+impl EvmLegacyTx {
+    /// Builds the EIP-155 signing payload bytes:
+    /// rlp([nonce, gasPrice, gasLimit, to, value, data, chainId, 0, 0]).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(9);
+        self.append_fields(&mut stream);
+        stream.append(&self.chain_id);
+        stream.append(&0u8);
+        stream.append(&0u8);
+        stream.out().to_vec()
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_payload_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    fn append_fields(&self, stream: &mut rlp::RlpStream) {
+        stream.append(&self.nonce);
+        append_u256(stream, &self.gas_price);
+        append_u256(stream, &self.gas_limit);
+        append_to(stream, self.to);
+        append_u256(stream, &self.value);
+        stream.append(&self.data.as_slice());
+    }
+
+    fn decode_fields(rlp: &Rlp, chain_id: u64) -> Result<Self> {
+        Ok(Self {
+            chain_id,
+            nonce: decode_at(rlp, 0)?,
+            gas_price: decode_quantity(&item_at(rlp, 1)?)?,
+            gas_limit: decode_quantity(&item_at(rlp, 2)?)?,
+            to: decode_to(&item_at(rlp, 3)?)?,
+            value: decode_quantity(&item_at(rlp, 4)?)?,
+            data: decode_at(rlp, 5)?,
+        })
+    }
+}
+
+/// Unsigned EIP-2930 (type 1) access list transaction.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct EvmEip2930Tx {
+    /// Chain id for replay protection.
+    pub chain_id: u64,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Gas price.
+    #[serde(with = "crate::quantity")]
+    pub gas_price: U256,
+    /// Gas limit.
+    #[serde(with = "crate::quantity")]
+    pub gas_limit: U256,
+    /// Recipient address, or None for contract creation.
+    pub to: Option<Address>,
+    /// Value transferred in wei.
+    #[serde(with = "crate::quantity")]
+    pub value: U256,
+    /// Call data.
+    pub data: Vec<u8>,
+    /// Access list.
+    pub access_list: AccessList,
+}
+
+impl EvmEip2930Tx {
+    /// Builds the EIP-2930 signing payload bytes: 0x01 || rlp([...]).
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut stream = rlp::RlpStream::new_list(8);
+        self.append_fields(&mut stream);
+        with_type_prefix(EIP2930_TX_TYPE, &stream.out())
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_payload_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    fn append_fields(&self, stream: &mut rlp::RlpStream) {
+        stream.append(&self.chain_id);
+        stream.append(&self.nonce);
+        append_u256(stream, &self.gas_price);
+        append_u256(stream, &self.gas_limit);
+        append_to(stream, self.to);
+        append_u256(stream, &self.value);
+        stream.append(&self.data.as_slice());
+        append_access_list(stream, &self.access_list);
+    }
+
+    fn decode_fields(rlp: &Rlp) -> Result<Self> {
+        Ok(Self {
+            chain_id: decode_at(rlp, 0)?,
+            nonce: decode_at(rlp, 1)?,
+            gas_price: decode_quantity(&item_at(rlp, 2)?)?,
+            gas_limit: decode_quantity(&item_at(rlp, 3)?)?,
+            to: decode_to(&item_at(rlp, 4)?)?,
+            value: decode_quantity(&item_at(rlp, 5)?)?,
+            data: decode_at(rlp, 6)?,
+            access_list: AccessList::decode_rlp(&item_at(rlp, 7)?)?,
+        })
+    }
+}
+
+/// Unsigned EVM transaction of any supported type.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EvmTypedTx {
+    /// Legacy transaction with EIP-155 replay protection.
+    Legacy(EvmLegacyTx),
+    /// EIP-2930 access list transaction (type 1).
+    Eip2930(EvmEip2930Tx),
+    /// EIP-1559 dynamic fee transaction (type 2).
+    Eip1559(EvmUnsignedTx),
+    /// EIP-4844 blob transaction (type 3).
+    Eip4844(EvmEip4844Tx),
+    /// EIP-7702 set-code transaction (type 4).
+    Eip7702(EvmEip7702Tx),
+}
+
+impl EvmTypedTx {
+    /// Returns the EIP-2718 type byte (0 for legacy transactions).
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            Self::Legacy(_) => 0x00,
+            Self::Eip2930(_) => EIP2930_TX_TYPE,
+            Self::Eip1559(_) => EIP1559_TX_TYPE,
+            Self::Eip4844(_) => EIP4844_TX_TYPE,
+            Self::Eip7702(_) => EIP7702_TX_TYPE,
+        }
+    }
+
+    /// Returns the chain id.
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Self::Legacy(tx) => tx.chain_id,
+            Self::Eip2930(tx) => tx.chain_id,
+            Self::Eip1559(tx) => tx.chain_id,
+            Self::Eip4844(tx) => tx.chain_id,
+            Self::Eip7702(tx) => tx.chain_id,
+        }
+    }
+
+    /// Returns the sender nonce.
+    pub fn nonce(&self) -> u64 {
+        match self {
+            Self::Legacy(tx) => tx.nonce,
+            Self::Eip2930(tx) => tx.nonce,
+            Self::Eip1559(tx) => tx.nonce,
+            Self::Eip4844(tx) => tx.nonce,
+            Self::Eip7702(tx) => tx.nonce,
+        }
+    }
+
+    /// Returns the gas limit.
+    pub fn gas_limit(&self) -> U256 {
+        match self {
+            Self::Legacy(tx) => tx.gas_limit,
+            Self::Eip2930(tx) => tx.gas_limit,
+            Self::Eip1559(tx) => tx.gas_limit,
+            Self::Eip4844(tx) => tx.gas_limit,
+            Self::Eip7702(tx) => tx.gas_limit,
+        }
+    }
+
+    /// Returns the highest price per gas the sender may pay
+    /// (`gas_price` or `max_fee_per_gas`).
+    pub fn max_fee_per_gas(&self) -> U256 {
+        match self {
+            Self::Legacy(tx) => tx.gas_price,
+            Self::Eip2930(tx) => tx.gas_price,
+            Self::Eip1559(tx) => tx.max_fee_per_gas,
+            Self::Eip4844(tx) => tx.max_fee_per_gas,
+            Self::Eip7702(tx) => tx.max_fee_per_gas,
+        }
+    }
+
+    /// Returns the recipient, or None for contract creation.
+    pub fn to(&self) -> Option<Address> {
+        match self {
+            Self::Legacy(tx) => tx.to,
+            Self::Eip2930(tx) => tx.to,
+            Self::Eip1559(tx) => tx.to,
+            Self::Eip4844(tx) => Some(tx.to),
+            Self::Eip7702(tx) => Some(tx.to),
+        }
+    }
+
+    /// Returns the value transferred in wei.
+    pub fn value(&self) -> U256 {
+        match self {
+            Self::Legacy(tx) => tx.value,
+            Self::Eip2930(tx) => tx.value,
+            Self::Eip1559(tx) => tx.value,
+            Self::Eip4844(tx) => tx.value,
+            Self::Eip7702(tx) => tx.value,
+        }
+    }
+
+    /// Returns the call data.
+    pub fn data(&self) -> &[u8] {
+        match self {
+            Self::Legacy(tx) => &tx.data,
+            Self::Eip2930(tx) => &tx.data,
+            Self::Eip1559(tx) => &tx.data,
+            Self::Eip4844(tx) => &tx.data,
+            Self::Eip7702(tx) => &tx.data,
+        }
+    }
+
+    /// Builds the signing payload for the transaction type.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        match self {
+            Self::Legacy(tx) => tx.signing_payload(),
+            Self::Eip2930(tx) => tx.signing_payload(),
+            Self::Eip1559(tx) => tx.signing_payload(),
+            Self::Eip4844(tx) => tx.signing_payload(),
+            Self::Eip7702(tx) => tx.signing_payload(),
+        }
+    }
+
+    /// Hashes the signing payload with keccak256.
+    pub fn signing_payload_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+}
+
+impl From<EvmLegacyTx> for EvmTypedTx {
+    fn from(tx: EvmLegacyTx) -> Self {
+        Self::Legacy(tx)
+    }
+}
+
+impl From<EvmEip2930Tx> for EvmTypedTx {
+    fn from(tx: EvmEip2930Tx) -> Self {
+        Self::Eip2930(tx)
+    }
+}
+
+impl From<EvmUnsignedTx> for EvmTypedTx {
+    fn from(tx: EvmUnsignedTx) -> Self {
+        Self::Eip1559(tx)
+    }
+}
+
+impl From<EvmEip4844Tx> for EvmTypedTx {
+    fn from(tx: EvmEip4844Tx) -> Self {
+        Self::Eip4844(tx)
+    }
+}
+
+impl From<EvmEip7702Tx> for EvmTypedTx {
+    fn from(tx: EvmEip7702Tx) -> Self {
+        Self::Eip7702(tx)
+    }
+}
+
+/// Signed EVM transaction of any supported type.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EvmSignedTypedTx {
+    /// The unsigned transaction fields.
+    pub tx: EvmTypedTx,
+    /// Signature y-parity (0 or 1); legacy `v` is derived from it and the chain id.
+    pub y_parity: u8,
+    /// Signature r value (big-endian, left-padded).
+    pub r: [u8; 32],
+    /// Signature s value (big-endian, left-padded).
+    pub s: [u8; 32],
+}
+
+impl EvmSignedTypedTx {
+    /// Builds a signed transaction from a 65-byte r || s || v signature over
+    /// [`EvmTypedTx::signing_payload_hash`], with v in {0, 1} or {27, 28}.
+    pub fn from_signature(tx: EvmTypedTx, signature: &[u8]) -> Result<Self> {
+        let (y_parity, r, s) = split_signature(signature)?;
+        Ok(Self { tx, y_parity, r, s })
+    }
+
+    /// Encodes the raw signed transaction for `eth_sendRawTransaction`.
+    pub fn encode(&self) -> Vec<u8> {
+        match &self.tx {
+            EvmTypedTx::Legacy(tx) => {
+                let mut stream = rlp::RlpStream::new_list(9);
+                tx.append_fields(&mut stream);
+                // EIP-155 `v` exceeds u64 for chain ids above (2^64 - 36) / 2.
+                let v = U256::from(tx.chain_id) * 2 + 35 + self.y_parity;
+                append_u256(&mut stream, &v);
+                self.append_rs(&mut stream);
+                stream.out().to_vec()
+            }
+            EvmTypedTx::Eip2930(tx) => {
+                let mut stream = rlp::RlpStream::new_list(11);
+                tx.append_fields(&mut stream);
+                stream.append(&self.y_parity);
+                self.append_rs(&mut stream);
+                with_type_prefix(EIP2930_TX_TYPE, &stream.out())
+            }
+            EvmTypedTx::Eip1559(tx) => {
+                let mut stream = rlp::RlpStream::new_list(12);
+                tx.append_fields(&mut stream);
+                stream.append(&self.y_parity);
+                self.append_rs(&mut stream);
+                with_type_prefix(EIP1559_TX_TYPE, &stream.out())
+            }
+            EvmTypedTx::Eip4844(tx) => {
+                let mut stream = rlp::RlpStream::new_list(14);
+                tx.append_fields(&mut stream);
+                stream.append(&self.y_parity);
+                self.append_rs(&mut stream);
+                with_type_prefix(EIP4844_TX_TYPE, &stream.out())
+            }
+            EvmTypedTx::Eip7702(tx) => {
+                let mut stream = rlp::RlpStream::new_list(13);
+                tx.append_fields(&mut stream);
+                stream.append(&self.y_parity);
+                self.append_rs(&mut stream);
+                with_type_prefix(EIP7702_TX_TYPE, &stream.out())
+            }
+        }
+    }
+
+    /// Decodes a raw signed transaction of any supported type. Blob transactions
+    /// must be in canonical form; see [`crate::EvmBlobTxWithSidecar`] for the network form.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        match bytes.first() {
+            Some(first) if *first >= 0xc0 => Self::decode_legacy(bytes),
+            Some(&EIP2930_TX_TYPE) => {
+                let rlp = decode_envelope(bytes, EIP2930_TX_TYPE, 11)?;
+                let tx = EvmEip2930Tx::decode_fields(&rlp)?;
+                Self::decode_signature(EvmTypedTx::Eip2930(tx), &rlp, 8)
+            }
+            Some(&EIP1559_TX_TYPE) => {
+                let rlp = decode_envelope(bytes, EIP1559_TX_TYPE, 12)?;
+                let tx = EvmUnsignedTx::decode_fields(&rlp)?;
+                Self::decode_signature(EvmTypedTx::Eip1559(tx), &rlp, 9)
+            }
+            Some(&EIP4844_TX_TYPE) => {
+                let rlp = decode_envelope(bytes, EIP4844_TX_TYPE, 14)?;
+                let tx = EvmEip4844Tx::decode_fields(&rlp)?;
+                Self::decode_signature(EvmTypedTx::Eip4844(tx), &rlp, 11)
+            }
+            Some(&EIP7702_TX_TYPE) => {
+                let rlp = decode_envelope(bytes, EIP7702_TX_TYPE, 13)?;
+                let tx = EvmEip7702Tx::decode_fields(&rlp)?;
+                Self::decode_signature(EvmTypedTx::Eip7702(tx), &rlp, 10)
+            }
+            Some(first) => Err(WalletError::InvalidInput(format!(
+                "unsupported transaction type: 0x{first:02x}"
+            ))),
+            None => Err(WalletError::InvalidInput(
+                "empty transaction bytes".to_string(),
+            )),
+        }
+    }
+
+    fn decode_legacy(bytes: &[u8]) -> Result<Self> {
+        let rlp = decode_list(bytes, 9)?;
+        let v = decode_quantity(&item_at(&rlp, 6)?)?;
+        if v < U256::from(35) {
+            return Err(WalletError::InvalidInput(
+                "legacy transaction lacks EIP-155 replay protection".to_string(),
+            ));
+        }
+        let chain_id = u64::try_from((v - 35) / 2).map_err(|_| {
+            WalletError::InvalidInput(format!("legacy chain id out of range: v = {v}"))
+        })?;
+        let tx = EvmLegacyTx::decode_fields(&rlp, chain_id)?;
+        Ok(Self {
+            tx: EvmTypedTx::Legacy(tx),
+            y_parity: ((v - 35) % 2).low_u32() as u8,
+            r: decode_word(&item_at(&rlp, 7)?)?,
+            s: decode_word(&item_at(&rlp, 8)?)?,
+        })
+    }
+
+    fn decode_signature(tx: EvmTypedTx, rlp: &Rlp, offset: usize) -> Result<Self> {
+        let y_parity: u8 = rlp.val_at(offset).map_err(rlp_error)?;
+        if y_parity > 1 {
+            return Err(WalletError::InvalidInput(format!(
+                "invalid y_parity: {y_parity}"
+            )));
+        }
+        Ok(Self {
+            tx,
+            y_parity,
+            r: decode_word(&item_at(rlp, offset + 1)?)?,
+            s: decode_word(&item_at(rlp, offset + 2)?)?,
+        })
+    }
+
+    fn append_rs(&self, stream: &mut rlp::RlpStream) {
+        stream.append(&trim_leading_zeros(&self.r));
+        stream.append(&trim_leading_zeros(&self.s));
+    }
+}
+
+fn append_to(stream: &mut rlp::RlpStream, to: Option<Address>) {
+    match to {
+        Some(address) => stream.append(&address.as_slice()),
+        None => stream.append(&Vec::<u8>::new()),
+    };
+}
+
+/// Helper builder for legacy EVM transactions.
+#[derive(Clone, Debug, Default)]
+pub struct EvmLegacyTxBuilder {
+    tx: EvmLegacyTx,
+}
+
+impl EvmLegacyTxBuilder {
+    /// Creates a new builder with required fields.
+    pub fn new(chain_id: u64, nonce: u64) -> Self {
+        Self {
+            tx: EvmLegacyTx {
+                chain_id,
+                nonce,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets gas price.
+    pub fn gas_price(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_price = value.into();
+        self
+    }
+
+    /// Sets gas limit.
+    pub fn gas_limit(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_limit = value.into();
+        self
+    }
+
+    /// Sets recipient.
+    pub fn to(mut self, value: Address) -> Self {
+        self.tx.to = Some(value);
+        self
+    }
+
+    /// Sets value.
+    pub fn value(mut self, value: impl Into<U256>) -> Self {
+        self.tx.value = value.into();
+        self
+    }
+
+    /// Sets data.
+    pub fn data(mut self, value: Vec<u8>) -> Self {
+        self.tx.data = value;
+        self
+    }
+
+    /// Returns the built transaction.
+    pub fn build(self) -> EvmLegacyTx {
+        self.tx
+    }
+}
+
+/// Helper builder for EIP-2930 transactions.
+#[derive(Clone, Debug, Default)]
+pub struct EvmEip2930TxBuilder {
+    tx: EvmEip2930Tx,
+}
+
+impl EvmEip2930TxBuilder {
+    /// Creates a new builder with required fields.
+    pub fn new(chain_id: u64, nonce: u64) -> Self {
+        Self {
+            tx: EvmEip2930Tx {
+                chain_id,
+                nonce,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets gas price.
+    pub fn gas_price(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_price = value.into();
+        self
+    }
+
+    /// Sets gas limit.
+    pub fn gas_limit(mut self, value: impl Into<U256>) -> Self {
+        self.tx.gas_limit = value.into();
+        self
+    }
+
+    /// Sets recipient.
+    pub fn to(mut self, value: Address) -> Self {
+        self.tx.to = Some(value);
+        self
+    }
+
+    /// Sets value.
+    pub fn value(mut self, value: impl Into<U256>) -> Self {
+        self.tx.value = value.into();
+        self
+    }
+
+    /// Sets data.
+    pub fn data(mut self, value: Vec<u8>) -> Self {
+        self.tx.data = value;
+        self
+    }
+
+    /// Sets access list.
+    pub fn access_list(mut self, value: AccessList) -> Self {
+        self.tx.access_list = value;
+        self
+    }
+
+    /// Returns the built transaction.
+    pub fn build(self) -> EvmEip2930Tx {
+        self.tx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::AccessListItem;
+
+    #[test]
+    fn legacy_signing_payload_matches_eip155_example() {
+        // Example transaction from the EIP-155 specification.
+        let tx = EvmLegacyTxBuilder::new(1, 9)
+            .gas_price(20_000_000_000u64)
+            .gas_limit(21_000)
+            .to(Address([0x35; 20]))
+            .value(1_000_000_000_000_000_000u64)
+            .build();
+        let expected = hex::decode(
+            "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080",
+        )
+        .expect("valid hex");
+        assert_eq!(tx.signing_payload(), expected);
+        assert_eq!(
+            tx.signing_payload_hash().to_vec(),
+            hex::decode("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+                .expect("valid hex")
+        );
+
+        let raw = hex::decode(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        )
+        .expect("valid hex");
+        let signed = EvmSignedTypedTx::decode(&raw).expect("decoded");
+        assert_eq!(signed.tx, EvmTypedTx::Legacy(tx));
+        assert_eq!(signed.y_parity, 0);
+        assert_eq!(signed.encode(), raw);
+    }
+
+    #[test]
+    fn eip2930_round_trips() {
+        let tx = EvmEip2930TxBuilder::new(1, 3)
+            .gas_price(1_000_000_000)
+            .gas_limit(30_000)
+            .to(Address([0x44; 20]))
+            .access_list(AccessList(vec![AccessListItem {
+                address: Address([0x44; 20]),
+                storage_keys: vec![[0x01; 32]],
+            }]))
+            .build();
+        let payload = tx.signing_payload();
+        assert_eq!(payload.first().copied(), Some(EIP2930_TX_TYPE));
+
+        let mut signature = [0x0fu8; 65];
+        signature[64] = 28;
+        let signed =
+            EvmSignedTypedTx::from_signature(tx.into(), &signature).expect("signed transaction");
+        assert_eq!(signed.y_parity, 1);
+        let decoded = EvmSignedTypedTx::decode(&signed.encode()).expect("decoded");
+        assert_eq!(decoded, signed);
+    }
+
+    #[test]
+    fn legacy_v_does_not_overflow_for_large_chain_ids() {
+        let tx = EvmLegacyTxBuilder::new(u64::MAX, 1)
+            .gas_price(1)
+            .gas_limit(21_000)
+            .to(Address([0x35; 20]))
+            .build();
+        let mut signature = [0x0fu8; 65];
+        signature[64] = 28;
+        let signed =
+            EvmSignedTypedTx::from_signature(tx.into(), &signature).expect("signed transaction");
+        let decoded = EvmSignedTypedTx::decode(&signed.encode()).expect("decoded");
+        assert_eq!(decoded, signed);
+    }
+
+    #[test]
+    fn rejects_pre_eip155_legacy_transactions() {
+        let mut stream = rlp::RlpStream::new_list(9);
+        EvmLegacyTx::default().append_fields(&mut stream);
+        stream.append(&27u8);
+        stream.append(&1u8);
+        stream.append(&1u8);
+        assert!(matches!(
+            EvmSignedTypedTx::decode(&stream.out()),
+            Err(WalletError::InvalidInput(_))
+        ));
+    }
+}
@@ -1,19 +1,374 @@
-//! Chain identifiers.
+//! Chain, account and asset identifiers (CAIP-2, CAIP-10 and CAIP-19).
+
+use std::fmt;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-/// CAIP-2 chain identifier wrapper (e.g. "eip155:1").
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
-pub struct CaipChainId(pub String);
+use crate::error::{Result, WalletError};
+
+/// CAIP-2 namespace of EVM chains.
+pub const EIP155_NAMESPACE: &str = "eip155";
+
+/// CAIP-2 chain identifier (e.g. "eip155:1").
+///
+/// The namespace must match `[-a-z0-9]{3,8}` and the reference
+/// `[-_a-zA-Z0-9]{1,32}`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CaipChainId(String);
 
 impl CaipChainId {
-    /// Creates a new CAIP-2 chain identifier.
-    pub fn new(value: impl Into<String>) -> Self {
-        Self(value.into())
+    /// Parses and validates a CAIP-2 chain identifier.
+    pub fn new(value: impl Into<String>) -> Result<Self> {
+        let value = value.into();
+        let (namespace, reference) = value
+            .split_once(':')
+            .ok_or_else(|| invalid("chain id", &value, "expected namespace:reference"))?;
+        if !is_namespace(namespace) {
+            return Err(invalid("chain id", &value, "invalid namespace"));
+        }
+        if !is_token(reference, 32, "-_") {
+            return Err(invalid("chain id", &value, "invalid reference"));
+        }
+        Ok(Self(value))
+    }
+
+    /// Returns the CAIP-2 identifier of an EVM chain.
+    pub fn eip155(chain_id: u64) -> Self {
+        Self(format!("{EIP155_NAMESPACE}:{chain_id}"))
     }
 
     /// Returns the underlying string.
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Returns the namespace (e.g. "eip155").
+    pub fn namespace(&self) -> &str {
+        self.split().0
+    }
+
+    /// Returns the reference within the namespace (e.g. "1").
+    pub fn reference(&self) -> &str {
+        self.split().1
+    }
+
+    /// Returns the numeric EIP-155 chain id. Fails for non-EVM namespaces and
+    /// references that are not canonical decimal `u64` values.
+    pub fn evm_chain_id(&self) -> Result<u64> {
+        if self.namespace() != EIP155_NAMESPACE {
+            return Err(invalid("chain id", &self.0, "not an eip155 chain"));
+        }
+        let reference = self.reference();
+        let canonical = reference.bytes().all(|b| b.is_ascii_digit())
+            && (reference == "0" || !reference.starts_with('0'));
+        if !canonical {
+            return Err(invalid(
+                "chain id",
+                &self.0,
+                "reference is not a decimal chain id",
+            ));
+        }
+        reference
+            .parse()
+            .map_err(|_| invalid("chain id", &self.0, "reference overflows u64"))
+    }
+
+    fn split(&self) -> (&str, &str) {
+        self.0.split_once(':').unwrap_or((&self.0, ""))
+    }
+}
+
+impl FromStr for CaipChainId {
+    type Err = WalletError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl TryFrom<String> for CaipChainId {
+    type Error = WalletError;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<CaipChainId> for String {
+    fn from(value: CaipChainId) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for CaipChainId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// CAIP-10 account identifier (e.g. "eip155:1:0xab16a96D359eC26a11e2C2b3d8f8B8942d5Bfcdb").
+///
+/// The account address must match `[-.%a-zA-Z0-9]{1,128}`; namespace-specific
+/// address rules (such as EIP-55 checksums) are left to the chain adapters.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CaipAccountId {
+    chain_id: CaipChainId,
+    address: String,
+}
+
+impl CaipAccountId {
+    /// Builds an account identifier from a chain id and account address.
+    pub fn new(chain_id: CaipChainId, address: impl Into<String>) -> Result<Self> {
+        let address = address.into();
+        if !is_token(&address, 128, "-.%") {
+            return Err(invalid("account address", &address, "invalid characters"));
+        }
+        Ok(Self { chain_id, address })
+    }
+
+    /// Returns the chain the account lives on.
+    pub fn chain_id(&self) -> &CaipChainId {
+        &self.chain_id
+    }
+
+    /// Returns the account address as written in the identifier.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl FromStr for CaipAccountId {
+    type Err = WalletError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (chain_id, address) = value
+            .rsplit_once(':')
+            .ok_or_else(|| invalid("account id", value, "expected chain_id:address"))?;
+        Self::new(chain_id.parse()?, address)
+    }
+}
+
+impl TryFrom<String> for CaipAccountId {
+    type Error = WalletError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<CaipAccountId> for String {
+    fn from(value: CaipAccountId) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for CaipAccountId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.chain_id, self.address)
+    }
+}
+
+/// CAIP-19 asset identifier (e.g. "eip155:1/erc20:0x6B17…" or
+/// "eip155:1/erc721:0x06012c8cf97BEaD5deAe237070F9587f8E7A266d/771769").
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CaipAssetId {
+    chain_id: CaipChainId,
+    asset_namespace: String,
+    asset_reference: String,
+    token_id: Option<String>,
+}
+
+impl CaipAssetId {
+    /// Builds an asset identifier from its parts.
+    pub fn new(
+        chain_id: CaipChainId,
+        asset_namespace: impl Into<String>,
+        asset_reference: impl Into<String>,
+        token_id: Option<String>,
+    ) -> Result<Self> {
+        let asset_namespace = asset_namespace.into();
+        let asset_reference = asset_reference.into();
+        if !is_namespace(&asset_namespace) {
+            return Err(invalid(
+                "asset namespace",
+                &asset_namespace,
+                "invalid namespace",
+            ));
+        }
+        if !is_token(&asset_reference, 128, "-.%") {
+            return Err(invalid(
+                "asset reference",
+                &asset_reference,
+                "invalid characters",
+            ));
+        }
+        if let Some(token_id) = &token_id {
+            if !is_token(token_id, 78, "-.%") {
+                return Err(invalid("token id", token_id, "invalid characters"));
+            }
+        }
+        Ok(Self {
+            chain_id,
+            asset_namespace,
+            asset_reference,
+            token_id,
+        })
+    }
+
+    /// Returns the chain the asset lives on.
+    pub fn chain_id(&self) -> &CaipChainId {
+        &self.chain_id
+    }
+
+    /// Returns the asset namespace (e.g. "erc20", "slip44").
+    pub fn asset_namespace(&self) -> &str {
+        &self.asset_namespace
+    }
+
+    /// Returns the asset reference (e.g. a token contract address).
+    pub fn asset_reference(&self) -> &str {
+        &self.asset_reference
+    }
+
+    /// Returns the token id of non-fungible assets.
+    pub fn token_id(&self) -> Option<&str> {
+        self.token_id.as_deref()
+    }
+}
+
+impl FromStr for CaipAssetId {
+    type Err = WalletError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut parts = value.splitn(3, '/');
+        let chain_id = parts.next().unwrap_or_default();
+        let asset_type = parts
+            .next()
+            .ok_or_else(|| invalid("asset id", value, "expected chain_id/namespace:reference"))?;
+        let (asset_namespace, asset_reference) = asset_type
+            .split_once(':')
+            .ok_or_else(|| invalid("asset id", value, "expected namespace:reference"))?;
+        Self::new(
+            chain_id.parse()?,
+            asset_namespace,
+            asset_reference,
+            parts.next().map(str::to_string),
+        )
+    }
+}
+
+impl TryFrom<String> for CaipAssetId {
+    type Error = WalletError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<CaipAssetId> for String {
+    fn from(value: CaipAssetId) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for CaipAssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}:{}",
+            self.chain_id, self.asset_namespace, self.asset_reference
+        )?;
+        if let Some(token_id) = &self.token_id {
+            write!(f, "/{token_id}")?;
+        }
+        Ok(())
+    }
+}
+
+fn is_namespace(value: &str) -> bool {
+    (3..=8).contains(&value.len())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn is_token(value: &str, max_len: usize, extra: &str) -> bool {
+    (1..=max_len).contains(&value.len())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || extra.as_bytes().contains(&b))
+}
+
+fn invalid(kind: &str, value: &str, detail: &str) -> WalletError {
+    WalletError::InvalidInput(format!("invalid {kind} {value:?}: {detail}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chain_ids() {
+        let chain: CaipChainId = "eip155:137".parse().expect("chain id");
+        assert_eq!(chain.namespace(), "eip155");
+        assert_eq!(chain.reference(), "137");
+        assert_eq!(chain.evm_chain_id().expect("evm chain id"), 137);
+        assert_eq!(CaipChainId::eip155(137), chain);
+
+        let solana: CaipChainId = "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp"
+            .parse()
+            .expect("solana chain id");
+        assert!(solana.evm_chain_id().is_err());
+
+        for bad in ["1", "eip155", "eip155:", "EIP155:1", "ab:1", "eip155:1:2"] {
+            assert!(bad.parse::<CaipChainId>().is_err(), "{bad}");
+        }
+        for bad in ["eip155:01", "eip155:0x1", "eip155:18446744073709551616"] {
+            let chain: CaipChainId = bad.parse().expect("valid caip-2");
+            assert!(chain.evm_chain_id().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parses_account_and_asset_ids() {
+        let account: CaipAccountId = "eip155:1:0xab16a96D359eC26a11e2C2b3d8f8B8942d5Bfcdb"
+            .parse()
+            .expect("account id");
+        assert_eq!(account.chain_id(), &CaipChainId::eip155(1));
+        assert_eq!(
+            account.address(),
+            "0xab16a96D359eC26a11e2C2b3d8f8B8942d5Bfcdb"
+        );
+
+        let erc721 = "eip155:1/erc721:0x06012c8cf97BEaD5deAe237070F9587f8E7A266d/771769";
+        let asset: CaipAssetId = erc721.parse().expect("asset id");
+        assert_eq!(asset.asset_namespace(), "erc721");
+        assert_eq!(asset.token_id(), Some("771769"));
+        assert_eq!(asset.to_string(), erc721);
+
+        let erc20: CaipAssetId = "eip155:1/erc20:0x6b175474e89094c44da98b954eedeac495271d0f"
+            .parse()
+            .expect("erc20 asset id");
+        assert_eq!(erc20.token_id(), None);
+        assert!("eip155:1/erc20".parse::<CaipAssetId>().is_err());
+        assert!("eip155:1:0x12 34".parse::<CaipAccountId>().is_err());
+    }
+
+    #[test]
+    fn serializes_as_strings() {
+        let chain = CaipChainId::eip155(1);
+        assert_eq!(
+            serde_json::to_string(&chain).expect("serialize"),
+            "\"eip155:1\""
+        );
+        assert!(serde_json::from_str::<CaipChainId>("\"mainnet\"").is_err());
+        let account: CaipAccountId =
+            serde_json::from_str("\"eip155:1:0x0000000000000000000000000000000000000001\"")
+                .expect("account id");
+        assert_eq!(account.chain_id().evm_chain_id().expect("evm"), 1);
+    }
 }
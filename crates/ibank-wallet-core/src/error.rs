@@ -20,4 +20,8 @@ pub enum WalletError {
     /// Indicates invalid input.
     #[error("invalid input: {0}")]
     InvalidInput(String),
+    /// Indicates the action is held until approvers sign off; carries the
+    /// hex hash of the pending intent.
+    #[error("approval required for intent {0}")]
+    ApprovalRequired(String),
 }
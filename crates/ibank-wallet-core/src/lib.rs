@@ -5,5 +5,5 @@ pub mod chain;
 pub mod error;
 
 pub use audit::{AuditEvent, AuditLog};
-pub use chain::CaipChainId;
+pub use chain::{CaipAccountId, CaipAssetId, CaipChainId, EIP155_NAMESPACE};
 pub use error::{Result, WalletError};
@@ -34,7 +34,7 @@ fn main() {
     build.flag_if_supported("-std=c++17");
     if std::env::var("CARGO_CFG_TARGET_OS").ok().as_deref() == Some("macos") {
         let target = std::env::var(MACOSX_DEPLOYMENT_TARGET_ENV).unwrap_or_else(|_| "11.0".to_string());
-        build.flag_if_supported(&format!("-mmacosx-version-min={}", target));
+        build.flag_if_supported(format!("-mmacosx-version-min={}", target));
         // Ensure the final link step uses the same deployment target.
         println!(
             "cargo:rustc-link-arg=-Wl,-platform_version,macos,{0},{0}",
@@ -26,5 +26,5 @@ fn main() {
         .expect("failed to derive address");
 
     println!("path: {}", path);
-    println!("address (20 bytes): 0x{}", to_hex(&addr20));
+    println!("address (20 bytes): 0x{}", to_hex(addr20.as_slice()));
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use ibank_wallet_crypto::wallet_core::WalletCoreSigner;
+use ibank_wallet_crypto::Signer;
 
 fn to_hex(bytes: &[u8]) -> String {
     const HEX: &[u8; 16] = b"0123456789abcdef";
@@ -19,12 +20,12 @@ fn main() {
         .expect("failed to create signer");
 
     let addr20 = signer.derive_evm_address(path).expect("addr");
-    println!("address: 0x{}", to_hex(&addr20));
+    println!("address: {}", addr20);
 
     // Example: "hello"
     let msg = b"hello";
 
-    // You need to implement this method (see below): signer.sign_evm_personal_message(path, msg)
+    // EIP-191 personal_sign: r || s || v
     let sig65 = signer
         .sign_evm_personal_message(path, msg)
         .expect("sign message");
@@ -1,4 +1,7 @@
-use ibank_wallet_crypto::wallet_core::{WalletCoreSigner, Evm1559Tx};
+use ibank_wallet_chains::{Address, EvmLegacyTxBuilder, EvmTypedTx, EvmUnsignedTxBuilder};
+use ibank_wallet_core::CaipChainId;
+use ibank_wallet_crypto::wallet_core::WalletCoreSigner;
+use ibank_wallet_crypto::{Signer, DEFAULT_EVM_DERIVATION_PATH};
 
 fn to_hex(bytes: &[u8]) -> String {
     const HEX: &[u8; 16] = b"0123456789abcdef";
@@ -17,23 +20,42 @@ fn main() {
 
     let signer = WalletCoreSigner::from_mnemonic(mnemonic, passphrase).unwrap();
     let from = signer.derive_evm_address(path).unwrap();
-    println!("from: 0x{}", to_hex(&from));
+    println!("from: {from}");
+
+    // vitalik.eth
+    let to = Address([
+        0xd8, 0xda, 0x6b, 0xf2, 0x69, 0x64, 0xaf, 0x9d, 0x7e, 0xed, 0x9e, 0x03, 0xe5, 0x34, 0x15,
+        0xd3, 0x7a, 0xa9, 0x60, 0x45,
+    ]);
 
     // A minimal EIP-1559 transfer
-    let tx = Evm1559Tx {
-        chain_id: 1,
-        nonce: 0,
-        max_priority_fee_per_gas: 1_500_000_000u64, // 1.5 gwei
-        max_fee_per_gas: 30_000_000_000u64,         // 30 gwei
-        gas_limit: 21_000u64,
-        to: hex::decode("d8da6bf26964af9d7eed9e03e53415d37aa96045").unwrap(), // 20 bytes
-        value: 1_000_000_000_000_000u64, // 0.001 ETH
-        data: vec![],
-        access_list_rlp: vec![], // can be empty for now
-    };
-
-    // You implement this method: signer.sign_evm_1559_tx(path, &tx) -> Vec<u8> (raw tx)
-    let raw = signer.sign_evm_1559_tx(path, &tx).unwrap();
+    let tx = EvmUnsignedTxBuilder::new(1, 0)
+        .max_priority_fee_per_gas(1_500_000_000) // 1.5 gwei
+        .max_fee_per_gas(30_000_000_000u64) // 30 gwei
+        .gas_limit(21_000)
+        .to(to)
+        .value(1_000_000_000_000_000u64) // 0.001 ETH
+        .build();
 
+    let raw = signer
+        .sign_evm_eip1559(&CaipChainId::eip155(1), &tx)
+        .unwrap();
     println!("raw signed tx (hex): 0x{}", to_hex(&raw));
-}
\ No newline at end of file
+
+    // The same transfer as a legacy EIP-155 transaction
+    let legacy = EvmLegacyTxBuilder::new(1, 0)
+        .gas_price(30_000_000_000u64)
+        .gas_limit(21_000)
+        .to(to)
+        .value(1_000_000_000_000_000u64)
+        .build();
+
+    let raw = signer
+        .sign_evm_transaction(
+            DEFAULT_EVM_DERIVATION_PATH,
+            &CaipChainId::eip155(1),
+            &EvmTypedTx::Legacy(legacy),
+        )
+        .unwrap();
+    println!("raw signed legacy tx (hex): 0x{}", to_hex(&raw));
+}
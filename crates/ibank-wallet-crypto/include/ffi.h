@@ -30,3 +30,13 @@ rust::Vec<std::uint8_t> sign_eip1559(
     const rust::Vec<std::uint8_t>& value_be,
     const rust::Vec<std::uint8_t>& data,
     const rust::Vec<std::uint8_t>& access_list_rlp);
+
+rust::Vec<std::uint8_t> sign_personal_message(
+    const WalletCoreSigner& signer,
+    const rust::Str derivation_path,
+    const rust::Vec<std::uint8_t>& message);
+
+rust::Vec<std::uint8_t> sign_evm_digest(
+    const WalletCoreSigner& signer,
+    const rust::Str derivation_path,
+    const rust::Vec<std::uint8_t>& digest);
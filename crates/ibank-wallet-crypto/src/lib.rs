@@ -1,15 +1,49 @@
 //! Signing interfaces and wallet-core bridge.
 
-use ibank_wallet_chains::EvmUnsignedTx;
-use ibank_wallet_core::Result;
+#[cfg(not(feature = "wallet-core"))]
+use ibank_wallet_chains::EvmSignedTypedTx;
+use ibank_wallet_chains::{
+    Authorization, EvmTypedTx, EvmUnsignedTx, SignedAuthorization, TypedData,
+};
+#[cfg(not(feature = "wallet-core"))]
+use ibank_wallet_core::WalletError;
+use ibank_wallet_core::{CaipChainId, Result};
 
 #[cfg(feature = "wallet-core")]
 pub mod wallet_core;
 
+/// Derivation path of the first EVM account (BIP-44 coin type 60).
+pub const DEFAULT_EVM_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
 /// A signer capable of producing signed EVM transactions.
 pub trait Signer {
-    /// Signs an EIP-1559 transaction and returns the signed bytes.
-    fn sign_evm_eip1559(&self, chain_id: &str, tx: &EvmUnsignedTx) -> Result<Vec<u8>>;
+    /// Signs an EIP-1559 transaction with the account at [`DEFAULT_EVM_DERIVATION_PATH`] and
+    /// returns the signed bytes.
+    fn sign_evm_eip1559(&self, chain_id: &CaipChainId, tx: &EvmUnsignedTx) -> Result<Vec<u8>>;
+
+    /// Signs a transaction of any supported type with the account at `derivation_path` and
+    /// returns the raw signed bytes. Blob transactions are returned in canonical form without
+    /// their sidecar.
+    fn sign_evm_transaction(
+        &self,
+        derivation_path: &str,
+        chain_id: &CaipChainId,
+        tx: &EvmTypedTx,
+    ) -> Result<Vec<u8>>;
+
+    /// Signs an EIP-191 personal message and returns the 65-byte r||s||v signature.
+    fn sign_evm_personal_message(&self, derivation_path: &str, message: &[u8]) -> Result<Vec<u8>>;
+
+    /// Signs EIP-712 typed data (`eth_signTypedData_v4`) and returns the 65-byte r||s||v signature.
+    fn sign_evm_typed_data(&self, derivation_path: &str, typed_data: &TypedData)
+        -> Result<Vec<u8>>;
+
+    /// Signs an EIP-7702 authorization delegating the account at `derivation_path`.
+    fn sign_evm_authorization(
+        &self,
+        derivation_path: &str,
+        authorization: &Authorization,
+    ) -> Result<SignedAuthorization>;
 }
 
 #[cfg(feature = "wallet-core")]
@@ -22,7 +56,7 @@ pub struct MockSigner;
 
 #[cfg(not(feature = "wallet-core"))]
 impl Signer for MockSigner {
-    fn sign_evm_eip1559(&self, _chain_id: &str, tx: &EvmUnsignedTx) -> Result<Vec<u8>> {
+    fn sign_evm_eip1559(&self, _chain_id: &CaipChainId, tx: &EvmUnsignedTx) -> Result<Vec<u8>> {
         let mut payload = tx.signing_payload();
         if payload.is_empty() {
             return Err(WalletError::SigningError("empty payload".to_string()));
@@ -30,4 +64,46 @@ impl Signer for MockSigner {
         payload.extend_from_slice(b"mock");
         Ok(payload)
     }
+
+    fn sign_evm_transaction(
+        &self,
+        _derivation_path: &str,
+        _chain_id: &CaipChainId,
+        tx: &EvmTypedTx,
+    ) -> Result<Vec<u8>> {
+        let signature = mock_signature(tx.signing_payload_hash());
+        Ok(EvmSignedTypedTx::from_signature(tx.clone(), &signature)?.encode())
+    }
+
+    fn sign_evm_personal_message(&self, _derivation_path: &str, message: &[u8]) -> Result<Vec<u8>> {
+        Ok(mock_signature(ibank_wallet_chains::personal_message_hash(
+            message,
+        )))
+    }
+
+    fn sign_evm_typed_data(
+        &self,
+        _derivation_path: &str,
+        typed_data: &TypedData,
+    ) -> Result<Vec<u8>> {
+        Ok(mock_signature(typed_data.signing_hash()?))
+    }
+
+    fn sign_evm_authorization(
+        &self,
+        _derivation_path: &str,
+        authorization: &Authorization,
+    ) -> Result<SignedAuthorization> {
+        let signature = mock_signature(authorization.signing_hash());
+        SignedAuthorization::from_signature(authorization.clone(), &signature)
+    }
+}
+
+#[cfg(not(feature = "wallet-core"))]
+fn mock_signature(digest: [u8; 32]) -> Vec<u8> {
+    let mut signature = Vec::with_capacity(65);
+    signature.extend_from_slice(&digest);
+    signature.extend_from_slice(&digest);
+    signature.push(27);
+    signature
 }
@@ -6,10 +6,12 @@
 #include <TrustWalletCore/TWAnyAddress.h>
 #include <TrustWalletCore/TWAnySigner.h>
 #include <TrustWalletCore/TWCoinType.h>
+#include <TrustWalletCore/TWCurve.h>
 #include <TrustWalletCore/TWData.h>
 #include <TrustWalletCore/TWEthereum.h>
 #include <TrustWalletCore/TWEthereumProto.h>
 #include <TrustWalletCore/TWHDWallet.h>
+#include <TrustWalletCore/TWHash.h>
 #include <TrustWalletCore/TWPrivateKey.h>
 #include <TrustWalletCore/TWPublicKey.h>
 #include <TrustWalletCore/TWString.h>
@@ -103,6 +105,26 @@ TWString* to_tw_string(const std::string& value) {
 SignerState* get_state(const WalletCoreSigner& signer) {
   return static_cast<SignerState*>(signer.inner);
 }
+
+// Signs a 32-byte digest and returns r || s || v with v in {27, 28}.
+rust::Vec<uint8_t> sign_digest(TWPrivateKey* private_key, const std::string& digest) {
+  TWData* digest_data =
+      TWDataCreateWithBytes(reinterpret_cast<const uint8_t*>(digest.data()), digest.size());
+  TWData* signature = TWPrivateKeySign(private_key, digest_data, TWCurveSECP256k1);
+  TWDataDelete(digest_data);
+  if (!signature) {
+    return {};
+  }
+  auto out = to_rust_vec(signature);
+  TWDataDelete(signature);
+  if (out.size() != 65) {
+    return {};
+  }
+  if (out[64] < 27) {
+    out[64] += 27;
+  }
+  return out;
+}
 }  // namespace
 
 WalletCoreSigner::~WalletCoreSigner() {
@@ -238,3 +260,53 @@ rust::Vec<std::uint8_t> sign_eip1559(
   return {};
 #endif
 }
+
+rust::Vec<std::uint8_t> sign_personal_message(const WalletCoreSigner& signer,
+                                              rust::Str derivation_path,
+                                              const rust::Vec<std::uint8_t>& message) {
+  auto* state = get_state(signer);
+  if (!state || !state->wallet) {
+    return {};
+  }
+  TWString* path_str = to_tw_string(derivation_path);
+  TWPrivateKey* private_key = TWHDWalletGetKey(state->wallet, TWCoinTypeEthereum, path_str);
+  TWStringDelete(path_str);
+  if (!private_key) {
+    return {};
+  }
+
+  std::string prefixed = "\x19" "Ethereum Signed Message:\n" + std::to_string(message.size());
+  prefixed.append(to_bytes_string(message));
+  TWData* prefixed_data =
+      TWDataCreateWithBytes(reinterpret_cast<const uint8_t*>(prefixed.data()), prefixed.size());
+  TWData* digest = TWHashKeccak256(prefixed_data);
+  TWDataDelete(prefixed_data);
+  if (!digest) {
+    TWPrivateKeyDelete(private_key);
+    return {};
+  }
+  std::string digest_bytes(reinterpret_cast<const char*>(TWDataBytes(digest)), TWDataSize(digest));
+  TWDataDelete(digest);
+
+  auto out = sign_digest(private_key, digest_bytes);
+  TWPrivateKeyDelete(private_key);
+  return out;
+}
+
+rust::Vec<std::uint8_t> sign_evm_digest(const WalletCoreSigner& signer,
+                                        rust::Str derivation_path,
+                                        const rust::Vec<std::uint8_t>& digest) {
+  auto* state = get_state(signer);
+  if (!state || !state->wallet || digest.size() != 32) {
+    return {};
+  }
+  TWString* path_str = to_tw_string(derivation_path);
+  TWPrivateKey* private_key = TWHDWalletGetKey(state->wallet, TWCoinTypeEthereum, path_str);
+  TWStringDelete(path_str);
+  if (!private_key) {
+    return {};
+  }
+  auto out = sign_digest(private_key, to_bytes_string(digest));
+  TWPrivateKeyDelete(private_key);
+  return out;
+}
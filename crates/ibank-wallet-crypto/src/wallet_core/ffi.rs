@@ -23,7 +23,22 @@ mod ffi {
             data: &Vec<u8>,
             access_list_rlp: &Vec<u8>,
         ) -> Vec<u8>;
+
+        fn sign_personal_message(
+            signer: &WalletCoreSigner,
+            derivation_path: &str,
+            message: &Vec<u8>,
+        ) -> Vec<u8>;
+
+        fn sign_evm_digest(
+            signer: &WalletCoreSigner,
+            derivation_path: &str,
+            digest: &Vec<u8>,
+        ) -> Vec<u8>;
     }
 }
 
-pub use ffi::{derive_evm_address, new_signer, sign_eip1559, WalletCoreSigner};
+pub use ffi::{
+    derive_evm_address, new_signer, sign_eip1559, sign_evm_digest, sign_personal_message,
+    WalletCoreSigner,
+};
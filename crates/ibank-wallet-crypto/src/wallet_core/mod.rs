@@ -1,9 +1,12 @@
 //! wallet-core backed signer implementation.
 
-use ibank_wallet_chains::EvmUnsignedTx;
-use ibank_wallet_core::{Result, WalletError};
+use ibank_wallet_chains::{
+    Address, Authorization, EvmSignedTypedTx, EvmTypedTx, EvmUnsignedTx, SignedAuthorization,
+    TypedData, U256,
+};
+use ibank_wallet_core::{CaipChainId, Result, WalletError};
 
-use crate::Signer;
+use crate::{Signer, DEFAULT_EVM_DERIVATION_PATH};
 
 mod ffi;
 
@@ -13,8 +16,6 @@ pub struct WalletCoreSigner {
     inner: cxx::UniquePtr<ffi::WalletCoreSigner>,
 }
 
-const DEFAULT_EVM_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
-
 impl WalletCoreSigner {
     /// Creates a signer from a mnemonic and optional passphrase.
     pub fn from_mnemonic(mnemonic: &str, passphrase: &str) -> Result<Self> {
@@ -28,33 +29,31 @@ impl WalletCoreSigner {
     }
 
     /// Derives the EVM address at the given derivation path.
-    pub fn derive_evm_address(&self, derivation_path: &str) -> Result<[u8; 20]> {
+    pub fn derive_evm_address(&self, derivation_path: &str) -> Result<Address> {
         let address = ffi::derive_evm_address(&self.inner, derivation_path);
-        if address.len() != 20 {
-            return Err(WalletError::SigningError(
-                "wallet-core returned invalid address".to_string(),
-            ));
-        }
-        let mut out = [0u8; 20];
-        out.copy_from_slice(&address);
-        Ok(out)
+        Address::from_slice(&address).map_err(|_| {
+            WalletError::SigningError("wallet-core returned invalid address".to_string())
+        })
     }
 
     /// Returns the EVM address for the given path or the default derivation path.
-    pub fn evm_address(&self, derivation_path: Option<&str>) -> Result<[u8; 20]> {
+    pub fn evm_address(&self, derivation_path: Option<&str>) -> Result<Address> {
         let path = derivation_path.unwrap_or(DEFAULT_EVM_DERIVATION_PATH);
         self.derive_evm_address(path)
     }
 }
 
 impl Signer for WalletCoreSigner {
-    fn sign_evm_eip1559(&self, chain_id: &str, tx: &EvmUnsignedTx) -> Result<Vec<u8>> {
-        let chain_id = parse_chain_id(chain_id)?;
-        let to_bytes = tx.to.map(|addr| addr.to_vec()).unwrap_or_default();
-        let max_priority_fee_per_gas = u128_to_bytes(tx.max_priority_fee_per_gas);
-        let max_fee_per_gas = u128_to_bytes(tx.max_fee_per_gas);
-        let gas_limit = u128_to_bytes(tx.gas_limit);
-        let value = u128_to_bytes(tx.value);
+    fn sign_evm_eip1559(&self, chain_id: &CaipChainId, tx: &EvmUnsignedTx) -> Result<Vec<u8>> {
+        let chain_id = chain_id.evm_chain_id()?;
+        let to_bytes = tx
+            .to
+            .map(|addr| addr.as_slice().to_vec())
+            .unwrap_or_default();
+        let max_priority_fee_per_gas = u256_to_bytes(tx.max_priority_fee_per_gas);
+        let max_fee_per_gas = u256_to_bytes(tx.max_fee_per_gas);
+        let gas_limit = u256_to_bytes(tx.gas_limit);
+        let value = u256_to_bytes(tx.value);
         let access_list = tx.access_list_rlp();
 
         let signed = ffi::sign_eip1559(
@@ -77,20 +76,68 @@ impl Signer for WalletCoreSigner {
         }
         Ok(signed)
     }
-}
 
-fn parse_chain_id(chain_id: &str) -> Result<u64> {
-    let trimmed = chain_id.strip_prefix("eip155:").unwrap_or(chain_id);
-    trimmed
-        .parse::<u64>()
-        .map_err(|_| WalletError::InvalidInput("invalid chain id".to_string()))
+    fn sign_evm_transaction(
+        &self,
+        derivation_path: &str,
+        chain_id: &CaipChainId,
+        tx: &EvmTypedTx,
+    ) -> Result<Vec<u8>> {
+        if chain_id.evm_chain_id()? != tx.chain_id() {
+            return Err(WalletError::InvalidInput(
+                "transaction chain id does not match".to_string(),
+            ));
+        }
+        let digest = tx.signing_payload_hash();
+        let signature = check_signature(ffi::sign_evm_digest(
+            &self.inner,
+            derivation_path,
+            &digest.to_vec(),
+        ))?;
+        Ok(EvmSignedTypedTx::from_signature(tx.clone(), &signature)?.encode())
+    }
+
+    fn sign_evm_personal_message(&self, derivation_path: &str, message: &[u8]) -> Result<Vec<u8>> {
+        let signature = ffi::sign_personal_message(&self.inner, derivation_path, &message.to_vec());
+        check_signature(signature)
+    }
+
+    fn sign_evm_typed_data(
+        &self,
+        derivation_path: &str,
+        typed_data: &TypedData,
+    ) -> Result<Vec<u8>> {
+        let digest = typed_data.signing_hash()?;
+        let signature = ffi::sign_evm_digest(&self.inner, derivation_path, &digest.to_vec());
+        check_signature(signature)
+    }
+
+    fn sign_evm_authorization(
+        &self,
+        derivation_path: &str,
+        authorization: &Authorization,
+    ) -> Result<SignedAuthorization> {
+        let digest = authorization.signing_hash();
+        let signature = check_signature(ffi::sign_evm_digest(
+            &self.inner,
+            derivation_path,
+            &digest.to_vec(),
+        ))?;
+        SignedAuthorization::from_signature(authorization.clone(), &signature)
+    }
 }
 
-fn u128_to_bytes(value: u128) -> Vec<u8> {
-    if value == 0 {
-        return Vec::new();
+fn check_signature(signature: Vec<u8>) -> Result<Vec<u8>> {
+    if signature.len() != 65 {
+        return Err(WalletError::SigningError(
+            "wallet-core returned invalid signature".to_string(),
+        ));
     }
-    let bytes = value.to_be_bytes();
+    Ok(signature)
+}
+
+fn u256_to_bytes(value: U256) -> Vec<u8> {
+    let bytes = value.to_big_endian();
     let first_nonzero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
     bytes[first_nonzero..].to_vec()
 }
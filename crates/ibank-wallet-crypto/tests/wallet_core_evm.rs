@@ -1,6 +1,10 @@
 #[cfg(feature = "wallet-core")]
 mod wallet_core_tests {
-    use ibank_wallet_chains::EvmUnsignedTx;
+    use ibank_wallet_chains::{
+        personal_message_hash, recover_address, Address, Authorization, EvmEip2930TxBuilder,
+        EvmSignedTypedTx, EvmTypedTx, EvmUnsignedTx, TypedData, U256,
+    };
+    use ibank_wallet_core::CaipChainId;
     use ibank_wallet_crypto::{Signer, WalletCoreSigner};
 
     const MNEMONIC: &str =
@@ -21,19 +25,119 @@ mod wallet_core_tests {
         let tx = EvmUnsignedTx {
             chain_id: 1,
             nonce: 0,
-            max_priority_fee_per_gas: 1,
-            max_fee_per_gas: 2,
-            gas_limit: 21_000,
-            to: Some([0x11u8; 20]),
-            value: 1,
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(2),
+            gas_limit: U256::from(21_000),
+            to: Some(Address([0x11u8; 20])),
+            value: U256::from(1),
             data: Vec::new(),
             access_list: Default::default(),
         };
-        let signed = signer.sign_evm_eip1559("eip155:1", &tx).expect("signed");
+        let signed = signer
+            .sign_evm_eip1559(&CaipChainId::eip155(1), &tx)
+            .expect("signed");
         assert!(!signed.is_empty());
         assert_eq!(signed.first().copied(), Some(0x02));
     }
 
+    #[test]
+    fn signs_eip2930_transaction() {
+        let signer = WalletCoreSigner::from_mnemonic(MNEMONIC, "").expect("signer");
+        let tx = EvmTypedTx::Eip2930(
+            EvmEip2930TxBuilder::new(1, 0)
+                .gas_price(2)
+                .gas_limit(21_000)
+                .to(Address([0x11u8; 20]))
+                .value(1)
+                .build(),
+        );
+        let signed = signer
+            .sign_evm_transaction(DEFAULT_PATH, &CaipChainId::eip155(1), &tx)
+            .expect("signed");
+        let decoded = EvmSignedTypedTx::decode(&signed).expect("decoded");
+        assert_eq!(decoded.tx, tx);
+        assert_eq!(
+            decoded.recover_sender().expect("sender"),
+            signer.evm_address(Some(DEFAULT_PATH)).expect("address")
+        );
+
+        // Other accounts sign with their own key.
+        let path = "m/44'/60'/0'/0/1";
+        let signed = signer
+            .sign_evm_transaction(path, &CaipChainId::eip155(1), &tx)
+            .expect("signed");
+        assert_eq!(
+            EvmSignedTypedTx::decode(&signed)
+                .expect("decoded")
+                .recover_sender()
+                .expect("sender"),
+            signer.evm_address(Some(path)).expect("address")
+        );
+    }
+
+    #[test]
+    fn signs_authorization() {
+        let signer = WalletCoreSigner::from_mnemonic(MNEMONIC, "").expect("signer");
+        let authorization = Authorization {
+            chain_id: 1,
+            address: Address([0x42u8; 20]),
+            nonce: 0,
+        };
+        let signed = signer
+            .sign_evm_authorization(DEFAULT_PATH, &authorization)
+            .expect("signed");
+        assert_eq!(signed.authorization, authorization);
+        assert_eq!(
+            signed.recover_authority().expect("authority"),
+            signer.evm_address(Some(DEFAULT_PATH)).expect("address")
+        );
+    }
+
+    #[test]
+    fn signs_personal_message() {
+        let signer = WalletCoreSigner::from_mnemonic(MNEMONIC, "").expect("signer");
+        let signature = signer
+            .sign_evm_personal_message(DEFAULT_PATH, b"hello")
+            .expect("signature");
+        assert_eq!(
+            recover(&personal_message_hash(b"hello"), &signature),
+            signer.evm_address(Some(DEFAULT_PATH)).expect("address")
+        );
+    }
+
+    #[test]
+    fn signs_typed_data() {
+        let signer = WalletCoreSigner::from_mnemonic(MNEMONIC, "").expect("signer");
+        let typed_data = TypedData::from_json(
+            r#"{
+                "types": {
+                    "EIP712Domain": [{"name": "name", "type": "string"}],
+                    "Greeting": [{"name": "contents", "type": "string"}]
+                },
+                "primaryType": "Greeting",
+                "domain": {"name": "ibank"},
+                "message": {"contents": "hello"}
+            }"#,
+        )
+        .expect("typed data");
+        let signature = signer
+            .sign_evm_typed_data(DEFAULT_PATH, &typed_data)
+            .expect("signature");
+        assert_eq!(
+            recover(&typed_data.signing_hash().expect("hash"), &signature),
+            signer.evm_address(Some(DEFAULT_PATH)).expect("address")
+        );
+    }
+
+    /// Recovers the signer of a 65-byte r || s || v signature with v in {27, 28}.
+    fn recover(digest: &[u8; 32], signature: &[u8]) -> Address {
+        assert_eq!(signature.len(), 65);
+        assert!(matches!(signature[64], 27 | 28));
+        let r: [u8; 32] = signature[..32].try_into().expect("r");
+        let s: [u8; 32] = signature[32..64].try_into().expect("s");
+        recover_address(digest, signature[64] - 27, &r, &s).expect("recovered")
+    }
+
     fn hex_to_bytes(hex: &str) -> Vec<u8> {
         let mut out = Vec::with_capacity(hex.len() / 2);
         let mut chars = hex.chars();
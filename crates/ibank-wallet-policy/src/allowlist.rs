@@ -0,0 +1,459 @@
+//! Recipient and contract allowlists and denylists.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use ibank_wallet_chains::{Address, Authorization, EvmUnsignedTx};
+use ibank_wallet_core::{CaipAccountId, CaipChainId, Result, WalletError};
+use serde::{Deserialize, Serialize};
+
+use crate::{PolicyDecision, PolicyEngine, ReasonCode};
+
+/// Restricts the accounts a transaction may touch: the `to` contract or
+/// recipient and, for token calls, the token recipient or approved spender.
+///
+/// Once any account is allowed, only allowed accounts may be used, on every
+/// chain; with an empty allowlist only the denylist applies. Denylist entries
+/// are CAIP-10 ids for a single chain or bare addresses for every chain.
+///
+/// EIP-7702 delegates are checked like recipients. An authorization valid on
+/// every chain (chain id 0) must not be denied anywhere, and is refused once
+/// an allowlist is set, since no per-chain entry covers it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "AllowListConfig", into = "AllowListConfig")]
+pub struct AllowListPolicy {
+    allowed: BTreeMap<CaipChainId, BTreeSet<Address>>,
+    denied: BTreeMap<CaipChainId, BTreeSet<Address>>,
+    denied_everywhere: BTreeSet<Address>,
+    allow_contract_creation: bool,
+}
+
+impl AllowListPolicy {
+    /// Creates an empty policy that allows every recipient.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows an account on its chain.
+    pub fn allow(&mut self, account: &CaipAccountId) -> Result<()> {
+        let address = Address::from_caip10(account)?;
+        self.allowed
+            .entry(account.chain_id().clone())
+            .or_default()
+            .insert(address);
+        Ok(())
+    }
+
+    /// Denies an account on its chain.
+    pub fn deny(&mut self, account: &CaipAccountId) -> Result<()> {
+        let address = Address::from_caip10(account)?;
+        self.denied
+            .entry(account.chain_id().clone())
+            .or_default()
+            .insert(address);
+        Ok(())
+    }
+
+    /// Denies an address on every chain.
+    pub fn deny_everywhere(&mut self, address: Address) {
+        self.denied_everywhere.insert(address);
+    }
+
+    /// Sets whether contract-creation transactions (`to == None`) are allowed.
+    pub fn with_contract_creation(mut self, allowed: bool) -> Self {
+        self.allow_contract_creation = allowed;
+        self
+    }
+
+    /// Adds denylist entries from a `.csv` or `.json` file and returns how
+    /// many were read.
+    ///
+    /// CSV files use the `address` column when there is a header naming one,
+    /// and the first column otherwise. JSON files hold an array of strings or
+    /// of objects with an `address` field.
+    pub fn load_denylist(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|err| {
+            WalletError::InvalidInput(format!("cannot read denylist {}: {err}", path.display()))
+        })?;
+        let entries = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => parse_csv(&contents)?,
+            Some(ext) if ext.eq_ignore_ascii_case("json") => parse_json(&contents)?,
+            _ => {
+                return Err(WalletError::InvalidInput(format!(
+                    "denylist {} must be a .csv or .json file",
+                    path.display()
+                )))
+            }
+        };
+        for entry in &entries {
+            self.add_denied(entry)?;
+        }
+        Ok(entries.len())
+    }
+
+    fn add_denied(&mut self, entry: &str) -> Result<()> {
+        if entry.starts_with("0x") || entry.starts_with("0X") {
+            self.deny_everywhere(entry.parse()?);
+            Ok(())
+        } else {
+            self.deny(&entry.parse()?)
+        }
+    }
+
+    fn check(&self, chain_id: &CaipChainId, address: Address) -> Option<PolicyDecision> {
+        let denied = self.denied_everywhere.contains(&address)
+            || self
+                .denied
+                .get(chain_id)
+                .is_some_and(|set| set.contains(&address));
+        if denied {
+            return Some(
+                PolicyDecision::deny(format!(
+                    "{} is on the denylist",
+                    address.to_caip10(chain_id)
+                ))
+                .with_code(ReasonCode::Denylisted),
+            );
+        }
+        let allowed = self.allowed.is_empty()
+            || self
+                .allowed
+                .get(chain_id)
+                .is_some_and(|set| set.contains(&address));
+        if !allowed {
+            return Some(
+                PolicyDecision::deny(format!(
+                    "{} is not on the allowlist",
+                    address.to_caip10(chain_id)
+                ))
+                .with_code(ReasonCode::NotAllowlisted),
+            );
+        }
+        None
+    }
+}
+
+impl PolicyEngine for AllowListPolicy {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        let chain_id = CaipChainId::eip155(tx.chain_id);
+        let Some(to) = tx.to else {
+            if self.allow_contract_creation {
+                return Ok(PolicyDecision::allow());
+            }
+            return Ok(PolicyDecision::deny(format!(
+                "contract creation is not permitted on {chain_id}"
+            ))
+            .with_code(ReasonCode::ContractCreation));
+        };
+        let mut accounts = vec![to];
+        match tx.token_call() {
+            Ok(Some(call)) => accounts.push(call.recipient()),
+            Ok(None) => {}
+            Err(err) => return Ok(crate::invalid_calldata(err)),
+        }
+        for address in accounts {
+            if let Some(denial) = self.check(&chain_id, address) {
+                return Ok(denial);
+            }
+        }
+        Ok(PolicyDecision::allow())
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        let delegate = authorization.address;
+        if authorization.chain_id != 0 {
+            let chain_id = CaipChainId::eip155(authorization.chain_id);
+            return Ok(self
+                .check(&chain_id, delegate)
+                .unwrap_or_else(PolicyDecision::allow));
+        }
+        let denied = self.denied_everywhere.contains(&delegate)
+            || self.denied.values().any(|set| set.contains(&delegate));
+        if denied {
+            return Ok(
+                PolicyDecision::deny(format!("delegate {delegate} is on the denylist"))
+                    .with_code(ReasonCode::Denylisted),
+            );
+        }
+        if !self.allowed.is_empty() {
+            return Ok(PolicyDecision::deny(format!(
+                "delegate {delegate} is not allowlisted on every chain"
+            ))
+            .with_code(ReasonCode::NotAllowlisted));
+        }
+        Ok(PolicyDecision::allow())
+    }
+}
+
+/// Serialized form of [`AllowListPolicy`].
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AllowListConfig {
+    #[serde(default)]
+    allowed: Vec<CaipAccountId>,
+    #[serde(default)]
+    denied: Vec<String>,
+    #[serde(default)]
+    allow_contract_creation: bool,
+}
+
+impl TryFrom<AllowListConfig> for AllowListPolicy {
+    type Error = WalletError;
+
+    fn try_from(config: AllowListConfig) -> Result<Self> {
+        let mut policy = Self::new().with_contract_creation(config.allow_contract_creation);
+        for account in &config.allowed {
+            policy.allow(account)?;
+        }
+        for entry in &config.denied {
+            policy.add_denied(entry)?;
+        }
+        Ok(policy)
+    }
+}
+
+impl From<AllowListPolicy> for AllowListConfig {
+    fn from(policy: AllowListPolicy) -> Self {
+        let accounts = |sets: &BTreeMap<CaipChainId, BTreeSet<Address>>| {
+            sets.iter()
+                .flat_map(|(chain_id, set)| set.iter().map(|address| address.to_caip10(chain_id)))
+                .collect::<Vec<_>>()
+        };
+        let mut denied: Vec<String> = policy
+            .denied_everywhere
+            .iter()
+            .map(Address::to_string)
+            .collect();
+        denied.extend(accounts(&policy.denied).iter().map(ToString::to_string));
+        Self {
+            allowed: accounts(&policy.allowed),
+            denied,
+            allow_contract_creation: policy.allow_contract_creation,
+        }
+    }
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(contents.as_bytes());
+    let mut column = 0;
+    let mut entries = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record
+            .map_err(|err| WalletError::InvalidInput(format!("invalid denylist csv: {err}")))?;
+        if index == 0 {
+            if let Some(header) = record
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case("address"))
+            {
+                column = header;
+                continue;
+            }
+        }
+        match record.get(column) {
+            Some(field) if !field.is_empty() => entries.push(field.to_string()),
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_json(contents: &str) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Address(String),
+        Object { address: String },
+    }
+
+    let entries: Vec<Entry> = serde_json::from_str(contents)
+        .map_err(|err| WalletError::InvalidInput(format!("invalid denylist json: {err}")))?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Address(address) | Entry::Object { address } => address,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ibank_wallet_chains::{EvmUnsignedTxBuilder, TokenCall, U256};
+
+    const TOKEN: &str = "eip155:1:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const FRIEND: &str = "eip155:1:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    const SANCTIONED: &str = "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359";
+
+    fn address(account: &str) -> Address {
+        Address::from_caip10(&account.parse().expect("account")).expect("address")
+    }
+
+    fn token_transfer(to: Address) -> EvmUnsignedTx {
+        let call = TokenCall::Transfer {
+            to,
+            amount: U256::one(),
+        };
+        EvmUnsignedTxBuilder::new(1, 0)
+            .to(address(TOKEN))
+            .data(call.encode())
+            .build()
+    }
+
+    #[test]
+    fn allows_only_listed_accounts_per_chain() {
+        let mut policy = AllowListPolicy::new();
+        assert!(
+            policy
+                .evaluate_evm(&token_transfer(Address([0x22; 20])))
+                .unwrap()
+                .allowed
+        );
+
+        policy.allow(&TOKEN.parse().unwrap()).unwrap();
+        policy.allow(&FRIEND.parse().unwrap()).unwrap();
+        assert!(
+            policy
+                .evaluate_evm(&token_transfer(address(FRIEND)))
+                .unwrap()
+                .allowed
+        );
+
+        let stranger = policy
+            .evaluate_evm(&token_transfer(Address([0x22; 20])))
+            .unwrap();
+        assert_eq!(
+            stranger.reason.as_deref(),
+            Some("eip155:1:0x2222222222222222222222222222222222222222 is not on the allowlist")
+        );
+
+        let mut on_optimism = token_transfer(address(FRIEND));
+        on_optimism.chain_id = 10;
+        assert!(!policy.evaluate_evm(&on_optimism).unwrap().allowed);
+    }
+
+    #[test]
+    fn handles_contract_creation() {
+        let deploy = EvmUnsignedTxBuilder::new(1, 0).data(vec![0x60]).build();
+        let denied = AllowListPolicy::new().evaluate_evm(&deploy).unwrap();
+        assert_eq!(
+            denied.reason.as_deref(),
+            Some("contract creation is not permitted on eip155:1")
+        );
+        let policy = AllowListPolicy::new().with_contract_creation(true);
+        assert!(policy.evaluate_evm(&deploy).unwrap().allowed);
+    }
+
+    #[test]
+    fn loads_denylists_from_csv_and_json() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let csv_path = dir.path().join("sanctions.csv");
+        std::fs::write(
+            &csv_path,
+            format!("name,address\n# comment\n\"Mixer, Inc\",{SANCTIONED}\nother,eip155:10:0x2222222222222222222222222222222222222222\n"),
+        )
+        .unwrap();
+        let json_path = dir.path().join("extra.json");
+        std::fs::write(
+            &json_path,
+            r#"["0x3333333333333333333333333333333333333333", {"address": "eip155:1:0x4444444444444444444444444444444444444444"}]"#,
+        )
+        .unwrap();
+
+        let mut policy = AllowListPolicy::new();
+        assert_eq!(policy.load_denylist(&csv_path).unwrap(), 2);
+        assert_eq!(policy.load_denylist(&json_path).unwrap(), 2);
+
+        let denied = policy
+            .evaluate_evm(&token_transfer(SANCTIONED.parse().unwrap()))
+            .unwrap();
+        assert_eq!(
+            denied.reason,
+            Some(format!("eip155:1:{SANCTIONED} is on the denylist"))
+        );
+        assert!(
+            !policy
+                .evaluate_evm(&token_transfer(Address([0x44; 20])))
+                .unwrap()
+                .allowed
+        );
+        // Denied on Optimism only.
+        assert!(
+            policy
+                .evaluate_evm(&token_transfer(Address([0x22; 20])))
+                .unwrap()
+                .allowed
+        );
+
+        let bad_path = dir.path().join("bad.json");
+        std::fs::write(&bad_path, r#"["not-an-address"]"#).unwrap();
+        assert!(policy.load_denylist(&bad_path).is_err());
+        assert!(policy.load_denylist(dir.path().join("list.txt")).is_err());
+    }
+
+    #[test]
+    fn checks_delegates_against_both_lists() {
+        let delegation = |chain_id, address| Authorization {
+            chain_id,
+            address,
+            nonce: 0,
+        };
+        let mut policy = AllowListPolicy::new();
+        policy.deny_everywhere(SANCTIONED.parse().unwrap());
+        let decision = policy
+            .evaluate_evm_authorization(&delegation(1, SANCTIONED.parse().unwrap()))
+            .unwrap();
+        assert_eq!(decision.code, Some(ReasonCode::Denylisted));
+        assert!(
+            !policy
+                .evaluate_evm_authorization(&delegation(0, SANCTIONED.parse().unwrap()))
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            policy
+                .evaluate_evm_authorization(&delegation(0, address(FRIEND)))
+                .unwrap()
+                .allowed
+        );
+
+        policy.allow(&FRIEND.parse().unwrap()).unwrap();
+        assert!(
+            policy
+                .evaluate_evm_authorization(&delegation(1, address(FRIEND)))
+                .unwrap()
+                .allowed
+        );
+        for authorization in [
+            delegation(10, address(FRIEND)),
+            delegation(0, address(FRIEND)),
+            delegation(1, Address([0x22; 20])),
+        ] {
+            let decision = policy.evaluate_evm_authorization(&authorization).unwrap();
+            assert_eq!(decision.code, Some(ReasonCode::NotAllowlisted));
+        }
+    }
+
+    #[test]
+    fn round_trips_through_serde() {
+        let policy: AllowListPolicy = serde_json::from_str(&format!(
+            r#"{{"allowed":["{FRIEND}"],"denied":["{SANCTIONED}"],"allow_contract_creation":true}}"#
+        ))
+        .expect("policy");
+        let json = serde_json::to_value(&policy).expect("serialize");
+        assert_eq!(json["allowed"][0], FRIEND);
+        assert_eq!(json["denied"][0], SANCTIONED);
+        assert_eq!(
+            serde_json::from_value::<AllowListPolicy>(json).expect("deserialize"),
+            policy
+        );
+        assert!(
+            serde_json::from_str::<AllowListPolicy>(r#"{"allowed":["eip155:1:0x12"]}"#).is_err()
+        );
+    }
+}
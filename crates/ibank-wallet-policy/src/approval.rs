@@ -0,0 +1,404 @@
+//! M-of-N human approval before signing.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use ibank_wallet_chains::{personal_message_hash, recover_address, Address, EvmUnsignedTx, U256};
+use ibank_wallet_core::{Result, WalletError};
+use serde::{Deserialize, Serialize};
+
+use crate::{transferred_amount, Obligation, PolicyDecision, PolicyEngine, TokenLimit};
+
+/// Requires a quorum of approvers for transfers above a threshold.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalPolicy {
+    /// Native value in wei above which approval is required.
+    #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+    pub min_value: U256,
+    /// Token amounts above which approval is required, per token contract.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_thresholds: Vec<TokenLimit>,
+    /// Approvers that must sign off.
+    pub quorum: Quorum,
+    /// Seconds signing must wait after the intent is held, even once approved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_secs: Option<u64>,
+}
+
+impl ApprovalPolicy {
+    /// Requires `quorum` for native transfers above `min_value`.
+    pub fn new(min_value: impl Into<U256>, quorum: Quorum) -> Self {
+        Self {
+            min_value: min_value.into(),
+            token_thresholds: Vec::new(),
+            quorum,
+            delay_secs: None,
+        }
+    }
+
+    /// Makes approved intents wait `delay_secs` before signing.
+    pub fn with_delay(mut self, delay_secs: u64) -> Self {
+        self.delay_secs = Some(delay_secs);
+        self
+    }
+
+    fn hold(&self, reason: String) -> PolicyDecision {
+        let decision = PolicyDecision::require_approval(self.quorum.clone(), reason);
+        match self.delay_secs {
+            Some(secs) => decision.with_obligation(Obligation::Delay { secs }),
+            None => decision,
+        }
+    }
+
+    /// Requires the quorum for transfers of `token` above `min_amount`.
+    pub fn with_token_threshold(mut self, token: Address, min_amount: impl Into<U256>) -> Self {
+        self.token_thresholds.push(TokenLimit {
+            token,
+            max_amount: min_amount.into(),
+        });
+        self
+    }
+}
+
+impl PolicyEngine for ApprovalPolicy {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        if tx.value > self.min_value {
+            return Ok(self.hold(format!(
+                "value {} exceeds approval threshold {}",
+                tx.value, self.min_value
+            )));
+        }
+        let Some((call, contract)) = tx.token_call().ok().flatten().zip(tx.to) else {
+            return Ok(PolicyDecision::allow());
+        };
+        let token = call.token(contract);
+        let threshold = self
+            .token_thresholds
+            .iter()
+            .find(|threshold| threshold.token == token);
+        match (threshold, transferred_amount(&call)) {
+            (Some(threshold), Some(amount)) if amount > threshold.max_amount => {
+                Ok(self.hold(format!(
+                    "transfer of {amount} exceeds approval threshold {} for token {token}",
+                    threshold.max_amount
+                )))
+            }
+            _ => Ok(PolicyDecision::allow()),
+        }
+    }
+}
+
+/// M-of-N approval rule.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Quorum {
+    /// Number of distinct approvers required (M).
+    pub threshold: usize,
+    /// Eligible approvers (N).
+    pub approvers: Vec<Approver>,
+    /// Seconds a pending intent may wait for approval.
+    pub ttl_secs: u64,
+}
+
+impl Quorum {
+    /// Creates a rule requiring `threshold` of `approvers` within `ttl_secs`.
+    pub fn new(threshold: usize, approvers: Vec<Approver>, ttl_secs: u64) -> Self {
+        Self {
+            threshold,
+            approvers,
+            ttl_secs,
+        }
+    }
+
+    /// Checks that `approval` comes from an eligible approver and signs
+    /// `intent_hash`.
+    pub fn verify(&self, intent_hash: &[u8; 32], approval: &Approval) -> Result<()> {
+        let approver = self
+            .approvers
+            .iter()
+            .find(|approver| approver.id == approval.approver)
+            .ok_or_else(|| {
+                WalletError::PolicyViolation(format!(
+                    "{} is not an approver for this intent",
+                    approval.approver
+                ))
+            })?;
+        approver.key.verify(intent_hash, &approval.signature)
+    }
+
+    /// Returns the distinct approvers whose approvals verify.
+    pub fn approved_by<'a>(
+        &self,
+        intent_hash: &[u8; 32],
+        approvals: &'a [Approval],
+    ) -> BTreeSet<&'a str> {
+        approvals
+            .iter()
+            .filter(|approval| self.verify(intent_hash, approval).is_ok())
+            .map(|approval| approval.approver.as_str())
+            .collect()
+    }
+
+    /// Returns true if enough distinct approvers signed `intent_hash`.
+    pub fn is_met(&self, intent_hash: &[u8; 32], approvals: &[Approval]) -> bool {
+        self.approved_by(intent_hash, approvals).len() >= self.threshold
+    }
+}
+
+/// A person or system allowed to approve intents.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Approver {
+    /// Stable identifier used in approvals and the audit log.
+    pub id: String,
+    /// Key the approver signs with.
+    pub key: ApproverKey,
+}
+
+impl Approver {
+    /// Creates an approver.
+    pub fn new(id: impl Into<String>, key: ApproverKey) -> Self {
+        Self { id: id.into(), key }
+    }
+}
+
+/// Approver signing key, written "ed25519:<hex public key>" or
+/// "secp256k1:<address>".
+///
+/// Ed25519 approvers sign the 32-byte intent hash directly; secp256k1
+/// approvers sign it as an EIP-191 personal message, as wallets do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ApproverKey {
+    Ed25519([u8; 32]),
+    Secp256k1(Address),
+}
+
+impl ApproverKey {
+    fn verify(&self, intent_hash: &[u8; 32], signature: &[u8]) -> Result<()> {
+        let invalid = || WalletError::PolicyViolation("invalid approval signature".to_string());
+        match self {
+            Self::Ed25519(public_key) => {
+                let key = VerifyingKey::from_bytes(public_key).map_err(|_| invalid())?;
+                let signature = Signature::from_slice(signature).map_err(|_| invalid())?;
+                key.verify_strict(intent_hash, &signature)
+                    .map_err(|_| invalid())
+            }
+            Self::Secp256k1(address) => {
+                let signature: &[u8; 65] = signature.try_into().map_err(|_| invalid())?;
+                let (r, rest) = signature.split_at(32);
+                let (s, v) = rest.split_at(32);
+                let y_parity = if v[0] >= 27 { v[0] - 27 } else { v[0] };
+                let recovered = recover_address(
+                    &personal_message_hash(intent_hash),
+                    y_parity,
+                    r.try_into().map_err(|_| invalid())?,
+                    s.try_into().map_err(|_| invalid())?,
+                )
+                .map_err(|_| invalid())?;
+                if recovered == *address {
+                    Ok(())
+                } else {
+                    Err(invalid())
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for ApproverKey {
+    type Err = WalletError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || {
+            WalletError::InvalidInput(format!(
+                "approver key {value:?} must be ed25519:<hex> or secp256k1:<address>"
+            ))
+        };
+        match value.split_once(':').ok_or_else(invalid)? {
+            ("ed25519", key) => {
+                let key = key.strip_prefix("0x").unwrap_or(key);
+                let mut bytes = [0u8; 32];
+                hex::decode_to_slice(key, &mut bytes).map_err(|_| invalid())?;
+                Ok(Self::Ed25519(bytes))
+            }
+            ("secp256k1", address) => Ok(Self::Secp256k1(address.parse()?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for ApproverKey {
+    type Error = WalletError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<ApproverKey> for String {
+    fn from(value: ApproverKey) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for ApproverKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ed25519(key) => write!(f, "ed25519:{}", hex::encode(key)),
+            Self::Secp256k1(address) => write!(f, "secp256k1:{address}"),
+        }
+    }
+}
+
+/// An approver's signature over an intent hash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// Approver id.
+    pub approver: String,
+    /// Ed25519 signature (64 bytes) or secp256k1 r||s||v signature (65 bytes).
+    pub signature: Vec<u8>,
+}
+
+impl Approval {
+    /// Creates an approval.
+    pub fn new(approver: impl Into<String>, signature: Vec<u8>) -> Self {
+        Self {
+            approver: approver.into(),
+            signature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use ibank_wallet_chains::{EvmUnsignedTxBuilder, TokenCall};
+    use k256::ecdsa::SigningKey as SecpKey;
+
+    const HASH: [u8; 32] = [0xab; 32];
+
+    fn ed25519(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn secp256k1(seed: u8) -> SecpKey {
+        SecpKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn secp_address(key: &SecpKey) -> Address {
+        let point = key.verifying_key().to_encoded_point(false);
+        Address::from_public_key(point.as_bytes()).unwrap()
+    }
+
+    fn secp_sign(key: &SecpKey, hash: &[u8; 32]) -> Vec<u8> {
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(&personal_message_hash(hash))
+            .unwrap();
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(recovery_id.to_byte() + 27);
+        bytes
+    }
+
+    fn quorum() -> Quorum {
+        Quorum::new(
+            2,
+            vec![
+                Approver::new(
+                    "alice",
+                    ApproverKey::Ed25519(ed25519(1).verifying_key().to_bytes()),
+                ),
+                Approver::new("bob", ApproverKey::Secp256k1(secp_address(&secp256k1(2)))),
+                Approver::new(
+                    "carol",
+                    ApproverKey::Ed25519(ed25519(3).verifying_key().to_bytes()),
+                ),
+            ],
+            3_600,
+        )
+    }
+
+    #[test]
+    fn counts_distinct_valid_approvals() {
+        let quorum = quorum();
+        let alice = Approval::new("alice", ed25519(1).sign(&HASH).to_bytes().to_vec());
+        let bob = Approval::new("bob", secp_sign(&secp256k1(2), &HASH));
+        quorum.verify(&HASH, &alice).unwrap();
+        quorum.verify(&HASH, &bob).unwrap();
+
+        assert!(!quorum.is_met(&HASH, &[alice.clone(), alice.clone()]));
+        assert!(quorum.is_met(&HASH, &[alice.clone(), bob.clone()]));
+        // Signatures over another intent do not count.
+        assert!(!quorum.is_met(&[0xcd; 32], &[alice.clone(), bob]));
+
+        let forged = Approval::new("carol", ed25519(1).sign(&HASH).to_bytes().to_vec());
+        assert!(quorum.verify(&HASH, &forged).is_err());
+        let outsider = Approval::new("mallory", alice.signature);
+        assert!(quorum.verify(&HASH, &outsider).is_err());
+    }
+
+    #[test]
+    fn requires_approval_above_thresholds() {
+        let token = Address([0x70; 20]);
+        let policy = ApprovalPolicy::new(1_000u64, quorum()).with_token_threshold(token, 50u64);
+        let send = |value: u64| {
+            EvmUnsignedTxBuilder::new(1, 0)
+                .to(Address([0x22; 20]))
+                .value(value)
+                .build()
+        };
+        assert!(policy.evaluate_evm(&send(1_000)).unwrap().allowed);
+        let decision = policy.evaluate_evm(&send(1_001)).unwrap();
+        assert!(decision.requires_approval());
+        assert_eq!(decision.approvals().collect::<Vec<_>>(), [&quorum()]);
+        assert_eq!(decision.delay_secs(), 0);
+        let delayed = policy
+            .clone()
+            .with_delay(600)
+            .evaluate_evm(&send(1_001))
+            .unwrap();
+        assert_eq!(delayed.delay_secs(), 600);
+
+        let transfer = |amount: u64| {
+            EvmUnsignedTxBuilder::new(1, 0)
+                .to(token)
+                .data(
+                    TokenCall::Transfer {
+                        to: Address([0x22; 20]),
+                        amount: U256::from(amount),
+                    }
+                    .encode(),
+                )
+                .build()
+        };
+        assert!(policy.evaluate_evm(&transfer(50)).unwrap().allowed);
+        assert!(policy
+            .evaluate_evm(&transfer(51))
+            .unwrap()
+            .requires_approval());
+    }
+
+    #[test]
+    fn parses_approver_keys() {
+        let key: ApproverKey = "secp256k1:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            key.to_string(),
+            "secp256k1:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        let ed = format!("ed25519:{}", hex::encode([7u8; 32]));
+        assert_eq!(ed.parse::<ApproverKey>().unwrap().to_string(), ed);
+        for bad in [
+            "ed25519:00",
+            "rsa:00",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        ] {
+            assert!(bad.parse::<ApproverKey>().is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,542 @@
+//! Policy combinators and per-chain routing.
+
+use std::collections::BTreeMap;
+
+use ibank_wallet_chains::{Authorization, EvmUnsignedTx};
+use ibank_wallet_core::{CaipChainId, Result};
+
+use crate::{Outcome, PolicyDecision, PolicyEngine, ReasonCode, RuleTrace};
+
+/// Boxed policy engine, as held by the combinators.
+pub type BoxedPolicy = Box<dyn PolicyEngine>;
+
+/// Allows an action only if every policy allows it. All policies are
+/// evaluated so that the decision reports every denial reason; an evaluation
+/// error stops immediately. Without outright denials, the approvals every
+/// policy asks for are all required.
+#[derive(Default)]
+pub struct AllOf {
+    policies: Vec<BoxedPolicy>,
+}
+
+impl AllOf {
+    /// Creates a combinator over `policies`. An empty list allows everything.
+    pub fn new(policies: Vec<BoxedPolicy>) -> Self {
+        Self { policies }
+    }
+
+    /// Adds a policy.
+    pub fn with(mut self, policy: impl PolicyEngine + 'static) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+
+    fn combine(
+        &self,
+        evaluate: impl Fn(&dyn PolicyEngine) -> Result<PolicyDecision>,
+    ) -> Result<PolicyDecision> {
+        let mut trace = Vec::new();
+        let mut reasons = Vec::new();
+        let mut obligations = Vec::new();
+        let (mut first_denial, mut first_hold) = (None, None);
+        for policy in &self.policies {
+            let mut decision = evaluate(policy.as_ref())?;
+            trace.append(&mut decision.trace);
+            let first = match decision.outcome() {
+                Outcome::Allow => continue,
+                Outcome::Hold => {
+                    obligations.append(&mut decision.obligations);
+                    &mut first_hold
+                }
+                Outcome::Deny => &mut first_denial,
+            };
+            first.get_or_insert((decision.code, decision.rule.clone()));
+            reasons.push(denial_reason(decision));
+        }
+        let denied = first_denial.is_some();
+        let Some((code, rule)) = first_denial.or(first_hold) else {
+            let mut decision = PolicyDecision::allow();
+            decision.trace = trace;
+            return Ok(decision);
+        };
+        let mut decision = PolicyDecision::deny(reasons.join("; "));
+        decision.code = code;
+        decision.rule = rule;
+        decision.trace = trace;
+        if !denied {
+            decision.obligations = obligations;
+        }
+        Ok(decision)
+    }
+}
+
+impl PolicyEngine for AllOf {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        self.combine(|policy| policy.evaluate_evm(tx))
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        self.combine(|policy| policy.evaluate_evm_authorization(authorization))
+    }
+}
+
+/// Allows an action if any policy allows it, stopping at the first that
+/// does. Otherwise the first policy asking for approval decides; a denial
+/// reports the reasons of every policy.
+#[derive(Default)]
+pub struct AnyOf {
+    policies: Vec<BoxedPolicy>,
+}
+
+impl AnyOf {
+    /// Creates a combinator over `policies`. An empty list denies everything.
+    pub fn new(policies: Vec<BoxedPolicy>) -> Self {
+        Self { policies }
+    }
+
+    /// Adds a policy.
+    pub fn with(mut self, policy: impl PolicyEngine + 'static) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+
+    fn combine(
+        &self,
+        evaluate: impl Fn(&dyn PolicyEngine) -> Result<PolicyDecision>,
+    ) -> Result<PolicyDecision> {
+        let mut trace = Vec::new();
+        let mut reasons = Vec::new();
+        let mut hold = None;
+        let mut cause = None;
+        for policy in &self.policies {
+            let mut decision = evaluate(policy.as_ref())?;
+            trace.append(&mut decision.trace);
+            match decision.outcome() {
+                Outcome::Allow => {
+                    decision.trace = trace;
+                    return Ok(decision);
+                }
+                Outcome::Hold => {
+                    hold.get_or_insert(decision);
+                }
+                Outcome::Deny => {
+                    cause.get_or_insert((decision.code, decision.rule.clone()));
+                    reasons.push(denial_reason(decision));
+                }
+            }
+        }
+        if let Some(mut decision) = hold {
+            decision.trace = trace;
+            return Ok(decision);
+        }
+        let mut decision = if reasons.is_empty() {
+            PolicyDecision::deny("no policy allows the action")
+        } else {
+            PolicyDecision::deny(format!(
+                "no policy allows the action: {}",
+                reasons.join("; ")
+            ))
+        };
+        if let Some((code, rule)) = cause {
+            decision.code = code;
+            decision.rule = rule;
+        }
+        decision.trace = trace;
+        Ok(decision)
+    }
+}
+
+impl PolicyEngine for AnyOf {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        self.combine(|policy| policy.evaluate_evm(tx))
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        self.combine(|policy| policy.evaluate_evm_authorization(authorization))
+    }
+}
+
+/// Inverts a policy: denies what it allows and allows what it denies.
+/// Held decisions pass through unchanged, so approvals and delays cannot be
+/// inverted away.
+pub struct Not {
+    policy: BoxedPolicy,
+    reason: String,
+}
+
+impl Not {
+    /// Wraps `policy`; `reason` explains denials (e.g. "recipient is a known
+    /// exchange").
+    pub fn new(policy: impl PolicyEngine + 'static, reason: impl Into<String>) -> Self {
+        Self {
+            policy: Box::new(policy),
+            reason: reason.into(),
+        }
+    }
+
+    fn invert(&self, decision: PolicyDecision) -> PolicyDecision {
+        if decision.is_held() {
+            return decision;
+        }
+        let mut inverted = if decision.allowed {
+            PolicyDecision::deny(self.reason.clone()).with_code(ReasonCode::Denied)
+        } else {
+            PolicyDecision::allow()
+        };
+        inverted.trace = decision.trace;
+        inverted
+    }
+}
+
+impl PolicyEngine for Not {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        Ok(self.invert(self.policy.evaluate_evm(tx)?))
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        Ok(self.invert(self.policy.evaluate_evm_authorization(authorization)?))
+    }
+}
+
+/// Selects the policy for a transaction by its CAIP-2 chain id.
+///
+/// Chains without a route use the default policy, or are denied when there is
+/// none. Authorizations valid on every chain (chain id 0) must pass every
+/// route and the default, and are denied when there is no default.
+#[derive(Default)]
+pub struct ChainRouter {
+    routes: BTreeMap<CaipChainId, BoxedPolicy>,
+    default: Option<BoxedPolicy>,
+}
+
+impl ChainRouter {
+    /// Creates a router without routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes transactions on `chain_id` to `policy`.
+    pub fn route(mut self, chain_id: CaipChainId, policy: impl PolicyEngine + 'static) -> Self {
+        self.routes.insert(chain_id, Box::new(policy));
+        self
+    }
+
+    /// Sets the policy for chains without a route.
+    pub fn with_default(mut self, policy: impl PolicyEngine + 'static) -> Self {
+        self.default = Some(Box::new(policy));
+        self
+    }
+
+    fn select(&self, chain_id: &CaipChainId) -> Option<&dyn PolicyEngine> {
+        self.routes
+            .get(chain_id)
+            .or(self.default.as_ref())
+            .map(|policy| policy.as_ref())
+    }
+}
+
+impl PolicyEngine for ChainRouter {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        let chain_id = CaipChainId::eip155(tx.chain_id);
+        match self.select(&chain_id) {
+            Some(policy) => policy.evaluate_evm(tx),
+            None => Ok(no_route(&chain_id)),
+        }
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        if authorization.chain_id != 0 {
+            let chain_id = CaipChainId::eip155(authorization.chain_id);
+            return match self.select(&chain_id) {
+                Some(policy) => policy.evaluate_evm_authorization(authorization),
+                None => Ok(no_route(&chain_id)),
+            };
+        }
+        let Some(default) = &self.default else {
+            return Ok(PolicyDecision::deny(
+                "authorizations valid on every chain need a policy for chains without a route",
+            )
+            .with_code(ReasonCode::ChainNotAllowed));
+        };
+        let mut trace = Vec::new();
+        let mut reasons = Vec::new();
+        let mut cause = None;
+        let policies = self
+            .routes
+            .iter()
+            .map(|(chain_id, policy)| (Some(chain_id), policy))
+            .chain([(None, default)]);
+        for (chain_id, policy) in policies {
+            let mut decision = policy.evaluate_evm_authorization(authorization)?;
+            trace.append(&mut decision.trace);
+            if decision.allowed {
+                continue;
+            }
+            cause.get_or_insert((decision.code, decision.rule.clone()));
+            let reason = denial_reason(decision);
+            reasons.push(match chain_id {
+                Some(chain_id) => format!("{chain_id}: {reason}"),
+                None => reason,
+            });
+        }
+        let mut decision = match cause {
+            Some((code, rule)) => {
+                let mut decision = PolicyDecision::deny(reasons.join("; "));
+                decision.code = code;
+                decision.rule = rule;
+                decision
+            }
+            None => PolicyDecision::allow(),
+        };
+        decision.trace = trace;
+        Ok(decision)
+    }
+}
+
+/// Names a policy: its denials carry the rule id unless a nested rule
+/// already set one, and every evaluation is appended to the decision trace.
+pub struct Rule {
+    id: String,
+    policy: BoxedPolicy,
+}
+
+impl Rule {
+    /// Wraps `policy` under `id` (e.g. "treasury-limit").
+    pub fn new(id: impl Into<String>, policy: impl PolicyEngine + 'static) -> Self {
+        Self {
+            id: id.into(),
+            policy: Box::new(policy),
+        }
+    }
+
+    fn name(&self, mut decision: PolicyDecision) -> PolicyDecision {
+        if !decision.allowed && decision.rule.is_none() {
+            decision.rule = Some(self.id.clone());
+        }
+        decision.trace.push(RuleTrace {
+            rule: self.id.clone(),
+            outcome: decision.outcome(),
+            code: decision.code,
+            reason: decision.reason.clone(),
+        });
+        decision
+    }
+}
+
+impl PolicyEngine for Rule {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        Ok(self.name(self.policy.evaluate_evm(tx)?))
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        Ok(self.name(self.policy.evaluate_evm_authorization(authorization)?))
+    }
+}
+
+fn no_route(chain_id: &CaipChainId) -> PolicyDecision {
+    PolicyDecision::deny(format!("no policy configured for chain {chain_id}"))
+        .with_code(ReasonCode::ChainNotAllowed)
+}
+
+fn denial_reason(decision: PolicyDecision) -> String {
+    decision
+        .reason
+        .unwrap_or_else(|| "policy denied".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllowListPolicy, DelegationPolicy, SpendLimitPolicy};
+    use ibank_wallet_chains::{Address, EvmUnsignedTxBuilder};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Counts evaluations and returns a fixed decision.
+    struct Fixed {
+        decision: PolicyDecision,
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl PolicyEngine for Fixed {
+        fn evaluate_evm(&self, _tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(self.decision.clone())
+        }
+    }
+
+    fn fixed(decision: PolicyDecision) -> (Fixed, Rc<Cell<usize>>) {
+        let calls = Rc::new(Cell::new(0));
+        (
+            Fixed {
+                decision,
+                calls: calls.clone(),
+            },
+            calls,
+        )
+    }
+
+    fn tx(chain_id: u64, value: u64) -> EvmUnsignedTx {
+        EvmUnsignedTxBuilder::new(chain_id, 0)
+            .to(Address([0x22; 20]))
+            .value(value)
+            .build()
+    }
+
+    #[test]
+    fn all_of_reports_every_denial() {
+        let mut allowlist = AllowListPolicy::new();
+        allowlist
+            .allow(&Address([0x33; 20]).to_caip10(&CaipChainId::eip155(1)))
+            .unwrap();
+        let policy = AllOf::default()
+            .with(SpendLimitPolicy::new(100))
+            .with(allowlist);
+
+        let decision = policy.evaluate_evm(&tx(1, 1_000)).unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(
+            decision.reason.as_deref(),
+            Some(
+                "value exceeds spend limit; \
+                 eip155:1:0x2222222222222222222222222222222222222222 is not on the allowlist"
+            )
+        );
+        assert!(AllOf::default().evaluate_evm(&tx(1, 0)).unwrap().allowed);
+    }
+
+    #[test]
+    fn combines_approval_requirements() {
+        let quorum = |threshold| crate::Quorum::new(threshold, Vec::new(), 60);
+        let hold =
+            |threshold| fixed(PolicyDecision::require_approval(quorum(threshold), "large")).0;
+
+        let decision = AllOf::default()
+            .with(hold(1))
+            .with(hold(2))
+            .evaluate_evm(&tx(1, 0))
+            .unwrap();
+        assert!(decision.requires_approval());
+        assert_eq!(
+            decision.approvals().collect::<Vec<_>>(),
+            [&quorum(1), &quorum(2)]
+        );
+        // An outright denial cannot be approved away.
+        let decision = AllOf::default()
+            .with(hold(1))
+            .with(fixed(PolicyDecision::deny("blocked")).0)
+            .evaluate_evm(&tx(1, 0))
+            .unwrap();
+        assert!(!decision.allowed && !decision.requires_approval());
+
+        let decision = AnyOf::default()
+            .with(fixed(PolicyDecision::deny("blocked")).0)
+            .with(hold(3))
+            .evaluate_evm(&tx(1, 0))
+            .unwrap();
+        assert_eq!(decision.approvals().collect::<Vec<_>>(), [&quorum(3)]);
+    }
+
+    #[test]
+    fn any_of_stops_at_first_allow() {
+        let (deny, denied_calls) = fixed(PolicyDecision::deny("first"));
+        let (allow, allowed_calls) = fixed(PolicyDecision::allow());
+        let (never, never_calls) = fixed(PolicyDecision::deny("never"));
+        let policy = AnyOf::default().with(deny).with(allow).with(never);
+        assert!(policy.evaluate_evm(&tx(1, 0)).unwrap().allowed);
+        assert_eq!(
+            (denied_calls.get(), allowed_calls.get(), never_calls.get()),
+            (1, 1, 0)
+        );
+
+        let policy = AnyOf::new(vec![
+            Box::new(SpendLimitPolicy::new(1)),
+            Box::new(Not::new(AllOf::default(), "always denied")),
+        ]);
+        assert_eq!(
+            policy.evaluate_evm(&tx(1, 5)).unwrap().reason.as_deref(),
+            Some("no policy allows the action: value exceeds spend limit; always denied")
+        );
+        assert!(!AnyOf::default().evaluate_evm(&tx(1, 0)).unwrap().allowed);
+    }
+
+    #[test]
+    fn not_inverts_decisions() {
+        let policy = Not::new(
+            SpendLimitPolicy::new(100),
+            "small transfers must be batched",
+        );
+        assert!(policy.evaluate_evm(&tx(1, 1_000)).unwrap().allowed);
+        assert_eq!(
+            policy.evaluate_evm(&tx(1, 10)).unwrap().reason.as_deref(),
+            Some("small transfers must be batched")
+        );
+
+        let quorum = crate::Quorum::new(2, Vec::new(), 60);
+        let held = Not::new(
+            fixed(PolicyDecision::require_approval(quorum.clone(), "large")).0,
+            "inverted",
+        )
+        .evaluate_evm(&tx(1, 0))
+        .unwrap();
+        assert!(!held.allowed);
+        assert_eq!(held.approvals().collect::<Vec<_>>(), [&quorum]);
+    }
+
+    #[test]
+    fn router_selects_policy_by_chain() {
+        let router = ChainRouter::new()
+            .route(CaipChainId::eip155(1), SpendLimitPolicy::new(100))
+            .route(CaipChainId::eip155(10), SpendLimitPolicy::new(10_000));
+        assert!(!router.evaluate_evm(&tx(1, 1_000)).unwrap().allowed);
+        assert!(router.evaluate_evm(&tx(10, 1_000)).unwrap().allowed);
+        assert_eq!(
+            router.evaluate_evm(&tx(137, 0)).unwrap().reason.as_deref(),
+            Some("no policy configured for chain eip155:137")
+        );
+        let router = router.with_default(SpendLimitPolicy::new(0));
+        assert!(router.evaluate_evm(&tx(137, 0)).unwrap().allowed);
+
+        let delegate = Address([0x42; 20]);
+        let router = ChainRouter::new()
+            .route(
+                CaipChainId::eip155(1),
+                DelegationPolicy {
+                    allowed_delegates: vec![delegate],
+                    allow_any_chain: true,
+                },
+            )
+            .route(CaipChainId::eip155(10), DelegationPolicy::default());
+        let authorization = Authorization {
+            chain_id: 1,
+            address: delegate,
+            nonce: 0,
+        };
+        assert!(
+            router
+                .evaluate_evm_authorization(&authorization)
+                .unwrap()
+                .allowed
+        );
+        let any_chain = Authorization {
+            chain_id: 0,
+            ..authorization
+        };
+        // Unrouted chains are denied, so without a default no route covers
+        // every chain an any-chain authorization is valid on.
+        for router in [&ChainRouter::new(), &router] {
+            let decision = router.evaluate_evm_authorization(&any_chain).unwrap();
+            assert!(!decision.allowed);
+            assert_eq!(decision.code, Some(ReasonCode::ChainNotAllowed));
+        }
+        let router = router.with_default(DelegationPolicy {
+            allowed_delegates: vec![delegate],
+            allow_any_chain: true,
+        });
+        let decision = router.evaluate_evm_authorization(&any_chain).unwrap();
+        assert_eq!(
+            decision.reason.as_deref(),
+            Some("eip155:10: cross-chain delegation is not permitted")
+        );
+    }
+}
@@ -0,0 +1,615 @@
+//! Declarative policy documents in TOML, JSON or YAML.
+//!
+//! ```toml
+//! version = 1
+//!
+//! [[rules]]
+//! type = "spend_limit"
+//! max_value = "1000000000000000000"
+//!
+//! [[rules]]
+//! type = "time_window"
+//! days = ["mon", "tue", "wed", "thu", "fri"]
+//! start = "08:00"
+//! end = "18:00"
+//!
+//! [[chains."eip155:1"]]
+//! type = "fee_cap"
+//! max_fee_per_gas = "100000000000"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use ibank_wallet_core::{CaipChainId, Result, WalletError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::approval::ApprovalPolicy;
+use crate::combinators::{AllOf, AnyOf, BoxedPolicy, ChainRouter, Not, Rule};
+use crate::fees::FeeCapPolicy;
+use crate::window::TimeWindowPolicy;
+use crate::{
+    AllowListPolicy, AllowedChainsPolicy, DelegationPolicy, SpendLimitPolicy, TokenApprovalPolicy,
+};
+
+/// Policy document version written by this crate.
+pub const POLICY_DOCUMENT_VERSION: u32 = 1;
+
+/// Serialization format of a policy document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolicyFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl PolicyFormat {
+    /// Picks the format from a file extension (`toml`, `json`, `yaml`, `yml`).
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            _ => Err(WalletError::InvalidInput(format!(
+                "policy file {} must have a .toml, .json, .yaml or .yml extension",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// A versioned policy document: rules for every chain plus per-chain rules.
+///
+/// All `rules` must allow an action. When `chains` is not empty, actions on
+/// chains it does not list are denied and listed chains must also pass their
+/// own rules.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDocument {
+    /// Document format version.
+    pub version: u32,
+    /// Rules applied on every chain.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<PolicyConfig>,
+    /// Rules applied on individual chains.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chains: BTreeMap<CaipChainId, Vec<PolicyConfig>>,
+}
+
+/// One node of a policy tree.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PolicyConfig {
+    /// [`SpendLimitPolicy`].
+    SpendLimit(SpendLimitPolicy),
+    /// [`AllowListPolicy`].
+    AllowList(AllowListPolicy),
+    /// [`TokenApprovalPolicy`].
+    TokenApproval(TokenApprovalPolicy),
+    /// [`DelegationPolicy`].
+    Delegation(DelegationPolicy),
+    /// [`FeeCapPolicy`].
+    FeeCap(FeeCapPolicy),
+    /// [`TimeWindowPolicy`].
+    TimeWindow(TimeWindowPolicy),
+    /// [`AllowedChainsPolicy`].
+    AllowedChains(AllowedChainsPolicy),
+    /// [`ApprovalPolicy`].
+    Approval(ApprovalPolicy),
+    /// [`AllOf`] over nested policies.
+    AllOf { policies: Vec<PolicyConfig> },
+    /// [`AnyOf`] over nested policies.
+    AnyOf { policies: Vec<PolicyConfig> },
+    /// [`Not`] of a nested policy.
+    Not {
+        policy: Box<PolicyConfig>,
+        reason: String,
+    },
+}
+
+impl PolicyDocument {
+    /// Creates an empty document of the current version.
+    pub fn new() -> Self {
+        Self {
+            version: POLICY_DOCUMENT_VERSION,
+            rules: Vec::new(),
+            chains: BTreeMap::new(),
+        }
+    }
+
+    /// Reads and validates a document, picking the format from the extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = PolicyFormat::from_path(path)?;
+        let contents = std::fs::read_to_string(path).map_err(|err| {
+            WalletError::InvalidInput(format!("cannot read policy file {}: {err}", path.display()))
+        })?;
+        Self::parse(&contents, format)
+    }
+
+    /// Parses and validates a document.
+    ///
+    /// Errors name the offending rule (e.g. `rules[1]` or `chains.eip155:1[0]`) and,
+    /// where the format reports it, the line and column.
+    pub fn parse(contents: &str, format: PolicyFormat) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: Option<u32>,
+        }
+
+        let version = deserialize::<Version>(contents, format)?.version;
+        match version {
+            Some(POLICY_DOCUMENT_VERSION) => {}
+            Some(version) => {
+                return Err(WalletError::InvalidInput(format!(
+                    "unsupported policy document version {version} \
+                     (supported: {POLICY_DOCUMENT_VERSION})"
+                )))
+            }
+            None => {
+                return Err(WalletError::InvalidInput(
+                    "policy document has no version".to_string(),
+                ))
+            }
+        }
+        let document: Self = deserialize(contents, format)?;
+        document.validate()?;
+        Ok(document)
+    }
+
+    /// Serializes the document.
+    pub fn to_string_as(&self, format: PolicyFormat) -> Result<String> {
+        let encoded = match format {
+            PolicyFormat::Toml => toml::to_string(self).map_err(|err| err.to_string()),
+            PolicyFormat::Json => serde_json::to_string_pretty(self).map_err(|err| err.to_string()),
+            PolicyFormat::Yaml => serde_yaml::to_string(self).map_err(|err| err.to_string()),
+        };
+        encoded.map_err(|err| {
+            WalletError::InvalidInput(format!("cannot serialize policy document: {err}"))
+        })
+    }
+
+    /// Checks constraints the format cannot express.
+    pub fn validate(&self) -> Result<()> {
+        if self.version != POLICY_DOCUMENT_VERSION {
+            return Err(WalletError::InvalidInput(format!(
+                "unsupported policy document version {}",
+                self.version
+            )));
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            rule.validate(&format!("rules[{index}]"))?;
+        }
+        for (chain_id, rules) in &self.chains {
+            for (index, rule) in rules.iter().enumerate() {
+                rule.validate(&format!("chains.{chain_id}[{index}]"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the policy tree.
+    ///
+    /// Every node is named by its path in the document (e.g. `rules[1]` or
+    /// `chains.eip155:1[0].policies[0]`), which decisions report as the rule id.
+    pub fn build(&self) -> BoxedPolicy {
+        let rules = |prefix: &str, rules: &[PolicyConfig]| {
+            rules
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| rule.build_rule(&format!("{prefix}[{index}]")))
+                .collect()
+        };
+        let mut policy = AllOf::new(rules("rules", &self.rules));
+        if !self.chains.is_empty() {
+            let router =
+                self.chains
+                    .iter()
+                    .fold(ChainRouter::new(), |router, (chain_id, chain_rules)| {
+                        router.route(
+                            chain_id.clone(),
+                            AllOf::new(rules(&format!("chains.{chain_id}"), chain_rules)),
+                        )
+                    });
+            policy = policy.with(router);
+        }
+        Box::new(policy)
+    }
+}
+
+impl Default for PolicyDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolicyConfig {
+    /// Builds the policy this node describes.
+    pub fn build(&self) -> BoxedPolicy {
+        match self {
+            Self::SpendLimit(policy) => Box::new(policy.clone()),
+            Self::AllowList(policy) => Box::new(policy.clone()),
+            Self::TokenApproval(policy) => Box::new(policy.clone()),
+            Self::Delegation(policy) => Box::new(policy.clone()),
+            Self::FeeCap(policy) => Box::new(policy.clone()),
+            Self::TimeWindow(policy) => Box::new(policy.clone()),
+            Self::AllowedChains(policy) => Box::new(policy.clone()),
+            Self::Approval(policy) => Box::new(policy.clone()),
+            Self::AllOf { policies } => {
+                Box::new(AllOf::new(policies.iter().map(Self::build).collect()))
+            }
+            Self::AnyOf { policies } => {
+                Box::new(AnyOf::new(policies.iter().map(Self::build).collect()))
+            }
+            Self::Not { policy, reason } => Box::new(Not::new(policy.build(), reason.clone())),
+        }
+    }
+
+    /// Builds the policy this node describes, naming it and its nested
+    /// nodes with [`Rule`] ids rooted at `id`.
+    pub fn build_rule(&self, id: &str) -> BoxedPolicy {
+        let nested = |policies: &[PolicyConfig]| {
+            policies
+                .iter()
+                .enumerate()
+                .map(|(index, policy)| policy.build_rule(&format!("{id}.policies[{index}]")))
+                .collect()
+        };
+        let policy: BoxedPolicy = match self {
+            Self::AllOf { policies } => Box::new(AllOf::new(nested(policies))),
+            Self::AnyOf { policies } => Box::new(AnyOf::new(nested(policies))),
+            Self::Not { policy, reason } => Box::new(Not::new(
+                policy.build_rule(&format!("{id}.policy")),
+                reason.clone(),
+            )),
+            _ => self.build(),
+        };
+        Box::new(Rule::new(id, policy))
+    }
+
+    fn validate(&self, path: &str) -> Result<()> {
+        let invalid = |field: &str, detail: &str| {
+            WalletError::InvalidInput(format!("{path}{field}: {detail}"))
+        };
+        match self {
+            Self::SpendLimit(policy) => {
+                for (index, limit) in policy.token_limits.iter().enumerate() {
+                    let duplicate = policy.token_limits[..index]
+                        .iter()
+                        .any(|other| other.token == limit.token);
+                    if duplicate {
+                        return Err(invalid(
+                            &format!(".token_limits[{index}].token"),
+                            &format!("duplicate limit for token {}", limit.token),
+                        ));
+                    }
+                }
+            }
+            Self::TimeWindow(policy) if policy.start == policy.end => {
+                return Err(invalid(".end", "window must not be empty"));
+            }
+            Self::FeeCap(policy) => {
+                for (chain_id, caps) in &policy.chains {
+                    if !caps.chains.is_empty() {
+                        return Err(invalid(
+                            &format!(".chains.{chain_id}.chains"),
+                            "per-chain caps must not be nested",
+                        ));
+                    }
+                }
+            }
+            Self::Approval(policy) => {
+                let quorum = &policy.quorum;
+                if quorum.threshold == 0 || quorum.threshold > quorum.approvers.len() {
+                    return Err(invalid(
+                        ".quorum.threshold",
+                        &format!(
+                            "must be between 1 and the {} approvers",
+                            quorum.approvers.len()
+                        ),
+                    ));
+                }
+                for (index, approver) in quorum.approvers.iter().enumerate() {
+                    if quorum.approvers[..index]
+                        .iter()
+                        .any(|other| other.id == approver.id)
+                    {
+                        return Err(invalid(
+                            &format!(".quorum.approvers[{index}].id"),
+                            &format!("duplicate approver {}", approver.id),
+                        ));
+                    }
+                }
+                if quorum.ttl_secs == 0 {
+                    return Err(invalid(".quorum.ttl_secs", "must not be zero"));
+                }
+                if policy
+                    .delay_secs
+                    .is_some_and(|delay| delay >= quorum.ttl_secs)
+                {
+                    return Err(invalid(
+                        ".delay_secs",
+                        "must be shorter than the quorum ttl_secs",
+                    ));
+                }
+            }
+            Self::AllowedChains(policy) if policy.chains.is_empty() => {
+                return Err(invalid(".chains", "must list at least one chain"));
+            }
+            Self::AllOf { policies } | Self::AnyOf { policies } => {
+                if policies.is_empty() {
+                    return Err(invalid(".policies", "must list at least one policy"));
+                }
+                for (index, policy) in policies.iter().enumerate() {
+                    policy.validate(&format!("{path}.policies[{index}]"))?;
+                }
+            }
+            Self::Not { policy, reason } => {
+                if reason.trim().is_empty() {
+                    return Err(invalid(".reason", "must not be empty"));
+                }
+                policy.validate(&format!("{path}.policy"))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(contents: &str, format: PolicyFormat) -> Result<T> {
+    let result = match format {
+        PolicyFormat::Toml => {
+            let deserializer = toml::Deserializer::parse(contents).map_err(|err| err.to_string());
+            deserializer.and_then(|deserializer| {
+                serde_path_to_error::deserialize(deserializer).map_err(located)
+            })
+        }
+        PolicyFormat::Json => {
+            let mut deserializer = serde_json::Deserializer::from_str(contents);
+            serde_path_to_error::deserialize(&mut deserializer).map_err(located)
+        }
+        PolicyFormat::Yaml => {
+            serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(contents))
+                .map_err(located)
+        }
+    };
+    result.map_err(|err| WalletError::InvalidInput(format!("invalid policy document: {err}")))
+}
+
+fn located<E: std::fmt::Display>(err: serde_path_to_error::Error<E>) -> String {
+    let path = err.path().to_string();
+    if path == "." {
+        err.inner().to_string()
+    } else {
+        format!("at {path}: {}", err.inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Outcome, PolicyEngine};
+    use ibank_wallet_chains::{Address, EvmUnsignedTxBuilder, U256};
+
+    const TOML: &str = r#"
+version = 1
+
+[[rules]]
+type = "spend_limit"
+max_value = "1000"
+token_limits = [
+    { token = "0x7070707070707070707070707070707070707070", max_amount = 50 },
+]
+
+[[rules]]
+type = "any_of"
+policies = [
+    { type = "allow_list", allowed = ["eip155:1:0x2222222222222222222222222222222222222222"] },
+    { type = "not", reason = "cheap", policy = { type = "spend_limit", max_value = "0x0" } },
+]
+
+[[chains."eip155:1"]]
+type = "fee_cap"
+max_gas_limit = 100000
+max_total_fee = "10000000000000000"
+
+[[chains."eip155:10"]]
+type = "time_window"
+start = "00:00"
+end = "23:59"
+"#;
+
+    fn tx(chain_id: u64, to: u8, value: u64) -> ibank_wallet_chains::EvmUnsignedTx {
+        EvmUnsignedTxBuilder::new(chain_id, 0)
+            .to(Address([to; 20]))
+            .value(value)
+            .gas_limit(21_000u64)
+            .build()
+    }
+
+    #[test]
+    fn builds_policy_tree_from_toml() {
+        let document = PolicyDocument::parse(TOML, PolicyFormat::Toml).expect("document");
+        assert_eq!(document.rules.len(), 2);
+        let PolicyConfig::SpendLimit(limit) = &document.rules[0] else {
+            panic!("expected a spend limit");
+        };
+        assert_eq!(limit.max_value, U256::from(1_000u64));
+        assert_eq!(limit.token_limits[0].max_amount, U256::from(50u64));
+
+        let policy = document.build();
+        assert!(policy.evaluate_evm(&tx(1, 0x22, 10)).unwrap().allowed);
+        // Any value is fine for other recipients as long as it is not zero.
+        assert!(policy.evaluate_evm(&tx(1, 0x33, 10)).unwrap().allowed);
+        assert_eq!(
+            policy
+                .evaluate_evm(&tx(1, 0x33, 0))
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some(
+                "no policy allows the action: \
+                 eip155:1:0x3333333333333333333333333333333333333333 is not on the allowlist; cheap"
+            )
+        );
+        let denied = policy.evaluate_evm(&tx(1, 0x33, 0)).unwrap();
+        assert_eq!(denied.rule.as_deref(), Some("rules[1].policies[0]"));
+        assert_eq!(denied.code, Some(crate::ReasonCode::NotAllowlisted));
+        let trace: Vec<_> = denied
+            .trace
+            .iter()
+            .map(|entry| (entry.rule.as_str(), entry.outcome))
+            .collect();
+        assert_eq!(
+            trace,
+            [
+                ("rules[0]", Outcome::Allow),
+                ("rules[1].policies[0]", Outcome::Deny),
+                ("rules[1].policies[1].policy", Outcome::Allow),
+                ("rules[1].policies[1]", Outcome::Deny),
+                ("rules[1]", Outcome::Deny),
+                ("chains.eip155:1[0]", Outcome::Allow),
+            ]
+        );
+
+        let over_limit = policy.evaluate_evm(&tx(1, 0x22, 5_000)).unwrap();
+        assert_eq!(
+            crate::enforce(over_limit).unwrap_err().to_string(),
+            "policy violation: rules[0] (spend_limit): value exceeds spend limit"
+        );
+        assert_eq!(
+            policy
+                .evaluate_evm(&tx(137, 0x22, 10))
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("no policy configured for chain eip155:137")
+        );
+    }
+
+    #[test]
+    fn chain_rules_deny_delegation_on_every_chain() {
+        let document = PolicyDocument::parse(
+            r#"
+version = 1
+
+[[chains."eip155:1"]]
+type = "delegation"
+allowed_delegates = ["0x4242424242424242424242424242424242424242"]
+allow_any_chain = true
+"#,
+            PolicyFormat::Toml,
+        )
+        .expect("document");
+        let policy = document.build();
+        let single_chain = ibank_wallet_chains::Authorization {
+            chain_id: 1,
+            address: Address([0x42; 20]),
+            nonce: 0,
+        };
+        assert!(
+            policy
+                .evaluate_evm_authorization(&single_chain)
+                .unwrap()
+                .allowed
+        );
+        // Other chains have no route, so an authorization valid on all of
+        // them is denied.
+        let any_chain = ibank_wallet_chains::Authorization {
+            chain_id: 0,
+            ..single_chain
+        };
+        let denied = policy.evaluate_evm_authorization(&any_chain).unwrap();
+        assert_eq!(denied.code, Some(crate::ReasonCode::ChainNotAllowed));
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        let document = PolicyDocument::parse(TOML, PolicyFormat::Toml).expect("document");
+        for format in [PolicyFormat::Toml, PolicyFormat::Json, PolicyFormat::Yaml] {
+            let encoded = document.to_string_as(format).expect("serialize");
+            assert_eq!(
+                PolicyDocument::parse(&encoded, format).expect("parse"),
+                document,
+                "{format:?}: {encoded}"
+            );
+        }
+        let json = document.to_string_as(PolicyFormat::Json).unwrap();
+        assert!(json.contains(r#""max_value": "0x3e8""#), "{json}");
+    }
+
+    #[test]
+    fn reports_error_locations() {
+        let err = |contents: &str, format| {
+            PolicyDocument::parse(contents, format)
+                .expect_err("invalid document")
+                .to_string()
+        };
+
+        let typo = err(
+            r#"{"version": 1, "rules": [{"type": "spend_limit", "max_vaule": "1"}]}"#,
+            PolicyFormat::Json,
+        );
+        assert!(
+            typo.contains("rules[0]") && typo.contains("max_vaule"),
+            "{typo}"
+        );
+
+        let bad_address = err(
+            "version: 1\nrules:\n  - type: allow_list\n    allowed: [\"eip155:1:0x12\"]\n",
+            PolicyFormat::Yaml,
+        );
+        assert!(bad_address.contains("rules[0]"), "{bad_address}");
+        assert!(bad_address.contains("line 3"), "{bad_address}");
+
+        let bad_time = err(
+            "version = 1\n[[rules]]\ntype = \"time_window\"\nstart = \"25:00\"\nend = \"01:00\"\n",
+            PolicyFormat::Toml,
+        );
+        assert!(
+            bad_time.contains("rules[0]") && bad_time.contains("25:00"),
+            "{bad_time}"
+        );
+
+        let empty = err(
+            r#"{"version": 1, "chains": {"eip155:1": [{"type": "not", "reason": "x", "policy": {"type": "any_of", "policies": []}}]}}"#,
+            PolicyFormat::Json,
+        );
+        assert!(
+            empty.contains("chains.eip155:1[0].policy.policies: must list at least one policy"),
+            "{empty}"
+        );
+
+        let nested = err(
+            r#"{"version": 1, "rules": [{"type": "fee_cap", "chains": {"eip155:10": {"chains": {"eip155:1": {}}}}}]}"#,
+            PolicyFormat::Json,
+        );
+        assert!(
+            nested.contains("rules[0].chains.eip155:10.chains: per-chain caps must not be nested"),
+            "{nested}"
+        );
+
+        let quorum = err(
+            r#"{"version": 1, "rules": [{"type": "approval", "min_value": "0", "quorum": {"threshold": 2, "ttl_secs": 60, "approvers": [{"id": "a", "key": "secp256k1:0x2222222222222222222222222222222222222222"}]}}]}"#,
+            PolicyFormat::Json,
+        );
+        assert!(
+            quorum.contains("rules[0].quorum.threshold: must be between 1 and the 1 approvers"),
+            "{quorum}"
+        );
+
+        assert!(err(r#"{"version": 2, "rules": "new"}"#, PolicyFormat::Json)
+            .contains("unsupported policy document version 2"));
+        assert!(err(r#"{"rules": []}"#, PolicyFormat::Json).contains("no version"));
+    }
+
+    #[test]
+    fn loads_files_by_extension() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("policy.toml");
+        std::fs::write(&path, TOML).unwrap();
+        assert_eq!(PolicyDocument::load(&path).unwrap().chains.len(), 2);
+        assert!(PolicyDocument::load(dir.path().join("policy.ini")).is_err());
+    }
+}
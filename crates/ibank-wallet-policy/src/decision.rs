@@ -0,0 +1,257 @@
+//! Policy decisions, reason codes, obligations and evaluation traces.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::approval::Quorum;
+
+/// Policy decision result for an intent.
+///
+/// A decision that is not allowed but carries obligations is held: the
+/// action may proceed once every obligation is met.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    /// True if the action is allowed.
+    pub allowed: bool,
+    /// Optional reason for denial.
+    pub reason: Option<String>,
+    /// Machine-readable reason for denial.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ReasonCode>,
+    /// Id of the rule that denied or held the action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Conditions to meet before the action may proceed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub obligations: Vec<Obligation>,
+    /// Outcome of every named rule evaluated, innermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trace: Vec<RuleTrace>,
+}
+
+impl PolicyDecision {
+    /// Allows the action.
+    pub fn allow() -> Self {
+        Self {
+            allowed: true,
+            reason: None,
+            code: None,
+            rule: None,
+            obligations: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// Denies the action with a reason.
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Self {
+            allowed: false,
+            reason: Some(reason.into()),
+            ..Self::allow()
+        }
+    }
+
+    /// Holds the action until `quorum` approves it.
+    pub fn require_approval(quorum: Quorum, reason: impl Into<String>) -> Self {
+        Self::deny(reason)
+            .with_code(ReasonCode::ApprovalRequired)
+            .with_obligation(Obligation::Approval { quorum })
+    }
+
+    /// Sets the reason code.
+    pub fn with_code(mut self, code: ReasonCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Sets the rule id.
+    pub fn with_rule(mut self, rule: impl Into<String>) -> Self {
+        self.rule = Some(rule.into());
+        self
+    }
+
+    /// Adds an obligation.
+    pub fn with_obligation(mut self, obligation: Obligation) -> Self {
+        self.obligations.push(obligation);
+        self
+    }
+
+    /// Returns the outcome.
+    pub fn outcome(&self) -> Outcome {
+        match (self.allowed, self.obligations.is_empty()) {
+            (true, _) => Outcome::Allow,
+            (false, true) => Outcome::Deny,
+            (false, false) => Outcome::Hold,
+        }
+    }
+
+    /// Returns true if the action may proceed once its obligations are met.
+    pub fn is_held(&self) -> bool {
+        self.outcome() == Outcome::Hold
+    }
+
+    /// Returns true if the action is held for approval.
+    pub fn requires_approval(&self) -> bool {
+        self.is_held() && self.approvals().next().is_some()
+    }
+
+    /// Returns the quorums that must approve.
+    pub fn approvals(&self) -> impl Iterator<Item = &Quorum> {
+        self.obligations
+            .iter()
+            .filter_map(|obligation| match obligation {
+                Obligation::Approval { quorum } => Some(quorum),
+                Obligation::Delay { .. } => None,
+            })
+    }
+
+    /// Returns the longest delay required, in seconds.
+    pub fn delay_secs(&self) -> u64 {
+        self.obligations
+            .iter()
+            .filter_map(|obligation| match obligation {
+                Obligation::Delay { secs } => Some(*secs),
+                Obligation::Approval { .. } => None,
+            })
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Result of evaluating a policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Allow,
+    Deny,
+    Hold,
+}
+
+/// Machine-readable reason for a denial or hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonCode {
+    /// Native value or token amount above a per-transaction limit.
+    SpendLimit,
+    /// Token transfer without a configured limit.
+    UnlistedToken,
+    /// Spend over a rolling window above a velocity limit.
+    VelocityLimit,
+    /// Recipient is on a denylist.
+    Denylisted,
+    /// Recipient is not on an allowlist.
+    NotAllowlisted,
+    /// Contract creation is not permitted.
+    ContractCreation,
+    /// Approval spender is not allowed.
+    SpenderNotAllowed,
+    /// Unlimited token approval.
+    UnlimitedApproval,
+    /// EIP-7702 delegate or chain scope is not allowed.
+    DelegationNotAllowed,
+    /// Chain is not permitted or has no policy.
+    ChainNotAllowed,
+    /// Gas limit or fee above a cap.
+    FeeCap,
+    /// Fee fields are inconsistent.
+    InvalidFee,
+    /// Outside the signing time window.
+    OutsideTimeWindow,
+    /// Calldata could not be decoded.
+    InvalidCalldata,
+    /// Action needs approval.
+    ApprovalRequired,
+    /// Denied by an inverted or custom rule.
+    Denied,
+}
+
+impl ReasonCode {
+    /// Returns the snake_case name used in serialized decisions.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SpendLimit => "spend_limit",
+            Self::UnlistedToken => "unlisted_token",
+            Self::VelocityLimit => "velocity_limit",
+            Self::Denylisted => "denylisted",
+            Self::NotAllowlisted => "not_allowlisted",
+            Self::ContractCreation => "contract_creation",
+            Self::SpenderNotAllowed => "spender_not_allowed",
+            Self::UnlimitedApproval => "unlimited_approval",
+            Self::DelegationNotAllowed => "delegation_not_allowed",
+            Self::ChainNotAllowed => "chain_not_allowed",
+            Self::FeeCap => "fee_cap",
+            Self::InvalidFee => "invalid_fee",
+            Self::OutsideTimeWindow => "outside_time_window",
+            Self::InvalidCalldata => "invalid_calldata",
+            Self::ApprovalRequired => "approval_required",
+            Self::Denied => "denied",
+        }
+    }
+}
+
+impl fmt::Display for ReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Condition a held action must meet before it may proceed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Obligation {
+    /// The quorum must approve the intent.
+    Approval {
+        /// Required approvers.
+        quorum: Quorum,
+    },
+    /// Signing must wait `secs` seconds after the action is first held.
+    Delay {
+        /// Delay in seconds.
+        secs: u64,
+    },
+}
+
+/// Outcome of one named rule, as recorded in a decision trace.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleTrace {
+    /// Rule id.
+    pub rule: String,
+    /// What the rule decided.
+    pub outcome: Outcome,
+    /// Reason code, if denied or held.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ReasonCode>,
+    /// Reason, if denied or held.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_outcomes_and_obligations() {
+        assert_eq!(PolicyDecision::allow().outcome(), Outcome::Allow);
+        assert_eq!(PolicyDecision::deny("no").outcome(), Outcome::Deny);
+
+        let quorum = Quorum::new(1, Vec::new(), 60);
+        let held = PolicyDecision::require_approval(quorum.clone(), "large")
+            .with_obligation(Obligation::Delay { secs: 600 });
+        assert!(held.requires_approval());
+        assert_eq!(held.approvals().collect::<Vec<_>>(), [&quorum]);
+        assert_eq!(held.delay_secs(), 600);
+
+        let json = serde_json::to_value(&held).unwrap();
+        assert_eq!(json["code"], "approval_required");
+        assert_eq!(
+            json["obligations"][1],
+            serde_json::json!({"type": "delay", "secs": 600})
+        );
+        assert_eq!(
+            serde_json::from_value::<PolicyDecision>(json).unwrap(),
+            held
+        );
+    }
+}
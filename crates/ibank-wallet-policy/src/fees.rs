@@ -0,0 +1,249 @@
+//! Gas and fee caps.
+
+use std::collections::BTreeMap;
+
+use ibank_wallet_chains::{EvmUnsignedTx, U256};
+use ibank_wallet_core::{CaipChainId, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{PolicyDecision, PolicyEngine, ReasonCode};
+
+/// Caps the gas and fees EVM transactions may burn.
+///
+/// The worst-case fee is `gas_limit * max_fee_per_gas`. Transactions whose
+/// priority fee exceeds their max fee are always denied. Entries in `chains`
+/// replace the top-level caps for that chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeCapPolicy {
+    /// Maximum gas limit.
+    #[serde(
+        default,
+        with = "ibank_wallet_chains::quantity::decimal::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_gas_limit: Option<U256>,
+    /// Maximum `max_fee_per_gas` in wei.
+    #[serde(
+        default,
+        with = "ibank_wallet_chains::quantity::decimal::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_fee_per_gas: Option<U256>,
+    /// Maximum worst-case fee in wei.
+    #[serde(
+        default,
+        with = "ibank_wallet_chains::quantity::decimal::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_total_fee: Option<U256>,
+    /// Maximum worst-case fee in basis points of the native value sent.
+    /// Only applies to transactions carrying value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_to_value_bps: Option<u32>,
+    /// Per-chain caps.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chains: BTreeMap<CaipChainId, FeeCapPolicy>,
+}
+
+impl FeeCapPolicy {
+    /// Sets the caps used on `chain_id` instead of the top-level ones.
+    pub fn with_chain(mut self, chain_id: CaipChainId, caps: FeeCapPolicy) -> Self {
+        self.chains.insert(chain_id, caps);
+        self
+    }
+
+    fn check(&self, tx: &EvmUnsignedTx) -> Option<String> {
+        if let Some(cap) = self.max_gas_limit.filter(|cap| tx.gas_limit > *cap) {
+            return Some(format!("gas limit {} exceeds cap {cap}", tx.gas_limit));
+        }
+        if let Some(cap) = self.max_fee_per_gas.filter(|cap| tx.max_fee_per_gas > *cap) {
+            return Some(format!(
+                "max fee per gas {} exceeds cap {cap}",
+                tx.max_fee_per_gas
+            ));
+        }
+        if self.max_total_fee.is_none() && self.max_fee_to_value_bps.is_none() {
+            return None;
+        }
+        let Some(fee) = tx.gas_limit.checked_mul(tx.max_fee_per_gas) else {
+            return Some("worst-case fee overflows".to_string());
+        };
+        if let Some(cap) = self.max_total_fee.filter(|cap| fee > *cap) {
+            return Some(format!("worst-case fee {fee} wei exceeds cap {cap}"));
+        }
+        if let Some(bps) = self.max_fee_to_value_bps.filter(|_| !tx.value.is_zero()) {
+            // fee / value > bps / 10_000, compared without division.
+            let too_high = fee
+                .checked_mul(U256::from(10_000u64))
+                .is_none_or(|scaled| scaled > tx.value.saturating_mul(U256::from(bps)));
+            if too_high {
+                return Some(format!(
+                    "worst-case fee {fee} wei exceeds {bps} bps of value {}",
+                    tx.value
+                ));
+            }
+        }
+        None
+    }
+}
+
+impl PolicyEngine for FeeCapPolicy {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        if tx.max_priority_fee_per_gas > tx.max_fee_per_gas {
+            return Ok(PolicyDecision::deny(format!(
+                "max priority fee per gas {} exceeds max fee per gas {}",
+                tx.max_priority_fee_per_gas, tx.max_fee_per_gas
+            ))
+            .with_code(ReasonCode::InvalidFee));
+        }
+        let caps = self
+            .chains
+            .get(&CaipChainId::eip155(tx.chain_id))
+            .unwrap_or(self);
+        Ok(match caps.check(tx) {
+            Some(reason) => PolicyDecision::deny(reason).with_code(ReasonCode::FeeCap),
+            None => PolicyDecision::allow(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ibank_wallet_chains::EvmUnsignedTxBuilder;
+
+    #[test]
+    fn caps_gas_limit_and_fee_per_gas() {
+        let policy = FeeCapPolicy {
+            max_gas_limit: Some(U256::from(100_000u64)),
+            max_fee_per_gas: Some(U256::from(50_000_000_000u64)),
+            ..FeeCapPolicy::default()
+        };
+        let tx = |gas_limit: u64, max_fee: u64| {
+            EvmUnsignedTxBuilder::new(1, 0)
+                .gas_limit(gas_limit)
+                .max_fee_per_gas(max_fee)
+                .build()
+        };
+        assert!(
+            policy
+                .evaluate_evm(&tx(21_000, 30_000_000_000))
+                .unwrap()
+                .allowed
+        );
+        assert_eq!(
+            policy
+                .evaluate_evm(&tx(200_000, 1))
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("gas limit 200000 exceeds cap 100000")
+        );
+        assert!(
+            !policy
+                .evaluate_evm(&tx(21_000, 60_000_000_000))
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            FeeCapPolicy::default()
+                .evaluate_evm(&tx(u64::MAX, u64::MAX))
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[test]
+    fn caps_worst_case_fee_absolutely_and_relative_to_value() {
+        let policy = FeeCapPolicy {
+            // 0.01 ETH, and at most 1% of the value sent.
+            max_total_fee: Some(U256::from(10_000_000_000_000_000u64)),
+            max_fee_to_value_bps: Some(100),
+            ..FeeCapPolicy::default()
+        };
+        let tx = |gas_limit: u64, max_fee: u64, value: u64| {
+            EvmUnsignedTxBuilder::new(1, 0)
+                .gas_limit(gas_limit)
+                .max_fee_per_gas(max_fee)
+                .value(value)
+                .build()
+        };
+        const ETHER: u64 = 1_000_000_000_000_000_000;
+
+        // 21000 * 100 gwei = 0.0021 ETH.
+        assert!(
+            policy
+                .evaluate_evm(&tx(21_000, 100_000_000_000, ETHER))
+                .unwrap()
+                .allowed
+        );
+        assert_eq!(
+            policy
+                .evaluate_evm(&tx(1_000_000, 100_000_000_000, ETHER))
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("worst-case fee 100000000000000000 wei exceeds cap 10000000000000000")
+        );
+        assert_eq!(
+            policy
+                .evaluate_evm(&tx(21_000, 100_000_000_000, ETHER / 10))
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("worst-case fee 2100000000000000 wei exceeds 100 bps of value 100000000000000000")
+        );
+        // Token transfers and calls carry no value to compare against.
+        assert!(
+            policy
+                .evaluate_evm(&tx(21_000, 100_000_000_000, 0))
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            !policy
+                .evaluate_evm(&tx(u64::MAX, u64::MAX, 1))
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[test]
+    fn rejects_priority_fee_above_max_fee() {
+        let tx = EvmUnsignedTxBuilder::new(1, 0)
+            .max_priority_fee_per_gas(3u64)
+            .max_fee_per_gas(2u64)
+            .build();
+        assert_eq!(
+            FeeCapPolicy::default()
+                .evaluate_evm(&tx)
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("max priority fee per gas 3 exceeds max fee per gas 2")
+        );
+    }
+
+    #[test]
+    fn applies_per_chain_caps() {
+        let policy = FeeCapPolicy {
+            max_fee_per_gas: Some(U256::from(100_000_000_000u64)),
+            ..FeeCapPolicy::default()
+        }
+        .with_chain(
+            CaipChainId::eip155(10),
+            FeeCapPolicy {
+                max_fee_per_gas: Some(U256::from(1_000_000_000u64)),
+                ..FeeCapPolicy::default()
+            },
+        );
+        let tx = |chain_id: u64| {
+            EvmUnsignedTxBuilder::new(chain_id, 0)
+                .max_fee_per_gas(50_000_000_000u64)
+                .build()
+        };
+        assert!(policy.evaluate_evm(&tx(1)).unwrap().allowed);
+        assert!(!policy.evaluate_evm(&tx(10)).unwrap().allowed);
+    }
+}
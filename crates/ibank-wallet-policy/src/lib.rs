@@ -1,66 +1,456 @@
 //! Policy engine skeleton.
 
-use ibank_wallet_chains::EvmUnsignedTx;
-use ibank_wallet_core::{Result, WalletError};
-use serde::{Deserialize, Serialize};
+pub mod allowlist;
+pub mod approval;
+pub mod combinators;
+pub mod config;
+pub mod decision;
+pub mod fees;
+pub mod velocity;
+pub mod window;
 
-/// Policy decision result for an intent.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct PolicyDecision {
-    /// True if the action is allowed.
-    pub allowed: bool,
-    /// Optional reason for denial.
-    pub reason: Option<String>,
-}
+pub use allowlist::AllowListPolicy;
+pub use approval::{Approval, ApprovalPolicy, Approver, ApproverKey, Quorum};
+pub use combinators::{AllOf, AnyOf, BoxedPolicy, ChainRouter, Not, Rule};
+pub use config::{PolicyConfig, PolicyDocument, PolicyFormat, POLICY_DOCUMENT_VERSION};
+pub use decision::{Obligation, Outcome, PolicyDecision, ReasonCode, RuleTrace};
+pub use fees::FeeCapPolicy;
+pub use velocity::{
+    FileSpendLedger, MemorySpendLedger, SpendLedger, SpendRecord, TokenSpend, VelocityLimit,
+    VelocityPolicy,
+};
+pub use window::{TimeOfDay, TimeWindowPolicy, Weekday};
+
+use ibank_wallet_chains::{Address, Authorization, EvmUnsignedTx, TokenCall, U256};
+use ibank_wallet_core::{CaipChainId, Result, WalletError};
+use serde::{Deserialize, Serialize};
 
 /// A policy engine that can evaluate EVM transactions.
 pub trait PolicyEngine {
-    /// Evaluates an EVM transaction intent.
+    /// Evaluates an EVM transaction intent. Token transfers arrive as calls to
+    /// the token contract; `EvmUnsignedTx::token_call` recovers the recipient
+    /// and amount.
     fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision>;
+
+    /// Evaluates an EIP-7702 authorization delegating the account's code to
+    /// `authorization.address`. Engines that do not restrict delegation allow it.
+    fn evaluate_evm_authorization(&self, _authorization: &Authorization) -> Result<PolicyDecision> {
+        Ok(PolicyDecision::allow())
+    }
 }
 
-/// Minimal spend limit policy stub.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl<P: PolicyEngine + ?Sized> PolicyEngine for Box<P> {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        (**self).evaluate_evm(tx)
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        (**self).evaluate_evm_authorization(authorization)
+    }
+}
+
+/// Per-transaction spend limits on native value and token transfers.
+///
+/// Token amounts are read from ERC-20, ERC-721 and ERC-1155 transfer calldata;
+/// approvals are left to [`TokenApprovalPolicy`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SpendLimitPolicy {
-    /// Maximum allowed value in wei.
-    pub max_value: u128,
+    /// Maximum allowed value in wei. Serialized as a hex quantity; decimal
+    /// strings are accepted too.
+    #[serde(
+        serialize_with = "ibank_wallet_chains::quantity::serialize",
+        deserialize_with = "ibank_wallet_chains::quantity::decimal::deserialize"
+    )]
+    pub max_value: U256,
+    /// Limits on transferred amounts per token contract.
+    #[serde(default)]
+    pub token_limits: Vec<TokenLimit>,
+    /// Whether transfers of tokens without a limit are denied.
+    #[serde(default)]
+    pub deny_unlisted_tokens: bool,
+}
+
+/// Maximum amount of a token that one transaction may transfer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenLimit {
+    /// Token contract.
+    pub token: Address,
+    /// Maximum amount in base units (token ids count as one unit for ERC-721).
+    #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+    pub max_amount: U256,
+}
+
+impl SpendLimitPolicy {
+    /// Creates a policy limiting native value only.
+    pub fn new(max_value: impl Into<U256>) -> Self {
+        Self {
+            max_value: max_value.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a per-transaction limit for a token.
+    pub fn with_token_limit(mut self, token: Address, max_amount: impl Into<U256>) -> Self {
+        self.token_limits.push(TokenLimit {
+            token,
+            max_amount: max_amount.into(),
+        });
+        self
+    }
 }
 
 impl PolicyEngine for SpendLimitPolicy {
     fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
         if tx.value > self.max_value {
-            return Ok(PolicyDecision {
-                allowed: false,
-                reason: Some("value exceeds spend limit".to_string()),
-            });
+            return Ok(
+                PolicyDecision::deny("value exceeds spend limit").with_code(ReasonCode::SpendLimit)
+            );
+        }
+        let call = match tx.token_call() {
+            Ok(Some(call)) => call,
+            Ok(None) => return Ok(PolicyDecision::allow()),
+            Err(err) => return Ok(invalid_calldata(err)),
+        };
+        let (Some(contract), Some(amount)) = (tx.to, transferred_amount(&call)) else {
+            return Ok(PolicyDecision::allow());
+        };
+        let token = call.token(contract);
+        match self.token_limits.iter().find(|limit| limit.token == token) {
+            Some(limit) if amount > limit.max_amount => Ok(PolicyDecision::deny(format!(
+                "transfer of {amount} exceeds spend limit of {} for token {token}",
+                limit.max_amount
+            ))
+            .with_code(ReasonCode::SpendLimit)),
+            None if self.deny_unlisted_tokens => Ok(PolicyDecision::deny(format!(
+                "token {token} has no spend limit"
+            ))
+            .with_code(ReasonCode::UnlistedToken)),
+            _ => Ok(PolicyDecision::allow()),
+        }
+    }
+}
+
+/// Restricts token approvals to allowlisted spenders and optionally blocks
+/// unlimited allowances. Revoking an approval is always allowed.
+///
+/// Covers ERC-20 `approve`/`increaseAllowance`, Permit2 `approve`/`permit`
+/// and ERC-721/ERC-1155 `setApprovalForAll`, which is treated as unlimited.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenApprovalPolicy {
+    /// Spenders and operators approvals may be granted to.
+    pub allowed_spenders: Vec<Address>,
+    /// Whether unlimited allowances are permitted.
+    #[serde(default)]
+    pub allow_unlimited: bool,
+}
+
+impl PolicyEngine for TokenApprovalPolicy {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        let call = match tx.token_call() {
+            Ok(Some(call)) if call.is_approval() => call,
+            Ok(_) => return Ok(PolicyDecision::allow()),
+            Err(err) => return Ok(invalid_calldata(err)),
+        };
+        let allowance = match &call {
+            TokenCall::SetApprovalForAll { approved, .. } => {
+                if *approved {
+                    U256::MAX
+                } else {
+                    U256::zero()
+                }
+            }
+            TokenCall::Approve { amount, .. }
+            | TokenCall::IncreaseAllowance { amount, .. }
+            | TokenCall::Permit2Approve { amount, .. }
+            | TokenCall::Permit2Permit { amount, .. } => *amount,
+            _ => return Ok(PolicyDecision::allow()),
+        };
+        if allowance.is_zero() {
+            return Ok(PolicyDecision::allow());
         }
-        Ok(PolicyDecision {
-            allowed: true,
-            reason: None,
-        })
+        let spender = call.recipient();
+        if !self.allowed_spenders.contains(&spender) {
+            return Ok(PolicyDecision::deny(format!(
+                "approval spender {spender} is not on the allowlist"
+            ))
+            .with_code(ReasonCode::SpenderNotAllowed));
+        }
+        if !self.allow_unlimited && is_unlimited(allowance) {
+            return Ok(PolicyDecision::deny(format!(
+                "unlimited approval to {spender} is not permitted"
+            ))
+            .with_code(ReasonCode::UnlimitedApproval));
+        }
+        Ok(PolicyDecision::allow())
     }
 }
 
-/// Basic allowlist stub that currently permits all recipients.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct AllowListPolicy;
+/// Returns the amount a token call moves, or `None` for approvals.
+fn transferred_amount(call: &TokenCall) -> Option<U256> {
+    match call {
+        TokenCall::Transfer { amount, .. }
+        | TokenCall::TransferFrom { amount, .. }
+        | TokenCall::Erc1155Transfer { amount, .. } => Some(*amount),
+        TokenCall::SafeTransferFrom { .. } => Some(U256::one()),
+        TokenCall::Erc1155BatchTransfer { amounts, .. } => Some(
+            amounts
+                .iter()
+                .try_fold(U256::zero(), |total, amount| total.checked_add(*amount))
+                .unwrap_or(U256::MAX),
+        ),
+        _ => None,
+    }
+}
 
-impl PolicyEngine for AllowListPolicy {
+/// Allowances of `type(uint160).max` (the Permit2 maximum) or more are
+/// treated as unlimited.
+fn is_unlimited(amount: U256) -> bool {
+    amount >= (U256::one() << 160) - 1
+}
+
+/// Restricts EIP-7702 delegation to known smart-account implementations.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DelegationPolicy {
+    /// Contracts accounts may delegate to.
+    pub allowed_delegates: Vec<Address>,
+    /// Whether authorizations valid on every chain (chain id 0) are permitted.
+    #[serde(default)]
+    pub allow_any_chain: bool,
+}
+
+impl PolicyEngine for DelegationPolicy {
     fn evaluate_evm(&self, _tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
-        Ok(PolicyDecision {
-            allowed: true,
-            reason: None,
-        })
+        Ok(PolicyDecision::allow())
     }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        if authorization.chain_id == 0 && !self.allow_any_chain {
+            return Ok(
+                PolicyDecision::deny("cross-chain delegation is not permitted")
+                    .with_code(ReasonCode::DelegationNotAllowed),
+            );
+        }
+        if !self.allowed_delegates.contains(&authorization.address) {
+            return Ok(PolicyDecision::deny(format!(
+                "delegate {} is not on the allowlist",
+                authorization.address
+            ))
+            .with_code(ReasonCode::DelegationNotAllowed));
+        }
+        Ok(PolicyDecision::allow())
+    }
+}
+
+/// Restricts transactions and authorizations to a set of chains.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AllowedChainsPolicy {
+    /// Chains that may be used.
+    pub chains: Vec<CaipChainId>,
+}
+
+impl AllowedChainsPolicy {
+    fn check(&self, chain_id: CaipChainId) -> PolicyDecision {
+        if self.chains.contains(&chain_id) {
+            PolicyDecision::allow()
+        } else {
+            PolicyDecision::deny(format!("chain {chain_id} is not permitted"))
+                .with_code(ReasonCode::ChainNotAllowed)
+        }
+    }
+}
+
+impl PolicyEngine for AllowedChainsPolicy {
+    fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        Ok(self.check(CaipChainId::eip155(tx.chain_id)))
+    }
+
+    fn evaluate_evm_authorization(&self, authorization: &Authorization) -> Result<PolicyDecision> {
+        if authorization.chain_id == 0 {
+            return Ok(PolicyDecision::deny(
+                "authorizations valid on every chain are not permitted",
+            )
+            .with_code(ReasonCode::ChainNotAllowed));
+        }
+        Ok(self.check(CaipChainId::eip155(authorization.chain_id)))
+    }
+}
+
+/// Denies calldata that claims to be a token call but does not decode.
+fn invalid_calldata(err: WalletError) -> PolicyDecision {
+    PolicyDecision::deny(err.to_string()).with_code(ReasonCode::InvalidCalldata)
 }
 
-/// Enforces policy decision or returns an error.
+/// Enforces policy decision or returns an error naming the rule and reason
+/// code, e.g. "rules[0] (spend_limit): value exceeds spend limit".
 pub fn enforce(decision: PolicyDecision) -> Result<()> {
     if decision.allowed {
-        Ok(())
-    } else {
-        Err(WalletError::PolicyViolation(
-            decision.reason.unwrap_or_else(|| "policy denied".to_string()),
-        ))
+        return Ok(());
+    }
+    let reason = decision
+        .reason
+        .unwrap_or_else(|| "policy denied".to_string());
+    let message = match (decision.rule, decision.code) {
+        (Some(rule), Some(code)) => format!("{rule} ({code}): {reason}"),
+        (Some(rule), None) => format!("{rule}: {reason}"),
+        (None, Some(code)) => format!("({code}) {reason}"),
+        (None, None) => reason,
+    };
+    Err(WalletError::PolicyViolation(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ibank_wallet_chains::{EvmUnsignedTxBuilder, PERMIT2_ADDRESS};
+
+    const TOKEN: Address = Address([0x70; 20]);
+
+    fn call(to: Address, call: TokenCall) -> EvmUnsignedTx {
+        EvmUnsignedTxBuilder::new(1, 0)
+            .to(to)
+            .data(call.encode())
+            .build()
+    }
+
+    #[test]
+    fn spend_limit_policy_checks_token_transfers() {
+        let policy = SpendLimitPolicy::new(0).with_token_limit(TOKEN, 1_000);
+        let transfer = |amount: u64| {
+            call(
+                TOKEN,
+                TokenCall::Transfer {
+                    to: Address([0x22; 20]),
+                    amount: U256::from(amount),
+                },
+            )
+        };
+        assert!(policy.evaluate_evm(&transfer(1_000)).unwrap().allowed);
+        let denied = policy.evaluate_evm(&transfer(1_001)).unwrap();
+        assert!(!denied.allowed);
+        assert!(denied.reason.unwrap().contains(&TOKEN.to_string()));
+
+        let batch = call(
+            TOKEN,
+            TokenCall::Erc1155BatchTransfer {
+                from: Address([0x11; 20]),
+                to: Address([0x22; 20]),
+                ids: vec![U256::one(), U256::from(2u64)],
+                amounts: vec![U256::from(600u64), U256::from(600u64)],
+                data: Vec::new(),
+            },
+        );
+        assert!(!policy.evaluate_evm(&batch).unwrap().allowed);
+
+        let other = call(
+            Address([0x71; 20]),
+            TokenCall::Transfer {
+                to: Address([0x22; 20]),
+                amount: U256::MAX,
+            },
+        );
+        assert!(policy.evaluate_evm(&other).unwrap().allowed);
+        let strict = SpendLimitPolicy {
+            deny_unlisted_tokens: true,
+            ..policy.clone()
+        };
+        assert!(!strict.evaluate_evm(&other).unwrap().allowed);
+
+        let mut malformed = transfer(1);
+        malformed.data.truncate(40);
+        assert!(!policy.evaluate_evm(&malformed).unwrap().allowed);
+    }
+
+    #[test]
+    fn token_approval_policy_checks_spender_and_amount() {
+        let router = Address([0x33; 20]);
+        let policy = TokenApprovalPolicy {
+            allowed_spenders: vec![router],
+            allow_unlimited: false,
+        };
+        let approve =
+            |spender: Address, amount: U256| call(TOKEN, TokenCall::Approve { spender, amount });
+        assert!(
+            policy
+                .evaluate_evm(&approve(router, U256::from(5u64)))
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            !policy
+                .evaluate_evm(&approve(router, U256::MAX))
+                .unwrap()
+                .allowed
+        );
+        let unknown = policy
+            .evaluate_evm(&approve(Address([0x44; 20]), U256::one()))
+            .unwrap();
+        assert!(unknown.reason.unwrap().contains("not on the allowlist"));
+        assert!(
+            policy
+                .evaluate_evm(&approve(Address([0x44; 20]), U256::zero()))
+                .unwrap()
+                .allowed
+        );
+
+        let permit2 = call(
+            PERMIT2_ADDRESS,
+            TokenCall::Permit2Approve {
+                token: TOKEN,
+                spender: router,
+                amount: (U256::one() << 160) - 1,
+                expiration: 0,
+            },
+        );
+        assert!(!policy.evaluate_evm(&permit2).unwrap().allowed);
+        let operator = call(
+            TOKEN,
+            TokenCall::SetApprovalForAll {
+                operator: router,
+                approved: true,
+            },
+        );
+        assert!(!policy.evaluate_evm(&operator).unwrap().allowed);
+        let permissive = TokenApprovalPolicy {
+            allow_unlimited: true,
+            ..policy
+        };
+        assert!(permissive.evaluate_evm(&operator).unwrap().allowed);
+    }
+
+    #[test]
+    fn delegation_policy_checks_delegate_and_chain() {
+        let policy = DelegationPolicy {
+            allowed_delegates: vec![Address([0x42; 20])],
+            allow_any_chain: false,
+        };
+        let allowed = Authorization {
+            chain_id: 1,
+            address: Address([0x42; 20]),
+            nonce: 0,
+        };
+        assert!(policy.evaluate_evm_authorization(&allowed).unwrap().allowed);
+
+        let unknown = Authorization {
+            address: Address([0x43; 20]),
+            ..allowed.clone()
+        };
+        assert!(!policy.evaluate_evm_authorization(&unknown).unwrap().allowed);
+
+        let any_chain = Authorization {
+            chain_id: 0,
+            ..allowed
+        };
+        assert!(
+            !policy
+                .evaluate_evm_authorization(&any_chain)
+                .unwrap()
+                .allowed
+        );
     }
 }
@@ -0,0 +1,910 @@
+//! Rolling-window velocity limits backed by a spend ledger.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ibank_wallet_chains::{Address, EvmUnsignedTx, U256};
+use ibank_wallet_core::{CaipAccountId, Result, WalletError};
+use serde::{Deserialize, Serialize};
+
+use crate::{transferred_amount, PolicyDecision, ReasonCode};
+
+/// One signed transaction as seen by velocity limits.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendRecord {
+    /// Signing account.
+    pub account: CaipAccountId,
+    /// Unix time of signing in seconds.
+    pub timestamp: u64,
+    /// Native value in wei.
+    #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+    pub value: U256,
+    /// Token transferred by the transaction, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<TokenSpend>,
+    /// Sender nonce, when the record is one signed version of the transaction
+    /// at that nonce; see [`SpendRecord::shares_nonce`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+}
+
+/// Token amount moved by a transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenSpend {
+    /// Token contract.
+    pub token: Address,
+    /// Amount in base units (token ids count as one unit for ERC-721).
+    #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+    pub amount: U256,
+}
+
+impl SpendRecord {
+    /// Builds the record for `tx` signed by `account` at `timestamp`.
+    pub fn from_tx(account: CaipAccountId, tx: &EvmUnsignedTx, timestamp: u64) -> Self {
+        let token = tx
+            .token_call()
+            .ok()
+            .flatten()
+            .zip(tx.to)
+            .and_then(|(call, contract)| {
+                transferred_amount(&call).map(|amount| TokenSpend {
+                    token: call.token(contract),
+                    amount,
+                })
+            });
+        Self {
+            account,
+            timestamp,
+            value: tx.value,
+            token,
+            nonce: None,
+        }
+    }
+
+    /// Returns true if both records are versions of one transaction: they
+    /// are keyed by the same nonce of the same account, and only one of them
+    /// can be mined.
+    pub fn shares_nonce(&self, other: &SpendRecord) -> bool {
+        self.nonce.is_some() && self.nonce == other.nonce && self.account == other.account
+    }
+
+    /// Folds another version of the same transaction into `self`, keeping the
+    /// latest timestamp and the highest value and token amount.
+    fn merge(&mut self, other: &SpendRecord) {
+        self.timestamp = self.timestamp.max(other.timestamp);
+        self.value = self.value.max(other.value);
+        let larger = match (&self.token, &other.token) {
+            (Some(ours), Some(theirs)) => theirs.amount > ours.amount,
+            (None, Some(_)) => true,
+            _ => false,
+        };
+        if larger {
+            self.token.clone_from(&other.token);
+        }
+    }
+}
+
+/// Merges records that [share a nonce](SpendRecord::shares_nonce) into one
+/// spend per transaction.
+fn merge_versions(records: &[SpendRecord]) -> Vec<SpendRecord> {
+    let mut merged: Vec<SpendRecord> = Vec::with_capacity(records.len());
+    for record in records {
+        match merged.iter_mut().find(|spend| spend.shares_nonce(record)) {
+            Some(spend) => spend.merge(record),
+            None => merged.push(record.clone()),
+        }
+    }
+    merged
+}
+
+/// Storage for past spends.
+///
+/// `record_if` must check and append as one atomic step so that concurrent
+/// signers cannot both pass a limit that only one of them fits under.
+/// Every signed version of a transaction is kept; limits count versions that
+/// [share a nonce](SpendRecord::shares_nonce) once.
+pub trait SpendLedger: Send + Sync {
+    /// Returns the account's records with a timestamp at or after `since`.
+    fn records(&self, account: &CaipAccountId, since: u64) -> Result<Vec<SpendRecord>>;
+
+    /// Runs `check` over the records of `record.account` and appends `record`
+    /// if the returned decision allows it.
+    fn record_if(
+        &self,
+        record: SpendRecord,
+        check: &mut dyn FnMut(&[SpendRecord]) -> PolicyDecision,
+    ) -> Result<PolicyDecision>;
+
+    /// Removes one record equal to `record`, undoing a `record_if` whose
+    /// transaction was not signed after all.
+    fn remove(&self, record: &SpendRecord) -> Result<()>;
+
+    /// Drops records older than `before`.
+    fn prune(&self, before: u64) -> Result<()>;
+}
+
+/// Ledger kept in process memory.
+#[derive(Debug, Default)]
+pub struct MemorySpendLedger {
+    records: Mutex<BTreeMap<CaipAccountId, Vec<SpendRecord>>>,
+}
+
+impl MemorySpendLedger {
+    /// Creates an empty ledger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, BTreeMap<CaipAccountId, Vec<SpendRecord>>>> {
+        self.records
+            .lock()
+            .map_err(|_| WalletError::PolicyViolation("spend ledger lock poisoned".to_string()))
+    }
+}
+
+impl SpendLedger for MemorySpendLedger {
+    fn records(&self, account: &CaipAccountId, since: u64) -> Result<Vec<SpendRecord>> {
+        Ok(self
+            .lock()?
+            .get(account)
+            .map(|records| since_filter(records, since))
+            .unwrap_or_default())
+    }
+
+    fn record_if(
+        &self,
+        record: SpendRecord,
+        check: &mut dyn FnMut(&[SpendRecord]) -> PolicyDecision,
+    ) -> Result<PolicyDecision> {
+        let mut records = self.lock()?;
+        let account = records.entry(record.account.clone()).or_default();
+        let decision = check(account);
+        if decision.allowed {
+            account.push(record);
+        }
+        Ok(decision)
+    }
+
+    fn remove(&self, record: &SpendRecord) -> Result<()> {
+        let mut records = self.lock()?;
+        if let Some(account) = records.get_mut(&record.account) {
+            if let Some(index) = account.iter().position(|past| past == record) {
+                account.remove(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn prune(&self, before: u64) -> Result<()> {
+        let mut records = self.lock()?;
+        for account in records.values_mut() {
+            account.retain(|record| record.timestamp >= before);
+        }
+        records.retain(|_, account| !account.is_empty());
+        Ok(())
+    }
+}
+
+/// Ledger stored as JSON lines in a file.
+///
+/// Every operation holds an exclusive lock on a `.lock` file next to the
+/// ledger, so several processes may share one ledger.
+#[derive(Clone, Debug)]
+pub struct FileSpendLedger {
+    path: PathBuf,
+    lock_path: PathBuf,
+}
+
+impl FileSpendLedger {
+    /// Opens the ledger at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut lock_path = path.clone().into_os_string();
+        lock_path.push(".lock");
+        let ledger = Self {
+            path,
+            lock_path: lock_path.into(),
+        };
+        ledger.with_lock(|| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&ledger.path)
+                .map_err(|err| ledger.io_error(err))?;
+            Ok(())
+        })?;
+        Ok(ledger)
+    }
+
+    fn with_lock<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)
+            .map_err(|err| self.io_error(err))?;
+        lock.lock().map_err(|err| self.io_error(err))?;
+        let result = f();
+        lock.unlock().map_err(|err| self.io_error(err))?;
+        result
+    }
+
+    fn read_all(&self) -> Result<Vec<SpendRecord>> {
+        let file = File::open(&self.path).map_err(|err| self.io_error(err))?;
+        let mut records = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|err| self.io_error(err))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line).map_err(|err| {
+                WalletError::PolicyViolation(format!(
+                    "spend ledger {} line {}: {err}",
+                    self.path.display(),
+                    index + 1
+                ))
+            })?);
+        }
+        Ok(records)
+    }
+
+    /// Replaces the ledger contents; the caller holds the lock.
+    fn write_all(&self, records: &[SpendRecord]) -> Result<()> {
+        let mut contents = String::new();
+        for record in records {
+            contents.push_str(
+                &serde_json::to_string(record)
+                    .map_err(|err| WalletError::PolicyViolation(err.to_string()))?,
+            );
+            contents.push('\n');
+        }
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let mut file = File::create(&temp).map_err(|err| self.io_error(err))?;
+        file.write_all(contents.as_bytes())
+            .and_then(|()| file.sync_all())
+            .map_err(|err| self.io_error(err))?;
+        fs::rename(&temp, &self.path).map_err(|err| self.io_error(err))
+    }
+
+    fn io_error(&self, err: std::io::Error) -> WalletError {
+        WalletError::PolicyViolation(format!("spend ledger {}: {err}", self.path.display()))
+    }
+}
+
+impl SpendLedger for FileSpendLedger {
+    fn records(&self, account: &CaipAccountId, since: u64) -> Result<Vec<SpendRecord>> {
+        self.with_lock(|| {
+            let records = self.read_all()?;
+            Ok(records
+                .into_iter()
+                .filter(|record| &record.account == account && record.timestamp >= since)
+                .collect())
+        })
+    }
+
+    fn record_if(
+        &self,
+        record: SpendRecord,
+        check: &mut dyn FnMut(&[SpendRecord]) -> PolicyDecision,
+    ) -> Result<PolicyDecision> {
+        self.with_lock(|| {
+            let history: Vec<_> = self
+                .read_all()?
+                .into_iter()
+                .filter(|past| past.account == record.account)
+                .collect();
+            let decision = check(&history);
+            if decision.allowed {
+                let mut line = serde_json::to_string(&record)
+                    .map_err(|err| WalletError::PolicyViolation(err.to_string()))?;
+                line.push('\n');
+                let mut file = OpenOptions::new()
+                    .append(true)
+                    .open(&self.path)
+                    .map_err(|err| self.io_error(err))?;
+                file.write_all(line.as_bytes())
+                    .and_then(|()| file.sync_data())
+                    .map_err(|err| self.io_error(err))?;
+            }
+            Ok(decision)
+        })
+    }
+
+    fn remove(&self, record: &SpendRecord) -> Result<()> {
+        self.with_lock(|| {
+            let mut records = self.read_all()?;
+            match records.iter().position(|past| past == record) {
+                Some(index) => {
+                    records.remove(index);
+                    self.write_all(&records)
+                }
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn prune(&self, before: u64) -> Result<()> {
+        self.with_lock(|| {
+            let mut records = self.read_all()?;
+            records.retain(|record| record.timestamp >= before);
+            self.write_all(&records)
+        })
+    }
+}
+
+fn since_filter(records: &[SpendRecord], since: u64) -> Vec<SpendRecord> {
+    records
+        .iter()
+        .filter(|record| record.timestamp >= since)
+        .cloned()
+        .collect()
+}
+
+/// Cap over a rolling window of `window_secs` seconds ending at signing time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum VelocityLimit {
+    /// Total native value in wei.
+    Value {
+        /// Window length in seconds.
+        window_secs: u64,
+        /// Maximum value in the window.
+        #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+        max_value: U256,
+    },
+    /// Total amount of one token, in base units.
+    Token {
+        /// Token contract.
+        token: Address,
+        /// Window length in seconds.
+        window_secs: u64,
+        /// Maximum amount in the window.
+        #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+        max_amount: U256,
+    },
+    /// Number of signed transactions.
+    Count {
+        /// Window length in seconds.
+        window_secs: u64,
+        /// Maximum number of transactions in the window.
+        max_count: u64,
+    },
+}
+
+impl VelocityLimit {
+    fn window_secs(&self) -> u64 {
+        match self {
+            Self::Value { window_secs, .. }
+            | Self::Token { window_secs, .. }
+            | Self::Count { window_secs, .. } => *window_secs,
+        }
+    }
+
+    fn check(&self, history: &[SpendRecord], record: &SpendRecord) -> Option<String> {
+        // Records less than `window_secs` old, plus the one being signed.
+        let window = self.window_secs();
+        let in_window = history
+            .iter()
+            .filter(|past| {
+                past.timestamp <= record.timestamp
+                    && past.timestamp.saturating_add(window) > record.timestamp
+            })
+            .chain(std::iter::once(record));
+        match self {
+            Self::Value {
+                window_secs,
+                max_value,
+            } => {
+                let total =
+                    in_window.fold(U256::zero(), |total, past| total.saturating_add(past.value));
+                (total > *max_value).then(|| {
+                    format!("value {total} wei exceeds velocity limit of {max_value} per {window_secs}s")
+                })
+            }
+            Self::Token {
+                token,
+                window_secs,
+                max_amount,
+            } => {
+                if record
+                    .token
+                    .as_ref()
+                    .is_none_or(|spend| spend.token != *token)
+                {
+                    return None;
+                }
+                let total = in_window
+                    .filter_map(|past| past.token.as_ref())
+                    .filter(|spend| spend.token == *token)
+                    .fold(U256::zero(), |total, spend| {
+                        total.saturating_add(spend.amount)
+                    });
+                (total > *max_amount).then(|| {
+                    format!(
+                        "transfers of {total} exceed velocity limit of {max_amount} per {window_secs}s for token {token}"
+                    )
+                })
+            }
+            Self::Count {
+                window_secs,
+                max_count,
+            } => {
+                let count = in_window.count() as u64;
+                (count > *max_count).then(|| {
+                    format!(
+                        "{count} transactions exceed velocity limit of {max_count} per {window_secs}s"
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// Stateful limits on how much an account may spend over time.
+///
+/// Unlike [`PolicyEngine`](crate::PolicyEngine) implementations, velocity
+/// limits depend on the signing account and on what was signed before, so
+/// the runtime records a transaction with [`VelocityPolicy::record_nonce`]
+/// before signing it and forgets it if signing fails.
+#[derive(Clone)]
+pub struct VelocityPolicy {
+    limits: Vec<VelocityLimit>,
+    ledger: Arc<dyn SpendLedger>,
+}
+
+impl VelocityPolicy {
+    /// Creates a policy without limits backed by `ledger`.
+    pub fn new(ledger: Arc<dyn SpendLedger>) -> Self {
+        Self {
+            limits: Vec::new(),
+            ledger,
+        }
+    }
+
+    /// Adds a limit.
+    pub fn with_limit(mut self, limit: VelocityLimit) -> Self {
+        self.limits.push(limit);
+        self
+    }
+
+    /// Returns the configured limits.
+    pub fn limits(&self) -> &[VelocityLimit] {
+        &self.limits
+    }
+
+    /// Returns the backing ledger.
+    pub fn ledger(&self) -> &Arc<dyn SpendLedger> {
+        &self.ledger
+    }
+
+    /// Checks `tx` against the limits without recording it.
+    pub fn check(
+        &self,
+        account: &CaipAccountId,
+        tx: &EvmUnsignedTx,
+        now: u64,
+    ) -> Result<PolicyDecision> {
+        let record = SpendRecord::from_tx(account.clone(), tx, now);
+        let history = self
+            .ledger
+            .records(account, now.saturating_sub(self.longest_window()))?;
+        Ok(self.decide(&history, &record))
+    }
+
+    /// Checks `tx` against the limits and records it in the same ledger step
+    /// if it is allowed.
+    pub fn record(
+        &self,
+        account: &CaipAccountId,
+        tx: &EvmUnsignedTx,
+        now: u64,
+    ) -> Result<PolicyDecision> {
+        self.record_spend(SpendRecord::from_tx(account.clone(), tx, now))
+    }
+
+    /// Like [`VelocityPolicy::record`], but the record is a version of the
+    /// transaction at the nonce of `tx`. Versions count once, at the highest
+    /// value and token amount any of them moves, so re-signing or replacing a
+    /// transaction does not count it twice and a cancellation does not undo
+    /// what an earlier version may still spend.
+    pub fn record_nonce(
+        &self,
+        account: &CaipAccountId,
+        tx: &EvmUnsignedTx,
+        now: u64,
+    ) -> Result<PolicyDecision> {
+        self.record_spend(nonce_record(account, tx, now))
+    }
+
+    /// Removes the version [`VelocityPolicy::record_nonce`] recorded for
+    /// `tx` at `now`, for a transaction that was not signed after all.
+    pub fn forget_nonce(
+        &self,
+        account: &CaipAccountId,
+        tx: &EvmUnsignedTx,
+        now: u64,
+    ) -> Result<()> {
+        self.ledger.remove(&nonce_record(account, tx, now))
+    }
+
+    /// Returns the account's spends since `since` as the limits count them:
+    /// one per transaction, merging the versions signed for each nonce.
+    pub fn spends(&self, account: &CaipAccountId, since: u64) -> Result<Vec<SpendRecord>> {
+        Ok(merge_versions(&self.ledger.records(account, since)?))
+    }
+
+    fn record_spend(&self, record: SpendRecord) -> Result<PolicyDecision> {
+        let pending = record.clone();
+        self.ledger
+            .record_if(record, &mut |history| self.decide(history, &pending))
+    }
+
+    fn decide(&self, history: &[SpendRecord], record: &SpendRecord) -> PolicyDecision {
+        // Earlier versions of the transaction count as one spend with it.
+        let (versions, others): (Vec<_>, Vec<_>) = history
+            .iter()
+            .cloned()
+            .partition(|past| record.shares_nonce(past));
+        let mut record = record.clone();
+        for version in &versions {
+            record.merge(version);
+        }
+        let history = merge_versions(&others);
+        match self
+            .limits
+            .iter()
+            .find_map(|limit| limit.check(&history, &record))
+        {
+            Some(reason) => PolicyDecision::deny(reason).with_code(ReasonCode::VelocityLimit),
+            None => PolicyDecision::allow(),
+        }
+    }
+
+    fn longest_window(&self) -> u64 {
+        self.limits
+            .iter()
+            .map(VelocityLimit::window_secs)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+fn nonce_record(account: &CaipAccountId, tx: &EvmUnsignedTx, now: u64) -> SpendRecord {
+    let mut record = SpendRecord::from_tx(account.clone(), tx, now);
+    record.nonce = Some(tx.nonce);
+    record
+}
+
+impl fmt::Debug for VelocityPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VelocityPolicy")
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ibank_wallet_chains::{EvmUnsignedTxBuilder, TokenCall};
+    use ibank_wallet_core::CaipChainId;
+
+    const ETHER: u64 = 1_000_000_000_000_000_000;
+    const DAY: u64 = 86_400;
+    const HOUR: u64 = 3_600;
+
+    fn account(byte: u8) -> CaipAccountId {
+        Address([byte; 20]).to_caip10(&CaipChainId::eip155(1))
+    }
+
+    fn send(wei: u64) -> EvmUnsignedTx {
+        EvmUnsignedTxBuilder::new(1, 0)
+            .to(Address([0x22; 20]))
+            .value(wei)
+            .build()
+    }
+
+    fn token_transfer(token: Address, amount: u64) -> EvmUnsignedTx {
+        EvmUnsignedTxBuilder::new(1, 0)
+            .to(token)
+            .data(
+                TokenCall::Transfer {
+                    to: Address([0x22; 20]),
+                    amount: U256::from(amount),
+                }
+                .encode(),
+            )
+            .build()
+    }
+
+    fn value_limit(ledger: Arc<dyn SpendLedger>) -> VelocityPolicy {
+        VelocityPolicy::new(ledger).with_limit(VelocityLimit::Value {
+            window_secs: DAY,
+            max_value: U256::from(10 * ETHER),
+        })
+    }
+
+    #[test]
+    fn caps_value_over_a_rolling_window_per_account() {
+        let policy = value_limit(Arc::new(MemorySpendLedger::new()));
+        let alice = account(0x11);
+
+        assert!(
+            policy
+                .record(&alice, &send(6 * ETHER), 1_000)
+                .unwrap()
+                .allowed
+        );
+        let denied = policy.record(&alice, &send(5 * ETHER), 2_000).unwrap();
+        assert_eq!(
+            denied.reason.as_deref(),
+            Some("value 11000000000000000000 wei exceeds velocity limit of 10000000000000000000 per 86400s")
+        );
+        // Denied spends are not recorded.
+        assert!(
+            policy
+                .record(&alice, &send(4 * ETHER), 3_000)
+                .unwrap()
+                .allowed
+        );
+        assert_eq!(policy.ledger().records(&alice, 0).unwrap().len(), 2);
+
+        // Other accounts have their own window.
+        assert!(
+            policy
+                .record(&account(0x12), &send(10 * ETHER), 3_000)
+                .unwrap()
+                .allowed
+        );
+        // The first spend leaves the window a day later.
+        assert!(
+            !policy
+                .check(&alice, &send(6 * ETHER), 1_000 + DAY - 1)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            policy
+                .check(&alice, &send(6 * ETHER), 1_000 + DAY)
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[test]
+    fn caps_transaction_count_and_token_amounts() {
+        let usdc = Address([0xa0; 20]);
+        let policy = VelocityPolicy::new(Arc::new(MemorySpendLedger::new()))
+            .with_limit(VelocityLimit::Count {
+                window_secs: HOUR,
+                max_count: 3,
+            })
+            .with_limit(VelocityLimit::Token {
+                token: usdc,
+                window_secs: DAY,
+                max_amount: U256::from(1_000u64),
+            });
+        let alice = account(0x11);
+
+        assert!(
+            policy
+                .record(&alice, &token_transfer(usdc, 600), 0)
+                .unwrap()
+                .allowed
+        );
+        let denied = policy
+            .record(&alice, &token_transfer(usdc, 500), 10)
+            .unwrap();
+        assert!(denied.reason.unwrap().contains("for token"));
+        // Other tokens are not capped.
+        assert!(
+            policy
+                .record(&alice, &token_transfer(Address([0xb0; 20]), 5_000), 20)
+                .unwrap()
+                .allowed
+        );
+        assert!(policy.record(&alice, &send(1), 30).unwrap().allowed);
+        let denied = policy.record(&alice, &send(1), 40).unwrap();
+        assert_eq!(
+            denied.reason.as_deref(),
+            Some("4 transactions exceed velocity limit of 3 per 3600s")
+        );
+        assert!(policy.record(&alice, &send(1), HOUR + 1).unwrap().allowed);
+    }
+
+    #[test]
+    fn record_nonce_counts_versions_of_a_transaction_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spend.jsonl");
+        let ledgers: [Arc<dyn SpendLedger>; 2] = [
+            Arc::new(MemorySpendLedger::new()),
+            Arc::new(FileSpendLedger::open(&path).unwrap()),
+        ];
+        let alice = account(0x11);
+        let next = |wei: u64| {
+            EvmUnsignedTxBuilder::new(1, 1)
+                .to(Address([0x22; 20]))
+                .value(wei)
+                .build()
+        };
+        for ledger in ledgers {
+            let policy = value_limit(ledger);
+            assert!(
+                policy
+                    .record_nonce(&alice, &send(6 * ETHER), 1_000)
+                    .unwrap()
+                    .allowed
+            );
+            // A replacement at the same nonce counts instead of the original.
+            assert!(
+                policy
+                    .record_nonce(&alice, &send(9 * ETHER), 1_100)
+                    .unwrap()
+                    .allowed
+            );
+            // A cancellation may lose the race, so the spend stays counted.
+            assert!(
+                policy
+                    .record_nonce(&alice, &send(0), 1_150)
+                    .unwrap()
+                    .allowed
+            );
+            assert!(
+                !policy
+                    .record_nonce(&alice, &next(2 * ETHER), 1_200)
+                    .unwrap()
+                    .allowed
+            );
+
+            assert_eq!(policy.ledger().records(&alice, 0).unwrap().len(), 3);
+            let spends = policy.spends(&alice, 0).unwrap();
+            assert_eq!(spends.len(), 1);
+            assert_eq!(spends[0].value, U256::from(9 * ETHER));
+            assert_eq!(spends[0].timestamp, 1_150);
+            assert!(
+                policy
+                    .record_nonce(&alice, &next(ETHER), 1_200)
+                    .unwrap()
+                    .allowed
+            );
+
+            // Forgetting a version that was never signed frees its room.
+            policy.forget_nonce(&alice, &next(ETHER), 1_200).unwrap();
+            assert_eq!(policy.spends(&alice, 0).unwrap().len(), 1);
+            assert!(
+                policy
+                    .record_nonce(&alice, &next(ETHER), 1_300)
+                    .unwrap()
+                    .allowed
+            );
+        }
+        let reopened = value_limit(Arc::new(FileSpendLedger::open(&path).unwrap()));
+        assert_eq!(reopened.spends(&alice, 0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn record_nonce_keeps_the_largest_token_amount_of_any_version() {
+        let usdc = Address([0xa0; 20]);
+        let policy = VelocityPolicy::new(Arc::new(MemorySpendLedger::new())).with_limit(
+            VelocityLimit::Token {
+                token: usdc,
+                window_secs: DAY,
+                max_amount: U256::from(1_000u64),
+            },
+        );
+        let alice = account(0x11);
+        let transfer = |nonce: u64, amount: u64| EvmUnsignedTx {
+            nonce,
+            ..token_transfer(usdc, amount)
+        };
+        assert!(
+            policy
+                .record_nonce(&alice, &transfer(0, 600), 0)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            policy
+                .record_nonce(&alice, &transfer(0, 100), 10)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            !policy
+                .record_nonce(&alice, &transfer(1, 500), 20)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            policy
+                .record_nonce(&alice, &transfer(1, 400), 20)
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[test]
+    fn file_ledger_persists_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spend.jsonl");
+        let alice = account(0x11);
+
+        let policy = value_limit(Arc::new(FileSpendLedger::open(&path).unwrap()));
+        assert!(
+            policy
+                .record(&alice, &send(6 * ETHER), 1_000)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            policy
+                .record(&account(0x12), &send(ETHER), 1_500)
+                .unwrap()
+                .allowed
+        );
+
+        let reopened = value_limit(Arc::new(FileSpendLedger::open(&path).unwrap()));
+        assert!(
+            !reopened
+                .record(&alice, &send(5 * ETHER), 2_000)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            reopened
+                .record(&alice, &send(4 * ETHER), 2_000)
+                .unwrap()
+                .allowed
+        );
+        assert_eq!(reopened.ledger().records(&alice, 1_500).unwrap().len(), 1);
+
+        reopened.ledger().prune(1_500).unwrap();
+        assert_eq!(reopened.ledger().records(&alice, 0).unwrap().len(), 1);
+        assert_eq!(
+            reopened.ledger().records(&account(0x12), 0).unwrap()[0].value,
+            U256::from(ETHER)
+        );
+    }
+
+    #[test]
+    fn record_is_atomic_across_threads() {
+        let ledger: Arc<dyn SpendLedger> = Arc::new(MemorySpendLedger::new());
+        let policy = VelocityPolicy::new(ledger.clone()).with_limit(VelocityLimit::Count {
+            window_secs: HOUR,
+            max_count: 5,
+        });
+        let alice = account(0x11);
+        let allowed: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16)
+                .map(|_| scope.spawn(|| policy.record(&alice, &send(1), 100).unwrap().allowed))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| usize::from(handle.join().unwrap()))
+                .sum()
+        });
+        assert_eq!(allowed, 5);
+        assert_eq!(ledger.records(&alice, 0).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn parses_limits() {
+        let limit: VelocityLimit = serde_json::from_str(
+            r#"{"type":"value","window_secs":86400,"max_value":"10000000000000000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            limit,
+            VelocityLimit::Value {
+                window_secs: DAY,
+                max_value: U256::from(10 * ETHER),
+            }
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Time-of-day signing windows.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ibank_wallet_chains::{Authorization, EvmUnsignedTx};
+use ibank_wallet_core::{Result, WalletError};
+use serde::{Deserialize, Serialize};
+
+use crate::{PolicyDecision, PolicyEngine, ReasonCode};
+
+/// Allows signing only inside a UTC time window.
+///
+/// `end` is exclusive; a window with `end` before `start` spans midnight.
+/// `days` is matched against the current UTC day.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindowPolicy {
+    /// Days the window is open; empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    /// Opening time.
+    pub start: TimeOfDay,
+    /// Closing time.
+    pub end: TimeOfDay,
+}
+
+impl TimeWindowPolicy {
+    /// Returns true if the window is open at `unix_seconds`.
+    pub fn is_open_at(&self, unix_seconds: u64) -> bool {
+        let days = unix_seconds / 86_400;
+        // 1970-01-01 was a Thursday.
+        let weekday = Weekday::ALL[((days + 3) % 7) as usize];
+        if !self.days.is_empty() && !self.days.contains(&weekday) {
+            return false;
+        }
+        let minute = ((unix_seconds % 86_400) / 60) as u16;
+        if self.start <= self.end {
+            self.start.0 <= minute && minute < self.end.0
+        } else {
+            minute >= self.start.0 || minute < self.end.0
+        }
+    }
+
+    fn check(&self) -> PolicyDecision {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        if self.is_open_at(now) {
+            PolicyDecision::allow()
+        } else {
+            PolicyDecision::deny(format!(
+                "outside the signing window {}-{} UTC",
+                self.start, self.end
+            ))
+            .with_code(ReasonCode::OutsideTimeWindow)
+        }
+    }
+}
+
+impl PolicyEngine for TimeWindowPolicy {
+    fn evaluate_evm(&self, _tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+        Ok(self.check())
+    }
+
+    fn evaluate_evm_authorization(&self, _authorization: &Authorization) -> Result<PolicyDecision> {
+        Ok(self.check())
+    }
+}
+
+/// Day of the week.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    const ALL: [Self; 7] = [
+        Self::Mon,
+        Self::Tue,
+        Self::Wed,
+        Self::Thu,
+        Self::Fri,
+        Self::Sat,
+        Self::Sun,
+    ];
+}
+
+/// UTC time of day with minute precision, written "HH:MM".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u16);
+
+impl TimeOfDay {
+    /// Builds a time of day; fails for hours above 23 or minutes above 59.
+    pub fn new(hour: u16, minute: u16) -> Result<Self> {
+        if hour > 23 || minute > 59 {
+            return Err(WalletError::InvalidInput(format!(
+                "invalid time of day {hour:02}:{minute:02}"
+            )));
+        }
+        Ok(Self(hour * 60 + minute))
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = WalletError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || WalletError::InvalidInput(format!("time {value:?} must be HH:MM"));
+        let (hour, minute) = value.split_once(':').ok_or_else(invalid)?;
+        if hour.len() != 2 || minute.len() != 2 {
+            return Err(invalid());
+        }
+        Self::new(
+            hour.parse().map_err(|_| invalid())?,
+            minute.parse().map_err(|_| invalid())?,
+        )
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = WalletError;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(value: TimeOfDay) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z, a Monday.
+    const MONDAY: u64 = 1_704_067_200;
+
+    fn at(day: u64, hour: u64, minute: u64) -> u64 {
+        MONDAY + day * 86_400 + hour * 3_600 + minute * 60
+    }
+
+    #[test]
+    fn checks_days_and_hours() {
+        let office = TimeWindowPolicy {
+            days: vec![Weekday::Mon, Weekday::Fri],
+            start: "09:00".parse().unwrap(),
+            end: "17:30".parse().unwrap(),
+        };
+        assert!(office.is_open_at(at(0, 9, 0)));
+        assert!(office.is_open_at(at(4, 17, 29)));
+        assert!(!office.is_open_at(at(0, 17, 30)));
+        assert!(!office.is_open_at(at(0, 8, 59)));
+        assert!(!office.is_open_at(at(1, 12, 0)));
+
+        let overnight = TimeWindowPolicy {
+            days: Vec::new(),
+            start: "22:00".parse().unwrap(),
+            end: "06:00".parse().unwrap(),
+        };
+        assert!(overnight.is_open_at(at(2, 23, 0)));
+        assert!(overnight.is_open_at(at(2, 5, 59)));
+        assert!(!overnight.is_open_at(at(2, 6, 0)));
+    }
+
+    #[test]
+    fn parses_times_of_day() {
+        assert_eq!(TimeOfDay::new(7, 5).unwrap().to_string(), "07:05");
+        for bad in ["24:00", "9:00", "09:60", "0900", "aa:bb"] {
+            assert!(bad.parse::<TimeOfDay>().is_err(), "{bad}");
+        }
+    }
+}
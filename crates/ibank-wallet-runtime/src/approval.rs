@@ -0,0 +1,101 @@
+//! Intents held for M-of-N approval or a delay.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ibank_wallet_core::Result;
+use ibank_wallet_policy::{Approval, Obligation, Quorum};
+use serde::{Deserialize, Serialize};
+
+use crate::{Intent, Quote};
+
+/// An intent waiting for approvals or a delay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingIntent {
+    /// Hash of the transaction signing payload; approvers sign this.
+    pub intent_hash: [u8; 32],
+    /// Held intent.
+    pub intent: Intent,
+    /// Quote the intent was evaluated with.
+    pub quote: Quote,
+    /// Obligations to meet before signing.
+    pub obligations: Vec<Obligation>,
+    /// Approvals collected so far, at most one per approver.
+    pub approvals: Vec<Approval>,
+    /// Unix time the intent was held.
+    pub created_at: u64,
+    /// Unix time before which the intent may not be signed.
+    pub not_before: u64,
+    /// Unix time after which approvals are no longer accepted.
+    pub expires_at: u64,
+}
+
+impl PendingIntent {
+    /// Returns true if the intent expired at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Returns the quorums that must approve.
+    pub fn quorums(&self) -> impl Iterator<Item = &Quorum> {
+        self.obligations
+            .iter()
+            .filter_map(|obligation| match obligation {
+                Obligation::Approval { quorum } => Some(quorum),
+                Obligation::Delay { .. } => None,
+            })
+    }
+
+    /// Returns true if every quorum is met.
+    pub fn is_approved(&self) -> bool {
+        self.quorums()
+            .all(|quorum| quorum.is_met(&self.intent_hash, &self.approvals))
+    }
+}
+
+/// Storage for pending intents, keyed by intent hash.
+pub trait PendingStore: fmt::Debug + Send {
+    /// Inserts or replaces a pending intent.
+    fn put(&mut self, pending: PendingIntent) -> Result<()>;
+
+    /// Returns the pending intent with `intent_hash`.
+    fn get(&self, intent_hash: &[u8; 32]) -> Result<Option<PendingIntent>>;
+
+    /// Removes and returns the pending intent with `intent_hash`.
+    fn remove(&mut self, intent_hash: &[u8; 32]) -> Result<Option<PendingIntent>>;
+
+    /// Returns every pending intent.
+    fn list(&self) -> Result<Vec<PendingIntent>>;
+}
+
+/// Pending intents kept in process memory.
+#[derive(Debug, Default)]
+pub struct MemoryPendingStore {
+    intents: BTreeMap<[u8; 32], PendingIntent>,
+}
+
+impl MemoryPendingStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PendingStore for MemoryPendingStore {
+    fn put(&mut self, pending: PendingIntent) -> Result<()> {
+        self.intents.insert(pending.intent_hash, pending);
+        Ok(())
+    }
+
+    fn get(&self, intent_hash: &[u8; 32]) -> Result<Option<PendingIntent>> {
+        Ok(self.intents.get(intent_hash).cloned())
+    }
+
+    fn remove(&mut self, intent_hash: &[u8; 32]) -> Result<Option<PendingIntent>> {
+        Ok(self.intents.remove(intent_hash))
+    }
+
+    fn list(&self) -> Result<Vec<PendingIntent>> {
+        Ok(self.intents.values().cloned().collect())
+    }
+}
@@ -0,0 +1,310 @@
+//! Intent identities and their persisted lifecycle.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ibank_wallet_core::{Result, WalletError};
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::{hash, TransactionReceipt};
+use crate::{Intent, Quote};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteIntentStore, SqlitePendingStore};
+
+/// Stable identifier of an intent; also the idempotency key for creating it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IntentId(pub String);
+
+impl IntentId {
+    /// Wraps a caller-chosen id.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    /// Generates an id unique within this host.
+    pub fn generate() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        Self(format!(
+            "{nanos:x}-{:x}-{:x}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    /// Returns the id as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for IntentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Lifecycle state of an intent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntentState {
+    /// Recorded by [`Runtime::create_intent`](crate::Runtime::create_intent).
+    Created,
+    /// A quote was attached and the intent awaits policy evaluation.
+    Quoted,
+    /// Policy allowed the intent, directly or once approved; signing follows.
+    PolicyApproved,
+    /// Policy denied the intent, or its approval window expired.
+    PolicyDenied,
+    /// Policy held the intent for approvals or a delay.
+    PendingApproval,
+    /// The transaction was signed and stored, but not yet broadcast.
+    Signed,
+    /// The transaction was accepted by the node and awaits confirmations.
+    Submitted,
+    /// The transaction was mined successfully with enough confirmations.
+    Confirmed,
+    /// The transaction reverted.
+    Failed,
+    /// Another transaction at the same nonce was mined instead.
+    Replaced,
+}
+
+impl IntentState {
+    /// Returns the snake_case name used in stores.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Quoted => "quoted",
+            Self::PolicyApproved => "policy_approved",
+            Self::PolicyDenied => "policy_denied",
+            Self::PendingApproval => "pending_approval",
+            Self::Signed => "signed",
+            Self::Submitted => "submitted",
+            Self::Confirmed => "confirmed",
+            Self::Failed => "failed",
+            Self::Replaced => "replaced",
+        }
+    }
+
+    /// Returns true if no further transitions are possible.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            Self::PolicyDenied | Self::Confirmed | Self::Failed | Self::Replaced
+        )
+    }
+
+    /// Returns true if the lifecycle allows moving from `self` to `next`.
+    pub fn can_transition_to(self, next: IntentState) -> bool {
+        use IntentState::*;
+        match (self, next) {
+            (_, Failed) => !self.is_terminal(),
+            (Created | Quoted, Quoted) => true,
+            (Quoted | PendingApproval, PolicyApproved | PolicyDenied) => true,
+            (Quoted, PendingApproval) => true,
+            (PolicyApproved, Signed) => true,
+            (Signed, Submitted) => true,
+            (Submitted, Confirmed) => true,
+            (Signed | Submitted, Replaced) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for IntentState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A state an intent entered, and when.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChange {
+    /// State entered.
+    pub state: IntentState,
+    /// Unix time of the change.
+    pub at: u64,
+}
+
+/// Persisted intent with its lifecycle state.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IntentRecord {
+    /// Intent id.
+    pub id: IntentId,
+    /// The intent.
+    pub intent: Intent,
+    /// Current state.
+    pub state: IntentState,
+    /// Quote the intent was last evaluated with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<Quote>,
+    /// Why the intent was denied or failed, or is still waiting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Hash approvers sign while the intent awaits approval.
+    #[serde(
+        default,
+        with = "hash::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub intent_hash: Option<[u8; 32]>,
+    /// Signed transaction, kept so it is rebroadcast rather than re-signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<u8>>,
+    /// Hash of the signed transaction.
+    #[serde(
+        default,
+        with = "hash::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tx_hash: Option<[u8; 32]>,
+    /// Receipt once mined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<TransactionReceipt>,
+    /// Intent this one replaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement_of: Option<IntentId>,
+    /// Intent that replaced this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<IntentId>,
+    /// States entered, oldest first.
+    pub history: Vec<StateChange>,
+}
+
+impl IntentRecord {
+    /// Creates a record in the [`IntentState::Created`] state.
+    pub fn new(id: IntentId, intent: Intent, now: u64) -> Self {
+        Self {
+            id,
+            intent,
+            state: IntentState::Created,
+            quote: None,
+            reason: None,
+            intent_hash: None,
+            raw: None,
+            tx_hash: None,
+            receipt: None,
+            replacement_of: None,
+            replaced_by: None,
+            history: vec![StateChange {
+                state: IntentState::Created,
+                at: now,
+            }],
+        }
+    }
+
+    /// Moves to `next`, rejecting transitions the lifecycle does not allow.
+    pub fn transition(&mut self, next: IntentState, now: u64) -> Result<()> {
+        if !self.state.can_transition_to(next) {
+            return Err(WalletError::InvalidInput(format!(
+                "intent {} cannot move from {} to {next}",
+                self.id, self.state
+            )));
+        }
+        self.state = next;
+        self.history.push(StateChange {
+            state: next,
+            at: now,
+        });
+        Ok(())
+    }
+
+    /// Returns the unix time of the last state change.
+    pub fn updated_at(&self) -> u64 {
+        self.history
+            .last()
+            .map(|change| change.at)
+            .unwrap_or_default()
+    }
+}
+
+/// Storage for intent records, keyed by id.
+///
+/// Stores must persist every `put` before returning so a restarted process
+/// sees each state the previous one reached.
+pub trait IntentStore: fmt::Debug + Send {
+    /// Inserts or replaces a record.
+    fn put(&mut self, record: &IntentRecord) -> Result<()>;
+
+    /// Returns the record with `id`.
+    fn get(&self, id: &IntentId) -> Result<Option<IntentRecord>>;
+
+    /// Returns every record not in a terminal state.
+    fn active(&self) -> Result<Vec<IntentRecord>>;
+}
+
+/// Intent records kept in process memory.
+#[derive(Debug, Default)]
+pub struct MemoryIntentStore {
+    records: BTreeMap<IntentId, IntentRecord>,
+}
+
+impl MemoryIntentStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IntentStore for MemoryIntentStore {
+    fn put(&mut self, record: &IntentRecord) -> Result<()> {
+        self.records.insert(record.id.clone(), record.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &IntentId) -> Result<Option<IntentRecord>> {
+        Ok(self.records.get(id).cloned())
+    }
+
+    fn active(&self) -> Result<Vec<IntentRecord>> {
+        Ok(self
+            .records
+            .values()
+            .filter(|record| !record.state.is_terminal())
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_lifecycle_transitions() {
+        use IntentState::*;
+        let happy = [Quoted, PolicyApproved, Signed, Submitted, Confirmed];
+        for pair in [Created]
+            .iter()
+            .chain(&happy)
+            .collect::<Vec<_>>()
+            .windows(2)
+        {
+            assert!(pair[0].can_transition_to(*pair[1]), "{pair:?}");
+        }
+        assert!(Quoted.can_transition_to(PendingApproval));
+        assert!(PendingApproval.can_transition_to(PolicyApproved));
+        assert!(Submitted.can_transition_to(Replaced));
+        assert!(!Created.can_transition_to(Signed));
+        assert!(!Confirmed.can_transition_to(Failed));
+        assert!(!PolicyDenied.can_transition_to(Quoted));
+
+        assert_ne!(IntentId::generate(), IntentId::generate());
+        assert_eq!(
+            serde_json::to_value(PendingApproval).expect("json"),
+            "pending_approval"
+        );
+    }
+}
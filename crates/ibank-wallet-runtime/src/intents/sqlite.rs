@@ -0,0 +1,237 @@
+//! SQLite-backed intent and pending-approval stores.
+
+use std::path::Path;
+
+use ibank_wallet_core::{Result, WalletError};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+
+use super::{IntentId, IntentRecord, IntentStore};
+use crate::approval::{PendingIntent, PendingStore};
+
+/// [`IntentStore`] in a SQLite database; each `put` is one committed
+/// statement, so records survive crashes.
+#[derive(Debug)]
+pub struct SqliteIntentStore {
+    conn: Connection,
+}
+
+impl SqliteIntentStore {
+    /// Opens or creates the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Opens a database that lives only as long as the store.
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "PRAGMA busy_timeout = 5000;
+             PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = FULL;
+             CREATE TABLE IF NOT EXISTS intents (
+                 id TEXT PRIMARY KEY,
+                 state TEXT NOT NULL,
+                 terminal INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL,
+                 record TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS intents_active ON intents (terminal, updated_at);",
+        )
+        .map_err(sqlite_error)?;
+        Ok(Self { conn })
+    }
+}
+
+impl IntentStore for SqliteIntentStore {
+    fn put(&mut self, record: &IntentRecord) -> Result<()> {
+        let json = serde_json::to_string(record)
+            .map_err(|err| WalletError::InvalidInput(err.to_string()))?;
+        self.conn
+            .execute(
+                "INSERT INTO intents (id, state, terminal, updated_at, record)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (id) DO UPDATE SET
+                     state = excluded.state,
+                     terminal = excluded.terminal,
+                     updated_at = excluded.updated_at,
+                     record = excluded.record",
+                params![
+                    record.id.as_str(),
+                    record.state.as_str(),
+                    record.state.is_terminal(),
+                    record.updated_at() as i64,
+                    json
+                ],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn get(&self, id: &IntentId) -> Result<Option<IntentRecord>> {
+        self.conn
+            .query_row(
+                "SELECT record FROM intents WHERE id = ?1",
+                [id.as_str()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?
+            .map(|json| decode(&json, "intent record"))
+            .transpose()
+    }
+
+    fn active(&self) -> Result<Vec<IntentRecord>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT record FROM intents WHERE terminal = 0 ORDER BY updated_at, id")
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        rows.map(|json| decode(&json.map_err(sqlite_error)?, "intent record"))
+            .collect()
+    }
+}
+
+/// [`PendingStore`] in a SQLite database, so held intents and their
+/// approvals survive restarts. It may share a file with a
+/// [`SqliteIntentStore`].
+#[derive(Debug)]
+pub struct SqlitePendingStore {
+    conn: Connection,
+}
+
+impl SqlitePendingStore {
+    /// Opens or creates the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Opens a database that lives only as long as the store.
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "PRAGMA busy_timeout = 5000;
+             PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = FULL;
+             CREATE TABLE IF NOT EXISTS pending_intents (
+                 intent_hash TEXT PRIMARY KEY,
+                 created_at INTEGER NOT NULL,
+                 record TEXT NOT NULL
+             );",
+        )
+        .map_err(sqlite_error)?;
+        Ok(Self { conn })
+    }
+}
+
+impl PendingStore for SqlitePendingStore {
+    fn put(&mut self, pending: PendingIntent) -> Result<()> {
+        let json = serde_json::to_string(&pending)
+            .map_err(|err| WalletError::InvalidInput(err.to_string()))?;
+        self.conn
+            .execute(
+                "INSERT INTO pending_intents (intent_hash, created_at, record)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT (intent_hash) DO UPDATE SET record = excluded.record",
+                params![
+                    hex::encode(pending.intent_hash),
+                    pending.created_at as i64,
+                    json
+                ],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn get(&self, intent_hash: &[u8; 32]) -> Result<Option<PendingIntent>> {
+        self.conn
+            .query_row(
+                "SELECT record FROM pending_intents WHERE intent_hash = ?1",
+                [hex::encode(intent_hash)],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?
+            .map(|json| decode(&json, "pending intent"))
+            .transpose()
+    }
+
+    fn remove(&mut self, intent_hash: &[u8; 32]) -> Result<Option<PendingIntent>> {
+        let pending = self.get(intent_hash)?;
+        self.conn
+            .execute(
+                "DELETE FROM pending_intents WHERE intent_hash = ?1",
+                [hex::encode(intent_hash)],
+            )
+            .map_err(sqlite_error)?;
+        Ok(pending)
+    }
+
+    fn list(&self) -> Result<Vec<PendingIntent>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT record FROM pending_intents ORDER BY created_at, intent_hash")
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        rows.map(|json| decode(&json.map_err(sqlite_error)?, "pending intent"))
+            .collect()
+    }
+}
+
+fn decode<T: DeserializeOwned>(json: &str, what: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|err| WalletError::InvalidInput(format!("{what}: {err}")))
+}
+
+fn sqlite_error(err: rusqlite::Error) -> WalletError {
+    WalletError::InvalidInput(format!("intent store: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intents::IntentState;
+    use crate::{Intent, IntentAction};
+    use ibank_wallet_chains::{Address, U256};
+    use ibank_wallet_core::CaipChainId;
+
+    #[test]
+    fn records_survive_reopening() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("intents.db");
+        let intent = Intent {
+            chain_id: CaipChainId::eip155(1),
+            nonce: 3,
+            action: IntentAction::Call {
+                to: Address([0x22; 20]),
+                value: U256::from(1_000),
+                data: Vec::new(),
+            },
+        };
+        let mut record = IntentRecord::new(IntentId::new("payout-1"), intent, 10);
+        {
+            let mut store = SqliteIntentStore::open(&path).expect("open");
+            store.put(&record).expect("put");
+            record.transition(IntentState::Quoted, 11).expect("quoted");
+            record.tx_hash = Some([0xab; 32]);
+            store.put(&record).expect("update");
+        }
+
+        let store = SqliteIntentStore::open(&path).expect("reopen");
+        let loaded = store.get(&record.id).expect("get").expect("record");
+        assert_eq!(loaded.state, IntentState::Quoted);
+        assert_eq!(loaded.tx_hash, Some([0xab; 32]));
+        assert_eq!(loaded.history.len(), 2);
+        assert_eq!(store.active().expect("active").len(), 1);
+        assert!(store.get(&IntentId::new("missing")).expect("get").is_none());
+    }
+}
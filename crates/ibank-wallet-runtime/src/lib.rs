@@ -1,11 +1,39 @@
 //! Intent-to-submit runtime orchestrator.
 
-use ibank_wallet_chains::{AccessList, EvmUnsignedTx};
-use ibank_wallet_core::{AuditEvent, AuditLog, CaipChainId, Result, WalletError};
+pub mod approval;
+pub mod intents;
+pub mod nonce;
+pub mod quote;
+pub mod rpc;
+
+pub use approval::{MemoryPendingStore, PendingIntent, PendingStore};
+pub use intents::{
+    IntentId, IntentRecord, IntentState, IntentStore, MemoryIntentStore, StateChange,
+};
+#[cfg(feature = "sqlite")]
+pub use intents::{SqliteIntentStore, SqlitePendingStore};
+pub use nonce::{
+    AccountNonces, FileNonceStore, MemoryNonceStore, NonceGap, NonceManager, NonceStatus,
+    NonceStore,
+};
+pub use quote::{CallRequest, FeeHistory, FeeRpc, FeeStrategy, QuoteConfig, Quoter};
+pub use rpc::{
+    BlockTag, HttpRpcClient, JsonRpc, RecordedRpc, RpcClient, SubmitOptions, TransactionReceipt,
+};
+
+use ibank_wallet_chains::{
+    quantity, recover_sender, transaction_hash, AccessList, Address, Authorization, EvmEip7702Tx,
+    EvmTypedTx, EvmUnsignedTx, SignedAuthorization, TokenCall, U256,
+};
+use ibank_wallet_core::{
+    AuditEvent, AuditLog, CaipAccountId, CaipAssetId, CaipChainId, Result, WalletError,
+};
 use ibank_wallet_crypto::Signer;
-use ibank_wallet_policy::{enforce, PolicyEngine};
+use ibank_wallet_policy::{enforce, Approval, PolicyDecision, PolicyEngine, VelocityPolicy};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 /// Transfer intent for EVM chains.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -14,27 +42,280 @@ pub struct Intent {
     pub chain_id: CaipChainId,
     /// Sender nonce.
     pub nonce: u64,
-    /// Recipient address.
-    pub to: [u8; 20],
-    /// Value in wei.
-    pub value: u128,
-    /// Calldata payload.
-    pub data: Vec<u8>,
+    /// What the transaction does.
+    #[serde(flatten)]
+    pub action: IntentAction,
+}
+
+/// Action requested by an intent. Token actions name the asset by its CAIP-19
+/// id and are lowered into calls to the token contract.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IntentAction {
+    /// Native value transfer or arbitrary contract call.
+    Call {
+        /// Recipient address.
+        to: Address,
+        /// Value in wei.
+        #[serde(with = "ibank_wallet_chains::quantity")]
+        value: U256,
+        /// Calldata payload.
+        data: Vec<u8>,
+    },
+    /// ERC-20 `transfer`.
+    Erc20Transfer {
+        /// Token asset id (e.g. "eip155:1/erc20:0x…").
+        asset: CaipAssetId,
+        /// Token recipient.
+        to: Address,
+        /// Human amount (e.g. "12.5").
+        amount: String,
+        /// Token decimals used to convert `amount` into base units.
+        decimals: u8,
+    },
+    /// ERC-20 `approve`.
+    Erc20Approve {
+        /// Token asset id.
+        asset: CaipAssetId,
+        /// Account allowed to spend the tokens.
+        spender: Address,
+        /// Human allowance amount.
+        amount: String,
+        /// Token decimals used to convert `amount` into base units.
+        decimals: u8,
+    },
+    /// ERC-721 `safeTransferFrom` of the token id named by `asset`.
+    Erc721Transfer {
+        /// Token asset id including the token id (e.g. "eip155:1/erc721:0x…/1").
+        asset: CaipAssetId,
+        /// Current owner.
+        from: Address,
+        /// Token recipient.
+        to: Address,
+    },
+    /// ERC-1155 `safeTransferFrom` of the token id named by `asset`.
+    Erc1155Transfer {
+        /// Token asset id including the token id.
+        asset: CaipAssetId,
+        /// Current holder.
+        from: Address,
+        /// Token recipient.
+        to: Address,
+        /// Number of tokens.
+        #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+        amount: U256,
+    },
+}
+
+impl Intent {
+    /// Lowers the intent into the transaction target, native value and calldata.
+    pub fn call(&self) -> Result<(Address, U256, Vec<u8>)> {
+        match &self.action {
+            IntentAction::Call { to, value, data } => Ok((*to, *value, data.clone())),
+            IntentAction::Erc20Transfer {
+                asset,
+                to,
+                amount,
+                decimals,
+            } => {
+                let call = TokenCall::Transfer {
+                    to: *to,
+                    amount: quantity::parse_units(amount, *decimals)?,
+                };
+                self.token_call(asset, "erc20", call)
+            }
+            IntentAction::Erc20Approve {
+                asset,
+                spender,
+                amount,
+                decimals,
+            } => {
+                let call = TokenCall::Approve {
+                    spender: *spender,
+                    amount: quantity::parse_units(amount, *decimals)?,
+                };
+                self.token_call(asset, "erc20", call)
+            }
+            IntentAction::Erc721Transfer { asset, from, to } => {
+                let call = TokenCall::SafeTransferFrom {
+                    from: *from,
+                    to: *to,
+                    token_id: token_id(asset)?,
+                    data: Vec::new(),
+                };
+                self.token_call(asset, "erc721", call)
+            }
+            IntentAction::Erc1155Transfer {
+                asset,
+                from,
+                to,
+                amount,
+            } => {
+                let call = TokenCall::Erc1155Transfer {
+                    from: *from,
+                    to: *to,
+                    id: token_id(asset)?,
+                    amount: *amount,
+                    data: Vec::new(),
+                };
+                self.token_call(asset, "erc1155", call)
+            }
+        }
+    }
+
+    /// Returns the account receiving the value or tokens, or the approved spender.
+    pub fn recipient(&self) -> Address {
+        match &self.action {
+            IntentAction::Call { to, .. }
+            | IntentAction::Erc20Transfer { to, .. }
+            | IntentAction::Erc721Transfer { to, .. }
+            | IntentAction::Erc1155Transfer { to, .. } => *to,
+            IntentAction::Erc20Approve { spender, .. } => *spender,
+        }
+    }
+
+    fn token_call(
+        &self,
+        asset: &CaipAssetId,
+        namespace: &str,
+        call: TokenCall,
+    ) -> Result<(Address, U256, Vec<u8>)> {
+        if asset.chain_id() != &self.chain_id {
+            return Err(WalletError::InvalidInput(format!(
+                "asset {asset} is not on chain {}",
+                self.chain_id
+            )));
+        }
+        if asset.asset_namespace() != namespace {
+            return Err(WalletError::InvalidInput(format!(
+                "asset {asset} is not an {namespace} token"
+            )));
+        }
+        if namespace == "erc20" && asset.token_id().is_some() {
+            return Err(WalletError::InvalidInput(format!(
+                "asset {asset} must not carry a token id"
+            )));
+        }
+        let contract: Address = asset.asset_reference().parse()?;
+        Ok((contract, U256::zero(), call.encode()))
+    }
+
+    fn audit_metadata(&self) -> Result<serde_json::Value> {
+        let (action, asset, amount) = match &self.action {
+            IntentAction::Call { .. } => ("call", None, None),
+            IntentAction::Erc20Transfer {
+                asset,
+                amount,
+                decimals,
+                ..
+            } => (
+                "erc20_transfer",
+                Some(asset),
+                Some(quantity::format_units(
+                    &quantity::parse_units(amount, *decimals)?,
+                    *decimals,
+                )),
+            ),
+            IntentAction::Erc20Approve {
+                asset,
+                amount,
+                decimals,
+                ..
+            } => (
+                "erc20_approve",
+                Some(asset),
+                Some(quantity::format_units(
+                    &quantity::parse_units(amount, *decimals)?,
+                    *decimals,
+                )),
+            ),
+            IntentAction::Erc721Transfer { asset, .. } => {
+                ("erc721_transfer", Some(asset), Some("1".to_string()))
+            }
+            IntentAction::Erc1155Transfer { asset, amount, .. } => {
+                ("erc1155_transfer", Some(asset), Some(amount.to_string()))
+            }
+        };
+        let mut metadata = json!({
+            "chain_id": self.chain_id.as_str(),
+            "nonce": self.nonce,
+            "action": action,
+            "to": self.recipient(),
+        });
+        if let Some(asset) = asset {
+            metadata["asset"] = json!(asset);
+        }
+        if let Some(amount) = amount {
+            metadata["amount"] = json!(amount);
+        }
+        Ok(metadata)
+    }
+}
+
+fn token_id(asset: &CaipAssetId) -> Result<U256> {
+    let token_id = asset
+        .token_id()
+        .ok_or_else(|| WalletError::InvalidInput(format!("asset {asset} has no token id")))?;
+    quantity::from_decimal(token_id)
 }
 
-/// Quote placeholder for gas estimates.
+/// Fee and gas parameters for an intent; build by hand or with [`Quoter`].
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Quote {
     /// Max priority fee per gas.
-    pub max_priority_fee_per_gas: u128,
+    #[serde(with = "ibank_wallet_chains::quantity")]
+    pub max_priority_fee_per_gas: U256,
     /// Max fee per gas.
-    pub max_fee_per_gas: u128,
+    #[serde(with = "ibank_wallet_chains::quantity")]
+    pub max_fee_per_gas: U256,
     /// Gas limit.
-    pub gas_limit: u128,
+    #[serde(with = "ibank_wallet_chains::quantity")]
+    pub gas_limit: U256,
     /// Access list (optional).
     pub access_list: AccessList,
 }
 
+/// Minimum fee bump, in basis points, nodes require to replace a pending
+/// transaction.
+pub const MIN_REPLACEMENT_BUMP_BPS: u64 = 1_000;
+
+impl Quote {
+    /// Returns the cheapest quote nodes accept as a replacement for this one:
+    /// both fee fields raised by [`MIN_REPLACEMENT_BUMP_BPS`], rounded up.
+    pub fn replacement(&self) -> Result<Quote> {
+        Ok(Quote {
+            max_priority_fee_per_gas: bump(self.max_priority_fee_per_gas)?,
+            max_fee_per_gas: bump(self.max_fee_per_gas)?,
+            ..self.clone()
+        })
+    }
+
+    /// Checks that this quote's fees are high enough to replace `original`.
+    pub fn check_replaces(&self, original: &Quote) -> Result<()> {
+        let minimum = original.replacement()?;
+        if self.max_priority_fee_per_gas < minimum.max_priority_fee_per_gas {
+            return Err(WalletError::InvalidInput(format!(
+                "replacement max priority fee per gas {} is below the minimum {}",
+                self.max_priority_fee_per_gas, minimum.max_priority_fee_per_gas
+            )));
+        }
+        if self.max_fee_per_gas < minimum.max_fee_per_gas {
+            return Err(WalletError::InvalidInput(format!(
+                "replacement max fee per gas {} is below the minimum {}",
+                self.max_fee_per_gas, minimum.max_fee_per_gas
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn bump(fee: U256) -> Result<U256> {
+    let scale = U256::from(10_000u64);
+    fee.checked_mul(scale + U256::from(MIN_REPLACEMENT_BUMP_BPS))
+        .map(|scaled| (scaled + scale - U256::one()) / scale)
+        .ok_or_else(|| WalletError::InvalidInput(format!("fee {fee} is too large to bump")))
+}
+
 /// Runtime orchestrator for intents.
 #[derive(Debug)]
 pub struct Runtime<P, S> {
@@ -44,6 +325,22 @@ pub struct Runtime<P, S> {
     pub signer: S,
     /// Audit log.
     pub audit_log: AuditLog,
+    /// Account the signer is expected to sign as; when set, every signed
+    /// transaction's recovered sender is checked against it.
+    pub expected_sender: Option<Address>,
+    /// Rolling-window limits; signed transactions are recorded in its ledger,
+    /// and versions signed for one nonce count once. Requires
+    /// `expected_sender`, which names the account being limited.
+    pub velocity: Option<VelocityPolicy>,
+    /// Intents held for approval.
+    pub pending: Box<dyn PendingStore>,
+    /// Node used by [`Runtime::submit_intent`].
+    pub rpc: Option<Box<dyn RpcClient>>,
+    /// Nonce reservations; signed transactions are marked in it.
+    /// Requires `expected_sender`.
+    pub nonces: Option<NonceManager>,
+    /// Intent records driven by [`Runtime::process_intent`].
+    pub intents: Box<dyn IntentStore>,
 }
 
 impl<P, S> Runtime<P, S>
@@ -57,51 +354,2359 @@ where
             policy,
             signer,
             audit_log: AuditLog::default(),
+            expected_sender: None,
+            velocity: None,
+            pending: Box::new(MemoryPendingStore::new()),
+            rpc: None,
+            nonces: None,
+            intents: Box::new(MemoryIntentStore::new()),
         }
     }
 
+    /// Sets the account signed transactions must recover to.
+    pub fn with_expected_sender(mut self, sender: Address) -> Self {
+        self.expected_sender = Some(sender);
+        self
+    }
+
+    /// Enforces velocity limits on signed transactions.
+    pub fn with_velocity(mut self, velocity: VelocityPolicy) -> Self {
+        self.velocity = Some(velocity);
+        self
+    }
+
+    /// Uses `store` for intents awaiting approval.
+    pub fn with_pending_store(mut self, store: impl PendingStore + 'static) -> Self {
+        self.pending = Box::new(store);
+        self
+    }
+
+    /// Submits signed transactions through `rpc`.
+    pub fn with_rpc(mut self, rpc: impl RpcClient + 'static) -> Self {
+        self.rpc = Some(Box::new(rpc));
+        self
+    }
+
+    /// Reserves nonces through `nonces` and marks them when signing.
+    pub fn with_nonce_manager(mut self, nonces: NonceManager) -> Self {
+        self.nonces = Some(nonces);
+        self
+    }
+
+    /// Persists intent records in `store`.
+    pub fn with_intent_store(mut self, store: impl IntentStore + 'static) -> Self {
+        self.intents = Box::new(store);
+        self
+    }
+
+    /// Reserves the next nonce of the expected sender on `chain_id`.
+    pub fn reserve_nonce(&self, chain_id: &CaipChainId) -> Result<u64> {
+        let (nonces, account) = self.nonce_account(chain_id)?;
+        nonces.reserve(self.rpc()?, &account)
+    }
+
+    /// Gives back a nonce reserved for an intent that will not be signed.
+    pub fn release_nonce(&self, chain_id: &CaipChainId, nonce: u64) -> Result<()> {
+        let (nonces, account) = self.nonce_account(chain_id)?;
+        nonces.release(&account, nonce)
+    }
+
+    /// Returns nonces of the expected sender the node has no transaction for.
+    pub fn nonce_gaps(&self, chain_id: &CaipChainId) -> Result<Vec<NonceGap>> {
+        let (nonces, account) = self.nonce_account(chain_id)?;
+        nonces.gaps(self.rpc()?, &account)
+    }
+
+    /// Fills every nonce gap with a zero-value transfer to the expected
+    /// sender, signed under policy with `quote`, and broadcasts them.
+    /// Returns the hashes of the filler transactions.
+    pub fn fill_nonce_gaps(
+        &mut self,
+        chain_id: &CaipChainId,
+        quote: &Quote,
+    ) -> Result<Vec<[u8; 32]>> {
+        let (_, account) = self.nonce_account(chain_id)?;
+        let sender = Address::from_caip10(&account)?;
+        let mut filled = Vec::new();
+        for gap in self.nonce_gaps(chain_id)? {
+            let intent = Intent {
+                chain_id: chain_id.clone(),
+                nonce: gap.nonce,
+                action: IntentAction::Call {
+                    to: sender,
+                    value: U256::zero(),
+                    data: Vec::new(),
+                },
+            };
+            let raw = self.sign_intent(&intent, quote)?;
+            let tx_hash = self.rpc()?.send_raw_transaction(&raw)?;
+            self.audit_log.record(AuditEvent {
+                name: "nonce_gap_filled".to_string(),
+                metadata: json!({
+                    "chain_id": chain_id,
+                    "nonce": gap.nonce,
+                    "tx_hash": hex_hash(&tx_hash),
+                    "dropped": gap.dropped,
+                }),
+            });
+            filled.push(tx_hash);
+        }
+        Ok(filled)
+    }
+
+    fn nonce_account(&self, chain_id: &CaipChainId) -> Result<(&NonceManager, CaipAccountId)> {
+        let nonces = self
+            .nonces
+            .as_ref()
+            .ok_or_else(|| WalletError::InvalidInput("no nonce manager configured".to_string()))?;
+        let sender = self.expected_sender.ok_or_else(|| {
+            WalletError::InvalidInput("nonce management requires an expected sender".to_string())
+        })?;
+        Ok((nonces, sender.to_caip10(chain_id)))
+    }
+
     /// Signs an intent after policy evaluation and audit logging.
+    ///
+    /// With velocity limits configured, the transaction is checked against
+    /// the ledger and recorded in one step after signing; a denial discards
+    /// the signature. Intents the policy holds for approvals or a delay are
+    /// stored and reported as [`WalletError::ApprovalRequired`]; see
+    /// [`Runtime::approve`] and [`Runtime::resume`]. Denials are audited with
+    /// the full decision.
     pub fn sign_intent(&mut self, intent: &Intent, quote: &Quote) -> Result<Vec<u8>> {
-        let chain_id = parse_chain_id(&intent.chain_id)?;
-        let tx = EvmUnsignedTx {
-            chain_id,
+        let tx = build_tx(intent, quote)?;
+        let decision = self.policy.evaluate_evm(&tx)?;
+        if decision.is_held() {
+            return Err(self.hold(intent, quote, &tx, decision)?);
+        }
+        self.enforce_audited(intent, decision)?;
+        self.sign_tx(intent, &tx, json!({}))
+    }
+
+    /// Re-signs an earlier signed intent at the same nonce with higher fees.
+    ///
+    /// Without `quote` the fees are the minimum nodes accept as a
+    /// replacement. The replacement is evaluated by policy again, fee caps
+    /// included, and may not be held; its audit event names the original
+    /// intent hash under `replaces`.
+    pub fn speed_up(
+        &mut self,
+        intent: &Intent,
+        original: &Quote,
+        quote: Option<&Quote>,
+    ) -> Result<Vec<u8>> {
+        let quote = match quote {
+            Some(quote) => quote.clone(),
+            None => original.replacement()?,
+        };
+        self.replace(intent, original, intent, &quote, "speed_up")
+    }
+
+    /// Replaces an earlier signed intent with a zero-value transfer from the
+    /// expected sender to itself at the same nonce.
+    ///
+    /// Without `quote` the replacement uses the minimum replacement fees and
+    /// a 21000 gas limit. Policy and auditing work as in
+    /// [`Runtime::speed_up`].
+    pub fn cancel(
+        &mut self,
+        intent: &Intent,
+        original: &Quote,
+        quote: Option<&Quote>,
+    ) -> Result<Vec<u8>> {
+        let (cancellation, quote) = self.cancellation(intent, original, quote)?;
+        self.replace(intent, original, &cancellation, &quote, "cancel")
+    }
+
+    fn cancellation(
+        &self,
+        intent: &Intent,
+        original: &Quote,
+        quote: Option<&Quote>,
+    ) -> Result<(Intent, Quote)> {
+        let sender = self.expected_sender.ok_or_else(|| {
+            WalletError::InvalidInput("cancellation requires an expected sender".to_string())
+        })?;
+        let cancellation = Intent {
+            chain_id: intent.chain_id.clone(),
             nonce: intent.nonce,
-            max_priority_fee_per_gas: quote.max_priority_fee_per_gas,
-            max_fee_per_gas: quote.max_fee_per_gas,
-            gas_limit: quote.gas_limit,
-            to: Some(intent.to),
-            value: intent.value,
-            data: intent.data.clone(),
-            access_list: quote.access_list.clone(),
+            action: IntentAction::Call {
+                to: sender,
+                value: U256::zero(),
+                data: Vec::new(),
+            },
+        };
+        let quote = match quote {
+            Some(quote) => quote.clone(),
+            None => Quote {
+                gas_limit: U256::from(21_000),
+                access_list: AccessList::default(),
+                ..original.replacement()?
+            },
         };
+        Ok((cancellation, quote))
+    }
 
+    fn replace(
+        &mut self,
+        original: &Intent,
+        original_quote: &Quote,
+        replacement: &Intent,
+        quote: &Quote,
+        kind: &str,
+    ) -> Result<Vec<u8>> {
+        quote.check_replaces(original_quote)?;
+        let tx = build_tx(replacement, quote)?;
         let decision = self.policy.evaluate_evm(&tx)?;
-        enforce(decision)?;
+        self.enforce_audited(replacement, decision)?;
+        let replaces = intent_hash(original, original_quote)?;
+        self.sign_tx(
+            replacement,
+            &tx,
+            json!({
+                "replaces": hex_hash(&replaces),
+                "replacement": kind,
+            }),
+        )
+    }
 
-        let signed = self
-            .signer
-            .sign_evm_eip1559(intent.chain_id.as_str(), &tx)?;
+    /// Signs an intent, broadcasts it and waits for its receipt.
+    ///
+    /// The node must be on the intent's chain and, with an expected sender,
+    /// must not have mined the intent's nonce yet. Reverted transactions
+    /// still return their receipt; check [`TransactionReceipt::status`].
+    pub fn submit_intent(
+        &mut self,
+        intent: &Intent,
+        quote: &Quote,
+        options: &SubmitOptions,
+    ) -> Result<TransactionReceipt> {
+        let rpc = self.rpc()?;
+        let chain_id = build_tx(intent, quote)?.chain_id;
+        let node_chain_id = rpc.chain_id()?;
+        if node_chain_id != chain_id {
+            return Err(WalletError::RpcError(format!(
+                "node is on chain {node_chain_id}, intent targets {chain_id}"
+            )));
+        }
+        if let Some(sender) = self.expected_sender {
+            let mined = rpc.transaction_count(&sender, BlockTag::Latest)?;
+            if intent.nonce < mined {
+                return Err(WalletError::InvalidInput(format!(
+                    "nonce {} already used; account nonce is {mined}",
+                    intent.nonce
+                )));
+            }
+        }
+
+        let raw = self.sign_intent(intent, quote)?;
+        let tx_hash = transaction_hash(&raw)?;
+        let reported = self.rpc()?.send_raw_transaction(&raw)?;
+        if reported != tx_hash {
+            return Err(WalletError::RpcError(format!(
+                "node reported hash {} for transaction {}",
+                hex_hash(&reported),
+                hex_hash(&tx_hash)
+            )));
+        }
+        let mut metadata = intent.audit_metadata()?;
+        metadata["tx_hash"] = json!(hex_hash(&tx_hash));
+        self.audit_log.record(AuditEvent {
+            name: "transaction_submitted".to_string(),
+            metadata,
+        });
+        self.wait_for_receipt(&tx_hash, options)
+    }
+
+    /// Polls for a transaction's receipt until it has `options.confirmations`
+    /// confirmations or `options.timeout` passes.
+    pub fn wait_for_receipt(
+        &mut self,
+        tx_hash: &[u8; 32],
+        options: &SubmitOptions,
+    ) -> Result<TransactionReceipt> {
+        self.wait_for_any_receipt(&[*tx_hash], options)
+            .map(|(_, receipt)| receipt)
+    }
+
+    /// Polls several transactions at one nonce until one of them has
+    /// `options.confirmations` confirmations, returning its index and receipt.
+    fn wait_for_any_receipt(
+        &mut self,
+        tx_hashes: &[[u8; 32]],
+        options: &SubmitOptions,
+    ) -> Result<(usize, TransactionReceipt)> {
+        let started = Instant::now();
+        loop {
+            let rpc = self.rpc()?;
+            for (index, tx_hash) in tx_hashes.iter().enumerate() {
+                let Some(receipt) = rpc.transaction_receipt(tx_hash)? else {
+                    continue;
+                };
+                let head = rpc.block_number()?;
+                let confirmations = (head + 1).saturating_sub(receipt.block_number);
+                if confirmations >= options.confirmations {
+                    self.audit_log.record(AuditEvent {
+                        name: "transaction_confirmed".to_string(),
+                        metadata: json!({
+                            "tx_hash": hex_hash(tx_hash),
+                            "block_number": receipt.block_number,
+                            "status": receipt.status,
+                            "confirmations": confirmations,
+                        }),
+                    });
+                    return Ok((index, receipt));
+                }
+            }
+            if started.elapsed() >= options.timeout {
+                let hashes: Vec<_> = tx_hashes.iter().map(hex_hash).collect();
+                return Err(WalletError::RpcError(format!(
+                    "transaction {} not confirmed after {:?}",
+                    hashes.join(" or "),
+                    options.timeout
+                )));
+            }
+            thread::sleep(options.poll_interval);
+        }
+    }
+
+    /// Records a new intent under `id`, or returns the existing record if
+    /// `id` was already used for the same intent.
+    pub fn create_intent(&mut self, id: IntentId, intent: &Intent) -> Result<IntentRecord> {
+        if let Some(existing) = self.intents.get(&id)? {
+            if to_json(&existing.intent)? != to_json(intent)? {
+                return Err(WalletError::InvalidInput(format!(
+                    "intent {id} already exists with different contents"
+                )));
+            }
+            return Ok(existing);
+        }
+        let record = IntentRecord::new(id, intent.clone(), unix_now());
+        self.intents.put(&record)?;
+        Ok(record)
+    }
+
+    /// Returns the record of intent `id`.
+    pub fn intent_record(&self, id: &IntentId) -> Result<IntentRecord> {
+        self.intents
+            .get(id)?
+            .ok_or_else(|| WalletError::InvalidInput(format!("no intent {id}")))
+    }
+
+    /// Advances intent `id` as far as it can go: evaluates and signs it,
+    /// broadcasts it and waits for its receipt.
+    ///
+    /// `quote` is required until the intent is signed and replaces an earlier
+    /// quote while it is still unsigned. Every state is stored before the
+    /// next step starts and signed transactions are stored before they are
+    /// broadcast, so calling this again after a crash rebroadcasts rather
+    /// than re-signs. A crash between signing and storing signs the same
+    /// transaction again; velocity and nonce records are kept per nonce, so
+    /// it is not counted twice. Intents whose pending entry is gone, e.g.
+    /// after a restart with a [`MemoryPendingStore`], are held again.
+    ///
+    /// A submitted intent waits for whichever of it, its speed-ups and its
+    /// cancellations is mined; that one becomes confirmed or failed and the
+    /// others replaced. Returns once the intent is terminal or waits for
+    /// approvals or a delay.
+    pub fn process_intent(
+        &mut self,
+        id: &IntentId,
+        quote: Option<&Quote>,
+        options: &SubmitOptions,
+    ) -> Result<IntentRecord> {
+        let mut record = self.intent_record(id)?;
+        loop {
+            match record.state {
+                IntentState::Created | IntentState::Quoted => {
+                    if let Some(quote) = quote {
+                        record.transition(IntentState::Quoted, unix_now())?;
+                        record.quote = Some(quote.clone());
+                        self.intents.put(&record)?;
+                    }
+                    let quote = record.quote.clone().ok_or_else(|| {
+                        WalletError::InvalidInput(format!("intent {id} needs a quote"))
+                    })?;
+                    let signed = self.sign_intent(&record.intent, &quote);
+                    if !self.settle_signing(&mut record, signed)? {
+                        return Ok(record);
+                    }
+                }
+                IntentState::PendingApproval => {
+                    let (hash, quote) = match (record.intent_hash, record.quote.clone()) {
+                        (Some(hash), Some(quote)) => (hash, quote),
+                        _ => {
+                            return Err(WalletError::InvalidInput(format!(
+                                "intent {id} is pending approval without a hash and quote"
+                            )))
+                        }
+                    };
+                    let signed = if self.pending.get(&hash)?.is_none() {
+                        // Lost with a store that does not persist; hold it again.
+                        record.reason = Some("held again; earlier approvals were lost".to_string());
+                        self.sign_intent(&record.intent, &quote)
+                    } else {
+                        let held = self
+                            .policy
+                            .evaluate_evm(&build_tx(&record.intent, &quote)?)?
+                            .is_held();
+                        match self.resume(&hash) {
+                            // Still waiting for approvals or a delay.
+                            Err(WalletError::PolicyViolation(reason))
+                                if held && self.pending.get(&hash)?.is_some() =>
+                            {
+                                record.reason = Some(reason);
+                                self.intents.put(&record)?;
+                                return Ok(record);
+                            }
+                            signed => signed,
+                        }
+                    };
+                    if !self.settle_signing(&mut record, signed)? {
+                        return Ok(record);
+                    }
+                }
+                IntentState::Signed => {
+                    let (raw, tx_hash) = match (&record.raw, record.tx_hash) {
+                        (Some(raw), Some(tx_hash)) => (raw.clone(), tx_hash),
+                        _ => {
+                            return Err(WalletError::InvalidInput(format!(
+                                "intent {id} is signed without a transaction"
+                            )))
+                        }
+                    };
+                    let rpc = self.rpc()?;
+                    if let Err(err) = rpc.send_raw_transaction(&raw) {
+                        // Nodes word rejected rebroadcasts differently; only
+                        // a receipt shows an earlier broadcast got through.
+                        if rpc.transaction_receipt(&tx_hash)?.is_none() {
+                            // The nonce may have gone to another version.
+                            let chain = self.replacement_chain(&record)?;
+                            let Some(winner) = self.mined_version(&chain)? else {
+                                return Err(err);
+                            };
+                            return self.finish_intent(
+                                record,
+                                IntentState::Replaced,
+                                format!("intent {winner} was mined at this nonce"),
+                            );
+                        }
+                    }
+                    let mut metadata = record.intent.audit_metadata()?;
+                    metadata["tx_hash"] = json!(hex_hash(&tx_hash));
+                    metadata["intent_id"] = json!(record.id);
+                    self.audit_log.record(AuditEvent {
+                        name: "transaction_submitted".to_string(),
+                        metadata,
+                    });
+                    record.transition(IntentState::Submitted, unix_now())?;
+                    self.intents.put(&record)?;
+                }
+                IntentState::Submitted => {
+                    if record.tx_hash.is_none() {
+                        return Err(WalletError::InvalidInput(format!(
+                            "intent {id} is submitted without a transaction hash"
+                        )));
+                    }
+                    let chain: Vec<_> = self
+                        .replacement_chain(&record)?
+                        .into_iter()
+                        .filter(|version| version.tx_hash.is_some())
+                        .collect();
+                    let hashes: Vec<_> =
+                        chain.iter().filter_map(|version| version.tx_hash).collect();
+                    let (winner, receipt) = self.wait_for_any_receipt(&hashes, options)?;
+                    self.settle_nonce(&chain, &chain[winner].id, receipt)?;
+                    return self.intent_record(id);
+                }
+                IntentState::PolicyApproved => {
+                    return Err(WalletError::InvalidInput(format!(
+                        "intent {id} was approved but never signed"
+                    )))
+                }
+                IntentState::PolicyDenied
+                | IntentState::Confirmed
+                | IntentState::Failed
+                | IntentState::Replaced => return Ok(record),
+            }
+        }
+    }
+
+    /// Continues every signed or submitted intent, e.g. after a restart.
+    ///
+    /// Signed intents are broadcast before submitted ones are waited on, so
+    /// stored speed-ups reach the node first. An intent that fails is left
+    /// as it is and recorded as `intent_recovery_failed`; the others still
+    /// run. Intents waiting for a quote or approvals are left for
+    /// [`Runtime::process_intent`].
+    pub fn recover_intents(&mut self, options: &SubmitOptions) -> Result<Vec<IntentRecord>> {
+        let mut active: Vec<_> = self
+            .intents
+            .active()?
+            .into_iter()
+            .filter(|record| matches!(record.state, IntentState::Signed | IntentState::Submitted))
+            .collect();
+        active.sort_by_key(|record| record.state != IntentState::Signed);
+        let mut recovered = Vec::new();
+        for record in active {
+            match self.process_intent(&record.id, None, options) {
+                Ok(record) => recovered.push(record),
+                Err(err) => self.audit_log.record(AuditEvent {
+                    name: "intent_recovery_failed".to_string(),
+                    metadata: json!({
+                        "intent_id": record.id,
+                        "state": record.state,
+                        "error": err.to_string(),
+                    }),
+                }),
+            }
+        }
+        Ok(recovered)
+    }
+
+    /// Records a [`Runtime::speed_up`] of intent `id` as a new signed intent
+    /// linked to `id`. Broadcast it with [`Runtime::process_intent`]; `id`
+    /// stays active until one of them is mined.
+    pub fn speed_up_intent(
+        &mut self,
+        id: &IntentId,
+        quote: Option<&Quote>,
+    ) -> Result<IntentRecord> {
+        self.replace_intent(id, quote, "speed_up")
+    }
+
+    /// Records a [`Runtime::cancel`] of intent `id` as a new signed intent
+    /// linked to `id`, which stays active until one of them is mined.
+    pub fn cancel_intent(&mut self, id: &IntentId, quote: Option<&Quote>) -> Result<IntentRecord> {
+        self.replace_intent(id, quote, "cancel")
+    }
+
+    fn replace_intent(
+        &mut self,
+        id: &IntentId,
+        quote: Option<&Quote>,
+        kind: &str,
+    ) -> Result<IntentRecord> {
+        let mut original = self.intent_record(id)?;
+        let original_quote = match (original.state, &original.quote) {
+            (IntentState::Signed | IntentState::Submitted, Some(quote)) => quote.clone(),
+            _ => {
+                return Err(WalletError::InvalidInput(format!(
+                    "intent {id} is {}; only signed or submitted intents can be replaced",
+                    original.state
+                )))
+            }
+        };
+        if let Some(newer) = &original.replaced_by {
+            return Err(WalletError::InvalidInput(format!(
+                "intent {id} was already replaced by {newer}; replace that instead"
+            )));
+        }
+        let (intent, quote) = if kind == "cancel" {
+            self.cancellation(&original.intent, &original_quote, quote)?
+        } else {
+            let quote = match quote {
+                Some(quote) => quote.clone(),
+                None => original_quote.replacement()?,
+            };
+            (original.intent.clone(), quote)
+        };
+        let raw = self.replace(&original.intent, &original_quote, &intent, &quote, kind)?;
+
+        let now = unix_now();
+        let mut replacement = IntentRecord::new(IntentId::generate(), intent, now);
+        replacement.transition(IntentState::Quoted, now)?;
+        replacement.quote = Some(quote);
+        replacement.replacement_of = Some(original.id.clone());
+        self.record_signed(&mut replacement, raw)?;
+        original.replaced_by = Some(replacement.id.clone());
+        self.intents.put(&original)?;
+        Ok(replacement)
+    }
+
+    /// Returns the versions of `record`'s transaction linked by speed-ups
+    /// and cancellations, oldest first.
+    fn replacement_chain(&self, record: &IntentRecord) -> Result<Vec<IntentRecord>> {
+        let mut chain = vec![record.clone()];
+        while let Some(older) = chain[0].replacement_of.clone() {
+            chain.insert(0, self.intent_record(&older)?);
+        }
+        while let Some(newer) = chain.last().and_then(|last| last.replaced_by.clone()) {
+            chain.push(self.intent_record(&newer)?);
+        }
+        Ok(chain)
+    }
+
+    /// Returns the id of the version in `chain` the node has a receipt for.
+    fn mined_version(&self, chain: &[IntentRecord]) -> Result<Option<IntentId>> {
+        let rpc = self.rpc()?;
+        for version in chain {
+            if let Some(tx_hash) = version.tx_hash {
+                if rpc.transaction_receipt(&tx_hash)?.is_some() {
+                    return Ok(Some(version.id.clone()));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Settles the versions of a transaction once `winner` is mined with
+    /// `receipt`: it is confirmed, or failed if it reverted, and the others
+    /// are replaced.
+    fn settle_nonce(
+        &mut self,
+        chain: &[IntentRecord],
+        winner: &IntentId,
+        receipt: TransactionReceipt,
+    ) -> Result<()> {
+        for version in chain {
+            let mut version = self.intent_record(&version.id)?;
+            if version.state.is_terminal() {
+                continue;
+            }
+            let now = unix_now();
+            if &version.id != winner {
+                version.transition(IntentState::Replaced, now)?;
+                version.reason = Some(format!("intent {winner} was mined at this nonce"));
+            } else {
+                if version.state == IntentState::Signed {
+                    version.transition(IntentState::Submitted, now)?;
+                }
+                if receipt.status {
+                    version.transition(IntentState::Confirmed, now)?;
+                } else {
+                    version.transition(IntentState::Failed, now)?;
+                    version.reason = Some("transaction reverted".to_string());
+                }
+                version.receipt = Some(receipt.clone());
+            }
+            self.intents.put(&version)?;
+        }
+        Ok(())
+    }
+
+    /// Stores the outcome of signing `record`. Returns false if the intent
+    /// now waits for approvals or was denied.
+    fn settle_signing(
+        &mut self,
+        record: &mut IntentRecord,
+        signed: Result<Vec<u8>>,
+    ) -> Result<bool> {
+        match signed {
+            Ok(raw) => {
+                self.record_signed(record, raw)?;
+                Ok(true)
+            }
+            Err(WalletError::ApprovalRequired(_)) => {
+                if record.state != IntentState::PendingApproval {
+                    record.transition(IntentState::PendingApproval, unix_now())?;
+                    let quote = record.quote.as_ref().ok_or_else(|| {
+                        WalletError::InvalidInput(format!("intent {} has no quote", record.id))
+                    })?;
+                    record.intent_hash = Some(intent_hash(&record.intent, quote)?);
+                }
+                self.intents.put(record)?;
+                Ok(false)
+            }
+            Err(WalletError::PolicyViolation(reason)) => {
+                record.transition(IntentState::PolicyDenied, unix_now())?;
+                record.reason = Some(reason);
+                self.intents.put(record)?;
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn record_signed(&mut self, record: &mut IntentRecord, raw: Vec<u8>) -> Result<()> {
+        let now = unix_now();
+        record.transition(IntentState::PolicyApproved, now)?;
+        record.transition(IntentState::Signed, now)?;
+        record.tx_hash = Some(transaction_hash(&raw)?);
+        record.raw = Some(raw);
+        record.reason = None;
+        self.intents.put(record)
+    }
+
+    fn finish_intent(
+        &mut self,
+        mut record: IntentRecord,
+        state: IntentState,
+        reason: String,
+    ) -> Result<IntentRecord> {
+        record.transition(state, unix_now())?;
+        record.reason = Some(reason);
+        self.intents.put(&record)?;
+        Ok(record)
+    }
+
+    fn rpc(&self) -> Result<&dyn RpcClient> {
+        self.rpc
+            .as_deref()
+            .ok_or_else(|| WalletError::RpcError("no rpc client configured".to_string()))
+    }
+
+    /// Evaluates an intent as [`Runtime::sign_intent`] would, without
+    /// signing, holding, auditing or recording anything.
+    pub fn explain(&self, intent: &Intent, quote: &Quote) -> Result<Explanation> {
+        let tx = build_tx(intent, quote)?;
+        let decision = self.policy.evaluate_evm(&tx)?;
+        let velocity = match (&self.velocity, self.expected_sender) {
+            (Some(velocity), Some(sender)) => {
+                Some(velocity.check(&sender.to_caip10(&intent.chain_id), &tx, unix_now())?)
+            }
+            (Some(_), None) => Some(PolicyDecision::deny(
+                "velocity limits require an expected sender",
+            )),
+            (None, _) => None,
+        };
+        Ok(Explanation {
+            intent_hash: hex_hash(&tx.signing_payload_hash()),
+            tx,
+            decision,
+            velocity,
+        })
+    }
+
+    /// Adds an approval to a pending intent and returns the number of
+    /// distinct approvers so far.
+    pub fn approve(&mut self, intent_hash: &[u8; 32], approval: Approval) -> Result<usize> {
+        let mut pending = self.pending_intent(intent_hash)?;
+        let hash = hex_hash(intent_hash);
+        // Valid if any quorum accepts it; otherwise report the last rejection.
+        let eligible = pending
+            .quorums()
+            .map(|quorum| quorum.verify(intent_hash, &approval))
+            .reduce(Result::or)
+            .unwrap_or_else(|| {
+                Err(WalletError::PolicyViolation(format!(
+                    "intent {hash} has no quorum"
+                )))
+            });
+        if let Err(err) = eligible {
+            self.audit_log.record(AuditEvent {
+                name: "approval_rejected".to_string(),
+                metadata: json!({
+                    "intent_hash": hash,
+                    "approver": approval.approver,
+                    "reason": err.to_string(),
+                }),
+            });
+            return Err(err);
+        }
 
         self.audit_log.record(AuditEvent {
-            name: "sign_evm_eip1559".to_string(),
+            name: "approval_recorded".to_string(),
             metadata: json!({
-                "chain_id": intent.chain_id.as_str(),
-                "nonce": intent.nonce,
-                "to": hex::encode(intent.to),
-                "value": intent.value,
+                "intent_hash": hash,
+                "approver": approval.approver,
             }),
         });
+        pending
+            .approvals
+            .retain(|existing| existing.approver != approval.approver);
+        pending.approvals.push(approval);
+        let count = pending.approvals.len();
+        self.pending.put(pending)?;
+        Ok(count)
+    }
+
+    /// Signs a pending intent once every quorum has approved it and any
+    /// delay has passed.
+    ///
+    /// The intent is evaluated again; approvals only satisfy the quorums the
+    /// current policy asks for, and delays count from when it was held.
+    pub fn resume(&mut self, intent_hash: &[u8; 32]) -> Result<Vec<u8>> {
+        let pending = self.pending_intent(intent_hash)?;
+        let hash = hex_hash(intent_hash);
+        let tx = build_tx(&pending.intent, &pending.quote)?;
+        let decision = self.policy.evaluate_evm(&tx)?;
+        if decision.is_held() {
+            if let Some(quorum) = decision
+                .approvals()
+                .find(|quorum| !quorum.is_met(intent_hash, &pending.approvals))
+            {
+                return Err(WalletError::PolicyViolation(format!(
+                    "intent {hash} has {} of {} required approvals",
+                    quorum.approved_by(intent_hash, &pending.approvals).len(),
+                    quorum.threshold
+                )));
+            }
+            let not_before = pending
+                .not_before
+                .max(pending.created_at.saturating_add(decision.delay_secs()));
+            if unix_now() < not_before {
+                return Err(WalletError::PolicyViolation(format!(
+                    "intent {hash} is delayed until {not_before}"
+                )));
+            }
+        } else {
+            self.enforce_audited(&pending.intent, decision)?;
+        }
 
+        let approved_by: Vec<_> = pending
+            .approvals
+            .iter()
+            .map(|approval| approval.approver.clone())
+            .collect();
+        let extra = if approved_by.is_empty() {
+            json!({})
+        } else {
+            json!({ "approved_by": approved_by })
+        };
+        let signed = self.sign_tx(&pending.intent, &tx, extra)?;
+        self.pending.remove(intent_hash)?;
         Ok(signed)
     }
+
+    fn hold(
+        &mut self,
+        intent: &Intent,
+        quote: &Quote,
+        tx: &EvmUnsignedTx,
+        decision: PolicyDecision,
+    ) -> Result<WalletError> {
+        let intent_hash = tx.signing_payload_hash();
+        let created_at = unix_now();
+        // Holding an intent again keeps the approvals and deadlines it has.
+        if let Some(existing) = self.pending.get(&intent_hash)? {
+            if !existing.is_expired(created_at) {
+                return Ok(WalletError::ApprovalRequired(hex_hash(&intent_hash)));
+            }
+        }
+        // Delay-only holds do not expire.
+        let expires_at = decision
+            .approvals()
+            .map(|quorum| created_at.saturating_add(quorum.ttl_secs))
+            .min()
+            .unwrap_or(u64::MAX);
+        let pending = PendingIntent {
+            intent_hash,
+            intent: intent.clone(),
+            quote: quote.clone(),
+            obligations: decision.obligations.clone(),
+            approvals: Vec::new(),
+            created_at,
+            not_before: created_at.saturating_add(decision.delay_secs()),
+            expires_at,
+        };
+
+        let mut metadata = intent.audit_metadata()?;
+        metadata["intent_hash"] = json!(hex_hash(&intent_hash));
+        metadata["rule"] = json!(decision.rule);
+        metadata["code"] = json!(decision.code);
+        metadata["reason"] = json!(decision.reason);
+        metadata["quorums"] = pending
+            .quorums()
+            .map(|quorum| {
+                json!({
+                    "threshold": quorum.threshold,
+                    "approvers": quorum
+                        .approvers
+                        .iter()
+                        .map(|approver| approver.id.as_str())
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        metadata["not_before"] = json!(pending.not_before);
+        metadata["expires_at"] = json!(pending.expires_at);
+        self.pending.put(pending)?;
+        self.audit_log.record(AuditEvent {
+            name: "intent_held".to_string(),
+            metadata,
+        });
+        Ok(WalletError::ApprovalRequired(hex_hash(&intent_hash)))
+    }
+
+    /// Enforces a decision, recording denials with their rule, reason code
+    /// and trace.
+    fn enforce_audited(&mut self, intent: &Intent, decision: PolicyDecision) -> Result<()> {
+        if decision.allowed {
+            return Ok(());
+        }
+        let metadata = intent.audit_metadata()?;
+        self.deny_audited(metadata, decision)
+    }
+
+    /// Records a denial under `metadata` and returns its error.
+    fn deny_audited(
+        &mut self,
+        mut metadata: serde_json::Value,
+        decision: PolicyDecision,
+    ) -> Result<()> {
+        metadata["decision"] = json!(decision);
+        self.audit_log.record(AuditEvent {
+            name: "policy_denied".to_string(),
+            metadata,
+        });
+        enforce(decision)
+    }
+
+    /// Loads a pending intent, dropping it if it has expired.
+    fn pending_intent(&mut self, intent_hash: &[u8; 32]) -> Result<PendingIntent> {
+        let hash = hex_hash(intent_hash);
+        let pending = self
+            .pending
+            .get(intent_hash)?
+            .ok_or_else(|| WalletError::InvalidInput(format!("no pending intent {hash}")))?;
+        if pending.is_expired(unix_now()) {
+            self.pending.remove(intent_hash)?;
+            self.audit_log.record(AuditEvent {
+                name: "approval_expired".to_string(),
+                metadata: json!({
+                    "intent_hash": hash,
+                    "expires_at": pending.expires_at,
+                }),
+            });
+            return Err(WalletError::PolicyViolation(format!(
+                "approval window for intent {hash} has expired"
+            )));
+        }
+        Ok(pending)
+    }
+
+    /// Signs an evaluated transaction, adding the fields of `extra` to its
+    /// audit event.
+    fn sign_tx(
+        &mut self,
+        intent: &Intent,
+        tx: &EvmUnsignedTx,
+        extra: serde_json::Value,
+    ) -> Result<Vec<u8>> {
+        let mut metadata = intent.audit_metadata()?;
+        metadata["value"] = json!(quantity::to_hex(&tx.value));
+        metadata["intent_hash"] = json!(hex_hash(&tx.signing_payload_hash()));
+        if let (Some(metadata), serde_json::Value::Object(extra)) =
+            (metadata.as_object_mut(), extra)
+        {
+            metadata.extend(extra);
+        }
+        let chain_id = intent.chain_id.clone();
+        self.sign_checked("sign_evm_eip1559", tx, metadata, |signer| {
+            signer.sign_evm_eip1559(&chain_id, tx)
+        })
+    }
+
+    /// Records the spend of `call` against velocity limits, signs with
+    /// `sign`, checks the recovered sender, marks the nonce signed and writes
+    /// the `event` audit record. The spend is forgotten if any step after
+    /// recording it fails.
+    fn sign_checked(
+        &mut self,
+        event: &str,
+        call: &EvmUnsignedTx,
+        mut metadata: serde_json::Value,
+        sign: impl FnOnce(&S) -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        if self.velocity.is_some() && self.expected_sender.is_none() {
+            return Err(WalletError::PolicyViolation(
+                "velocity limits require an expected sender".to_string(),
+            ));
+        }
+        if self.nonces.is_some() && self.expected_sender.is_none() {
+            return Err(WalletError::InvalidInput(
+                "nonce management requires an expected sender".to_string(),
+            ));
+        }
+
+        let chain_id = CaipChainId::eip155(call.chain_id);
+        let spend = match (self.velocity.clone(), self.expected_sender) {
+            (Some(velocity), Some(sender)) => {
+                let account = sender.to_caip10(&chain_id);
+                let now = unix_now();
+                // Keyed by nonce so replacements count once with the original.
+                let decision = velocity.record_nonce(&account, call, now)?;
+                if !decision.allowed {
+                    self.deny_audited(metadata.clone(), decision)?;
+                }
+                Some((velocity, account, now))
+            }
+            _ => None,
+        };
+
+        let signed = self.sign_and_mark(&chain_id, call, &mut metadata, sign);
+        if let (Err(_), Some((velocity, account, now))) = (&signed, &spend) {
+            velocity.forget_nonce(account, call, *now)?;
+        }
+        let signed = signed?;
+
+        self.audit_log.record(AuditEvent {
+            name: event.to_string(),
+            metadata,
+        });
+
+        Ok(signed)
+    }
+
+    fn sign_and_mark(
+        &self,
+        chain_id: &CaipChainId,
+        call: &EvmUnsignedTx,
+        metadata: &mut serde_json::Value,
+        sign: impl FnOnce(&S) -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let signed = sign(&self.signer)?;
+        let Some(expected) = self.expected_sender else {
+            return Ok(signed);
+        };
+        let sender = recover_sender(&signed)?;
+        if sender != expected {
+            return Err(WalletError::SigningError(format!(
+                "recovered sender {sender} does not match expected account {expected}"
+            )));
+        }
+        let tx_hash = transaction_hash(&signed)?;
+        metadata["from"] = json!(sender);
+        metadata["tx_hash"] = json!(hex_hash(&tx_hash));
+        if let Some(nonces) = &self.nonces {
+            nonces.mark_signed(&sender.to_caip10(chain_id), call.nonce, tx_hash)?;
+        }
+        Ok(signed)
+    }
+
+    /// Signs an EIP-7702 authorization after policy evaluation and audit logging.
+    pub fn sign_authorization(
+        &mut self,
+        derivation_path: &str,
+        authorization: &Authorization,
+    ) -> Result<SignedAuthorization> {
+        self.enforce_authorization(authorization)?;
+
+        let signed = self
+            .signer
+            .sign_evm_authorization(derivation_path, authorization)?;
+
+        self.audit_log.record(AuditEvent {
+            name: "sign_evm_authorization".to_string(),
+            metadata: authorization_metadata(authorization),
+        });
+
+        Ok(signed)
+    }
+
+    /// Signs an EIP-7702 transaction at `derivation_path`.
+    ///
+    /// Its call is evaluated like an EIP-1559 transaction and every entry of
+    /// its authorization list like [`Runtime::sign_authorization`], so the
+    /// delegations it carries pass the same checks as ones signed here.
+    pub fn sign_eip7702(&mut self, derivation_path: &str, tx: &EvmEip7702Tx) -> Result<Vec<u8>> {
+        let call = tx.call();
+        let mut metadata = json!({
+            "chain_id": CaipChainId::eip155(tx.chain_id),
+            "to": tx.to,
+            "value": quantity::to_hex(&tx.value),
+            "nonce": tx.nonce,
+            "delegates": tx.delegates(),
+        });
+        let decision = self.policy.evaluate_evm(&call)?;
+        if !decision.allowed {
+            self.deny_audited(metadata.clone(), decision)?;
+        }
+        for signed in &tx.authorization_list {
+            self.enforce_authorization(&signed.authorization)?;
+        }
+
+        metadata["intent_hash"] = json!(hex_hash(&tx.signing_payload_hash()));
+        let chain_id = CaipChainId::eip155(tx.chain_id);
+        let typed = EvmTypedTx::Eip7702(tx.clone());
+        self.sign_checked("sign_evm_eip7702", &call, metadata, |signer| {
+            signer.sign_evm_transaction(derivation_path, &chain_id, &typed)
+        })
+    }
+
+    /// Evaluates an authorization, recording a denial in the audit log.
+    fn enforce_authorization(&mut self, authorization: &Authorization) -> Result<()> {
+        let decision = self.policy.evaluate_evm_authorization(authorization)?;
+        if decision.allowed {
+            return Ok(());
+        }
+        self.deny_audited(authorization_metadata(authorization), decision)
+    }
+}
+
+/// Audit metadata describing an EIP-7702 authorization.
+fn authorization_metadata(authorization: &Authorization) -> serde_json::Value {
+    json!({
+        "chain_id": authorization.chain_id,
+        "delegate": authorization.address,
+        "nonce": authorization.nonce,
+    })
+}
+
+/// Builds the EIP-1559 transaction for an intent.
+fn build_tx(intent: &Intent, quote: &Quote) -> Result<EvmUnsignedTx> {
+    let (to, value, data) = intent.call()?;
+    Ok(EvmUnsignedTx {
+        chain_id: intent.chain_id.evm_chain_id()?,
+        nonce: intent.nonce,
+        max_priority_fee_per_gas: quote.max_priority_fee_per_gas,
+        max_fee_per_gas: quote.max_fee_per_gas,
+        gas_limit: quote.gas_limit,
+        to: Some(to),
+        value,
+        data,
+        access_list: quote.access_list.clone(),
+    })
+}
+
+/// Returns the hash approvers sign for an intent: the hash of its EIP-1559
+/// signing payload, which also binds the quoted fees.
+pub fn intent_hash(intent: &Intent, quote: &Quote) -> Result<[u8; 32]> {
+    Ok(build_tx(intent, quote)?.signing_payload_hash())
+}
+
+/// Result of [`Runtime::explain`].
+#[derive(Clone, Debug, Serialize)]
+pub struct Explanation {
+    /// Hash approvers would sign (see [`intent_hash`]).
+    pub intent_hash: String,
+    /// Transaction that would be signed.
+    pub tx: EvmUnsignedTx,
+    /// Policy decision including the rule trace.
+    pub decision: PolicyDecision,
+    /// Velocity limit check against the current ledger, if configured.
+    pub velocity: Option<PolicyDecision>,
 }
 
-fn parse_chain_id(chain_id: &CaipChainId) -> Result<u64> {
-    let value = chain_id.as_str();
-    let trimmed = value
-        .strip_prefix("eip155:")
-        .ok_or_else(|| WalletError::InvalidInput("unsupported chain id".to_string()))?;
-    trimmed
-        .parse::<u64>()
-        .map_err(|_| WalletError::InvalidInput("invalid chain id".to_string()))
+fn to_json(value: &impl Serialize) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|err| WalletError::InvalidInput(err.to_string()))
+}
+
+fn hex_hash(hash: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ibank_wallet_chains::{
+        personal_message_hash, EvmEip7702TxBuilder, EvmSignedTypedTx, EvmTypedTx, TypedData,
+    };
+    use ibank_wallet_policy::{
+        AllowListPolicy, ApprovalPolicy, Approver, ApproverKey, FeeCapPolicy, MemorySpendLedger,
+        Outcome, Quorum, ReasonCode, Rule, SpendLedger, SpendLimitPolicy, VelocityLimit,
+    };
+    use k256::ecdsa::SigningKey;
+    use rpc::mock::MockRpcServer;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Signs with an in-memory secp256k1 key.
+    struct KeySigner(SigningKey);
+
+    impl KeySigner {
+        fn new(seed: u8) -> Self {
+            Self(SigningKey::from_slice(&[seed; 32]).expect("key"))
+        }
+
+        fn address(&self) -> Address {
+            let point = self.0.verifying_key().to_encoded_point(false);
+            Address::from_public_key(point.as_bytes()).expect("address")
+        }
+
+        fn sign_digest(&self, digest: &[u8; 32]) -> Vec<u8> {
+            let (signature, recovery_id) =
+                self.0.sign_prehash_recoverable(digest).expect("signature");
+            let mut bytes = signature.to_bytes().to_vec();
+            bytes.push(recovery_id.to_byte() + 27);
+            bytes
+        }
+    }
+
+    impl Signer for KeySigner {
+        fn sign_evm_eip1559(&self, chain_id: &CaipChainId, tx: &EvmUnsignedTx) -> Result<Vec<u8>> {
+            self.sign_evm_transaction("", chain_id, &EvmTypedTx::Eip1559(tx.clone()))
+        }
+
+        fn sign_evm_transaction(
+            &self,
+            _path: &str,
+            _chain_id: &CaipChainId,
+            tx: &EvmTypedTx,
+        ) -> Result<Vec<u8>> {
+            let signature = self.sign_digest(&tx.signing_payload_hash());
+            Ok(EvmSignedTypedTx::from_signature(tx.clone(), &signature)?.encode())
+        }
+
+        fn sign_evm_personal_message(&self, _path: &str, message: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.sign_digest(&personal_message_hash(message)))
+        }
+
+        fn sign_evm_typed_data(&self, _path: &str, typed_data: &TypedData) -> Result<Vec<u8>> {
+            Ok(self.sign_digest(&typed_data.signing_hash()?))
+        }
+
+        fn sign_evm_authorization(
+            &self,
+            _path: &str,
+            authorization: &Authorization,
+        ) -> Result<SignedAuthorization> {
+            let signature = self.sign_digest(&authorization.signing_hash());
+            SignedAuthorization::from_signature(authorization.clone(), &signature)
+        }
+    }
+
+    fn intent() -> Intent {
+        Intent {
+            chain_id: CaipChainId::eip155(1),
+            nonce: 0,
+            action: IntentAction::Call {
+                to: Address([0x22; 20]),
+                value: U256::from(1_000),
+                data: Vec::new(),
+            },
+        }
+    }
+
+    fn quote() -> Quote {
+        Quote {
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(2),
+            gas_limit: U256::from(21_000),
+            access_list: AccessList::default(),
+        }
+    }
+
+    #[test]
+    fn sign_intent_checks_recovered_sender() {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let mut runtime =
+            Runtime::new(SpendLimitPolicy::new(10_000), signer).with_expected_sender(sender);
+
+        let raw = runtime.sign_intent(&intent(), &quote()).expect("signed");
+        let event = runtime.audit_log.events.last().expect("audit event");
+        assert_eq!(event.metadata["from"], json!(sender.to_string()));
+        assert_eq!(
+            event.metadata["tx_hash"],
+            json!(format!(
+                "0x{}",
+                hex::encode(transaction_hash(&raw).expect("hash"))
+            ))
+        );
+    }
+
+    #[test]
+    fn sign_intent_rejects_unexpected_sender() {
+        let mut runtime = Runtime::new(SpendLimitPolicy::new(10_000), KeySigner::new(0x11))
+            .with_expected_sender(Address([0x33; 20]));
+
+        assert!(matches!(
+            runtime.sign_intent(&intent(), &quote()),
+            Err(WalletError::SigningError(_))
+        ));
+        assert!(runtime.audit_log.events.is_empty());
+    }
+
+    #[test]
+    fn sign_intent_records_spends_against_velocity_limits() {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let ledger = Arc::new(MemorySpendLedger::new());
+        let velocity = VelocityPolicy::new(ledger.clone()).with_limit(VelocityLimit::Value {
+            window_secs: 86_400,
+            max_value: U256::from(2_500),
+        });
+        let mut runtime = Runtime::new(SpendLimitPolicy::new(10_000), signer)
+            .with_expected_sender(sender)
+            .with_velocity(velocity);
+
+        let at = |nonce| Intent { nonce, ..intent() };
+        runtime.sign_intent(&at(0), &quote()).expect("first");
+        runtime.sign_intent(&at(1), &quote()).expect("second");
+        assert!(matches!(
+            runtime.sign_intent(&at(2), &quote()),
+            Err(WalletError::PolicyViolation(reason)) if reason.contains("velocity limit")
+        ));
+        assert_eq!(runtime.audit_log.events.len(), 3);
+        let denied = runtime.audit_log.events.last().expect("denial");
+        assert_eq!(denied.name, "policy_denied");
+        assert_eq!(denied.metadata["decision"]["code"], json!("velocity_limit"));
+        let account = sender.to_caip10(&CaipChainId::eip155(1));
+        assert_eq!(ledger.records(&account, 0).expect("records").len(), 2);
+
+        let mut unbound = Runtime::new(SpendLimitPolicy::new(10_000), KeySigner::new(0x11))
+            .with_velocity(VelocityPolicy::new(ledger));
+        assert!(unbound.sign_intent(&intent(), &quote()).is_err());
+    }
+
+    /// Fails every update, as a store whose disk is gone would.
+    #[derive(Debug)]
+    struct BrokenNonceStore;
+
+    impl NonceStore for BrokenNonceStore {
+        fn update(
+            &self,
+            _account: &CaipAccountId,
+            _update: &mut dyn FnMut(&mut AccountNonces),
+        ) -> Result<AccountNonces> {
+            Err(WalletError::InvalidInput(
+                "nonce store unavailable".to_string(),
+            ))
+        }
+    }
+
+    #[test]
+    fn failed_signing_gives_back_velocity_room() {
+        let ledger = Arc::new(MemorySpendLedger::new());
+        let velocity = VelocityPolicy::new(ledger.clone()).with_limit(VelocityLimit::Count {
+            window_secs: 86_400,
+            max_count: 1,
+        });
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let account = sender.to_caip10(&CaipChainId::eip155(1));
+
+        // Signed as the wrong account.
+        let mut runtime = Runtime::new(SpendLimitPolicy::new(10_000), KeySigner::new(0x12))
+            .with_expected_sender(sender)
+            .with_velocity(velocity.clone());
+        assert!(matches!(
+            runtime.sign_intent(&intent(), &quote()),
+            Err(WalletError::SigningError(_))
+        ));
+        assert!(ledger.records(&account, 0).expect("records").is_empty());
+
+        // Signed, but the nonce could not be marked.
+        let mut runtime = Runtime::new(SpendLimitPolicy::new(10_000), signer)
+            .with_expected_sender(sender)
+            .with_velocity(velocity)
+            .with_nonce_manager(NonceManager::new(BrokenNonceStore));
+        assert!(runtime.sign_intent(&intent(), &quote()).is_err());
+        assert!(ledger.records(&account, 0).expect("records").is_empty());
+
+        runtime.nonces = None;
+        runtime
+            .sign_intent(&intent(), &quote())
+            .expect("room was given back");
+        assert_eq!(ledger.records(&account, 0).expect("records").len(), 1);
+    }
+
+    fn treasury_policy(ttl_secs: u64) -> ApprovalPolicy {
+        let approvers = [0x41, 0x42, 0x43]
+            .into_iter()
+            .map(|seed| {
+                Approver::new(
+                    format!("approver-{seed:x}"),
+                    ApproverKey::Secp256k1(KeySigner::new(seed).address()),
+                )
+            })
+            .collect();
+        ApprovalPolicy::new(500u64, Quorum::new(2, approvers, ttl_secs))
+    }
+
+    fn approval(seed: u8, intent_hash: &[u8; 32]) -> Approval {
+        let signature = KeySigner::new(seed)
+            .sign_evm_personal_message("", intent_hash)
+            .expect("signature");
+        Approval::new(format!("approver-{seed:x}"), signature)
+    }
+
+    #[test]
+    fn holds_large_transfers_until_quorum_approves() {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let mut runtime = Runtime::new(treasury_policy(3_600), signer).with_expected_sender(sender);
+        let hash = intent_hash(&intent(), &quote()).expect("hash");
+
+        let Err(WalletError::ApprovalRequired(pending)) = runtime.sign_intent(&intent(), &quote())
+        else {
+            panic!("expected the intent to be held");
+        };
+        assert_eq!(pending, format!("0x{}", hex::encode(hash)));
+        let requested = &runtime.audit_log.events[0];
+        assert_eq!(requested.name, "intent_held");
+        assert_eq!(requested.metadata["quorums"][0]["threshold"], json!(2));
+
+        assert!(runtime.approve(&hash, approval(0x99, &hash)).is_err());
+        assert!(runtime.approve(&hash, approval(0x42, &[0u8; 32])).is_err());
+        assert_eq!(
+            runtime
+                .approve(&hash, approval(0x42, &hash))
+                .expect("approve"),
+            1
+        );
+        assert_eq!(
+            runtime
+                .approve(&hash, approval(0x42, &hash))
+                .expect("approve"),
+            1
+        );
+        // Submitting the intent again does not reset its approvals.
+        assert!(matches!(
+            runtime.sign_intent(&intent(), &quote()),
+            Err(WalletError::ApprovalRequired(again)) if again == pending
+        ));
+        let held = runtime.pending.get(&hash).expect("get").expect("pending");
+        assert_eq!(held.approvals.len(), 1);
+        assert!(matches!(
+            runtime.resume(&hash),
+            Err(WalletError::PolicyViolation(reason)) if reason.ends_with("has 1 of 2 required approvals")
+        ));
+
+        assert_eq!(
+            runtime
+                .approve(&hash, approval(0x43, &hash))
+                .expect("approve"),
+            2
+        );
+        let raw = runtime.resume(&hash).expect("signed");
+        assert_eq!(recover_sender(&raw).expect("sender"), sender);
+        assert!(runtime.pending.list().expect("pending").is_empty());
+        assert!(runtime.resume(&hash).is_err());
+
+        let names: Vec<_> = runtime
+            .audit_log
+            .events
+            .iter()
+            .map(|event| event.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "intent_held",
+                "approval_rejected",
+                "approval_rejected",
+                "approval_recorded",
+                "approval_recorded",
+                "approval_recorded",
+                "sign_evm_eip1559",
+            ]
+        );
+        let signed = runtime.audit_log.events.last().expect("event");
+        assert_eq!(
+            signed.metadata["approved_by"],
+            json!(["approver-42", "approver-43"])
+        );
+    }
+
+    #[test]
+    fn expired_intents_cannot_be_approved() {
+        let mut runtime = Runtime::new(treasury_policy(0), KeySigner::new(0x11));
+        let hash = intent_hash(&intent(), &quote()).expect("hash");
+        assert!(matches!(
+            runtime.sign_intent(&intent(), &quote()),
+            Err(WalletError::ApprovalRequired(_))
+        ));
+
+        assert!(matches!(
+            runtime.approve(&hash, approval(0x41, &hash)),
+            Err(WalletError::PolicyViolation(reason)) if reason.contains("expired")
+        ));
+        assert_eq!(
+            runtime.audit_log.events.last().expect("event").name,
+            "approval_expired"
+        );
+        assert!(runtime.pending.get(&hash).expect("get").is_none());
+    }
+
+    #[test]
+    fn delayed_intents_wait_after_approval() {
+        let policy = treasury_policy(3_600).with_delay(600);
+        let mut runtime = Runtime::new(policy, KeySigner::new(0x11));
+        let hash = intent_hash(&intent(), &quote()).expect("hash");
+        assert!(runtime.sign_intent(&intent(), &quote()).is_err());
+        let held = runtime.pending.get(&hash).expect("get").expect("pending");
+        assert_eq!(held.not_before, held.created_at + 600);
+
+        runtime
+            .approve(&hash, approval(0x41, &hash))
+            .expect("approve");
+        runtime
+            .approve(&hash, approval(0x42, &hash))
+            .expect("approve");
+        assert!(matches!(
+            runtime.resume(&hash),
+            Err(WalletError::PolicyViolation(reason)) if reason.contains("is delayed until")
+        ));
+        assert!(runtime.pending.get(&hash).expect("get").is_some());
+    }
+
+    #[test]
+    fn explain_reports_decision_without_signing() {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let velocity = VelocityPolicy::new(Arc::new(MemorySpendLedger::new())).with_limit(
+            VelocityLimit::Count {
+                window_secs: 60,
+                max_count: 0,
+            },
+        );
+        let runtime = Runtime::new(Rule::new("treasury", treasury_policy(3_600)), signer)
+            .with_expected_sender(sender)
+            .with_velocity(velocity);
+
+        let explanation = runtime.explain(&intent(), &quote()).expect("explain");
+        assert_eq!(
+            explanation.intent_hash,
+            hex_hash(&intent_hash(&intent(), &quote()).expect("hash"))
+        );
+        assert_eq!(explanation.decision.outcome(), Outcome::Hold);
+        assert_eq!(explanation.decision.rule.as_deref(), Some("treasury"));
+        assert_eq!(explanation.decision.trace[0].rule, "treasury");
+        let velocity = explanation.velocity.as_ref().expect("velocity");
+        assert_eq!(velocity.code, Some(ReasonCode::VelocityLimit));
+
+        let json = serde_json::to_value(&explanation).expect("json");
+        assert_eq!(json["decision"]["code"], json!("approval_required"));
+        assert!(runtime.audit_log.events.is_empty());
+        assert!(runtime.pending.list().expect("pending").is_empty());
+    }
+
+    fn node_runtime(server: &MockRpcServer) -> Runtime<SpendLimitPolicy, KeySigner> {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        Runtime::new(SpendLimitPolicy::new(10_000), signer)
+            .with_expected_sender(sender)
+            .with_rpc(HttpRpcClient::new(server.url()))
+    }
+
+    fn fast(confirmations: u64) -> SubmitOptions {
+        SubmitOptions::default()
+            .with_confirmations(confirmations)
+            .with_poll_interval(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn submit_intent_waits_for_confirmations() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+
+        let receipt = runtime
+            .submit_intent(&intent(), &quote(), &fast(3))
+            .expect("submitted");
+        assert!(receipt.status);
+        let node = server.node();
+        assert_eq!(node.sent.len(), 1);
+        assert_eq!(
+            receipt.transaction_hash,
+            transaction_hash(&node.sent[0]).expect("hash")
+        );
+        assert!(node.head >= receipt.block_number + 2);
+
+        let names: Vec<_> = runtime
+            .audit_log
+            .events
+            .iter()
+            .map(|event| event.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "sign_evm_eip1559",
+                "transaction_submitted",
+                "transaction_confirmed"
+            ]
+        );
+        let confirmed = runtime.audit_log.events.last().expect("event");
+        assert_eq!(confirmed.metadata["confirmations"], json!(3));
+    }
+
+    #[test]
+    fn submit_intent_checks_node_before_signing() {
+        let server = MockRpcServer::start(5);
+        let mut runtime = node_runtime(&server);
+        assert!(matches!(
+            runtime.submit_intent(&intent(), &quote(), &fast(1)),
+            Err(WalletError::RpcError(reason)) if reason == "node is on chain 5, intent targets 1"
+        ));
+
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+        let sender = runtime.expected_sender.expect("sender");
+        server.node().nonces.insert(sender, 1);
+        assert!(matches!(
+            runtime.submit_intent(&intent(), &quote(), &fast(1)),
+            Err(WalletError::InvalidInput(reason)) if reason.contains("nonce 0 already used")
+        ));
+        assert!(runtime.audit_log.events.is_empty());
+        assert!(server.node().sent.is_empty());
+
+        assert!(matches!(
+            Runtime::new(SpendLimitPolicy::new(10_000), KeySigner::new(0x11))
+                .submit_intent(&intent(), &quote(), &fast(1)),
+            Err(WalletError::RpcError(reason)) if reason == "no rpc client configured"
+        ));
+    }
+
+    #[test]
+    fn reserves_nonces_and_fills_gaps_with_self_transfers() {
+        let server = MockRpcServer::start(1);
+        let mut runtime =
+            node_runtime(&server).with_nonce_manager(NonceManager::new(MemoryNonceStore::new()));
+        let chain = CaipChainId::eip155(1);
+        let sender = runtime.expected_sender.expect("sender");
+
+        let first = runtime.reserve_nonce(&chain).expect("reserve");
+        let second = runtime.reserve_nonce(&chain).expect("reserve");
+        assert_eq!((first, second), (0, 1));
+        // The intent holding nonce 0 is abandoned after nonce 1 is sent.
+        runtime.release_nonce(&chain, first).expect("release");
+        let intent = Intent {
+            nonce: second,
+            ..intent()
+        };
+        let raw = runtime.sign_intent(&intent, &quote()).expect("signed");
+        runtime
+            .rpc()
+            .expect("rpc")
+            .send_raw_transaction(&raw)
+            .expect("sent");
+        // Nonce 1 waits in the node's queue, so the pending count is 0.
+        server.node().nonces.insert(sender, 0);
+
+        let gaps = runtime.nonce_gaps(&chain).expect("gaps");
+        assert_eq!(
+            gaps,
+            [NonceGap {
+                nonce: 0,
+                dropped: None
+            }]
+        );
+        server.node().sent.clear();
+        let filled = runtime.fill_nonce_gaps(&chain, &quote()).expect("filled");
+        assert_eq!(filled.len(), 1);
+
+        let node = server.node();
+        let filler = decode_eip1559(&node.sent[0]);
+        assert_eq!(
+            (filler.nonce, filler.to, filler.value),
+            (0, Some(sender), U256::zero())
+        );
+        let event = runtime.audit_log.events.last().expect("event");
+        assert_eq!(event.name, "nonce_gap_filled");
+
+        let unbound = Runtime::new(SpendLimitPolicy::new(10_000), KeySigner::new(0x11))
+            .with_nonce_manager(NonceManager::new(MemoryNonceStore::new()));
+        assert!(unbound.reserve_nonce(&chain).is_err());
+    }
+
+    #[test]
+    fn wait_for_receipt_times_out() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+        let options = fast(1).with_timeout(Duration::from_millis(20));
+        assert!(matches!(
+            runtime.wait_for_receipt(&[0xab; 32], &options),
+            Err(WalletError::RpcError(reason)) if reason.contains("not confirmed after")
+        ));
+    }
+
+    #[test]
+    fn replacement_quotes_bump_both_fees_by_ten_percent() {
+        let original = Quote {
+            max_priority_fee_per_gas: U256::from(100),
+            max_fee_per_gas: U256::from(101),
+            ..quote()
+        };
+        let minimum = original.replacement().expect("replacement");
+        assert_eq!(minimum.max_priority_fee_per_gas, U256::from(110));
+        // 111.1 rounds up.
+        assert_eq!(minimum.max_fee_per_gas, U256::from(112));
+        assert!(minimum.check_replaces(&original).is_ok());
+
+        let low = Quote {
+            max_fee_per_gas: U256::from(111),
+            ..minimum
+        };
+        assert_eq!(
+            low.check_replaces(&original).unwrap_err().to_string(),
+            "invalid input: replacement max fee per gas 111 is below the minimum 112"
+        );
+    }
+
+    fn decode_eip1559(raw: &[u8]) -> EvmUnsignedTx {
+        match EvmSignedTypedTx::decode(raw).expect("decode").tx {
+            EvmTypedTx::Eip1559(tx) => tx,
+            _ => panic!("expected an EIP-1559 transaction"),
+        }
+    }
+
+    #[test]
+    fn speed_up_and_cancel_replace_the_original_nonce() {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let mut runtime =
+            Runtime::new(SpendLimitPolicy::new(10_000), signer).with_expected_sender(sender);
+        let intent = Intent {
+            nonce: 7,
+            ..intent()
+        };
+        runtime.sign_intent(&intent, &quote()).expect("original");
+        let original = runtime.audit_log.events[0].metadata["intent_hash"].clone();
+        assert_eq!(
+            original,
+            json!(hex_hash(&intent_hash(&intent, &quote()).expect("hash")))
+        );
+
+        let faster = decode_eip1559(&runtime.speed_up(&intent, &quote(), None).expect("speed up"));
+        assert_eq!(faster.nonce, 7);
+        assert_eq!(faster.max_priority_fee_per_gas, U256::from(2));
+        assert_eq!(faster.max_fee_per_gas, U256::from(3));
+        assert_eq!(faster.value, U256::from(1_000));
+        let event = runtime.audit_log.events.last().expect("event");
+        assert_eq!(event.metadata["replaces"], original);
+        assert_eq!(event.metadata["replacement"], json!("speed_up"));
+
+        let cancelled = decode_eip1559(&runtime.cancel(&intent, &quote(), None).expect("cancel"));
+        assert_eq!(cancelled.nonce, 7);
+        assert_eq!(cancelled.to, Some(sender));
+        assert_eq!(cancelled.value, U256::zero());
+        assert!(cancelled.data.is_empty());
+        assert_eq!(cancelled.gas_limit, U256::from(21_000));
+        let event = runtime.audit_log.events.last().expect("event");
+        assert_eq!(event.metadata["replaces"], original);
+        assert_eq!(event.metadata["replacement"], json!("cancel"));
+
+        assert!(matches!(
+            runtime.speed_up(&intent, &quote(), Some(&quote())),
+            Err(WalletError::InvalidInput(reason)) if reason.contains("below the minimum")
+        ));
+        let mut unbound = Runtime::new(SpendLimitPolicy::new(10_000), KeySigner::new(0x11));
+        assert!(unbound.cancel(&intent, &quote(), None).is_err());
+    }
+
+    #[test]
+    fn process_intent_rebroadcasts_signed_intents_after_restart() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+        let id = IntentId::new("payout-1");
+        runtime
+            .create_intent(id.clone(), &intent())
+            .expect("create");
+        assert!(runtime
+            .create_intent(
+                id.clone(),
+                &Intent {
+                    nonce: 1,
+                    ..intent()
+                }
+            )
+            .is_err());
+
+        server.node().reject = Some("connection reset".to_string());
+        assert!(runtime
+            .process_intent(&id, Some(&quote()), &fast(1))
+            .is_err());
+        let signed = runtime.intent_record(&id).expect("record");
+        assert_eq!(signed.state, IntentState::Signed);
+        let raw = signed.raw.clone().expect("raw");
+
+        // A new process picks up the stored record and sends the same bytes.
+        server.node().reject = None;
+        let mut restarted = node_runtime(&server);
+        restarted.intents =
+            std::mem::replace(&mut runtime.intents, Box::new(MemoryIntentStore::new()));
+        assert_eq!(
+            restarted
+                .create_intent(id.clone(), &intent())
+                .expect("create")
+                .state,
+            IntentState::Signed
+        );
+        let recovered = restarted.recover_intents(&fast(2)).expect("recover");
+        assert_eq!(recovered.len(), 1);
+        let confirmed = &recovered[0];
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        assert_eq!(server.node().sent, [raw]);
+        assert_eq!(
+            confirmed
+                .receipt
+                .as_ref()
+                .map(|receipt| receipt.transaction_hash),
+            signed.tx_hash
+        );
+        let states: Vec<_> = confirmed
+            .history
+            .iter()
+            .map(|change| change.state)
+            .collect();
+        assert_eq!(
+            states,
+            [
+                IntentState::Created,
+                IntentState::Quoted,
+                IntentState::PolicyApproved,
+                IntentState::Signed,
+                IntentState::Submitted,
+                IntentState::Confirmed
+            ]
+        );
+        assert!(restarted
+            .audit_log
+            .events
+            .iter()
+            .all(|event| event.name != "sign_evm_eip1559"));
+        assert!(restarted.intents.active().expect("active").is_empty());
+    }
+
+    #[test]
+    fn process_intent_waits_for_approvals_and_records_denials() {
+        let server = MockRpcServer::start(1);
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let mut runtime = Runtime::new(treasury_policy(3_600), signer)
+            .with_expected_sender(sender)
+            .with_rpc(HttpRpcClient::new(server.url()));
+        let id = IntentId::new("treasury-1");
+        runtime
+            .create_intent(id.clone(), &intent())
+            .expect("create");
+
+        let held = runtime
+            .process_intent(&id, Some(&quote()), &fast(1))
+            .expect("held");
+        assert_eq!(held.state, IntentState::PendingApproval);
+        let hash = held.intent_hash.expect("hash");
+        runtime
+            .approve(&hash, approval(0x42, &hash))
+            .expect("approve");
+        let waiting = runtime
+            .process_intent(&id, None, &fast(1))
+            .expect("waiting");
+        assert_eq!(waiting.state, IntentState::PendingApproval);
+        assert!(waiting
+            .reason
+            .is_some_and(|reason| reason.ends_with("has 1 of 2 required approvals")));
+
+        runtime
+            .approve(&hash, approval(0x43, &hash))
+            .expect("approve");
+        let confirmed = runtime
+            .process_intent(&id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        assert_eq!(server.node().sent.len(), 1);
+
+        let mut strict = node_runtime(&server);
+        strict.policy = SpendLimitPolicy::new(500);
+        strict.create_intent(id.clone(), &intent()).expect("create");
+        let denied = strict
+            .process_intent(&id, Some(&quote()), &fast(1))
+            .expect("denied");
+        assert_eq!(denied.state, IntentState::PolicyDenied);
+        assert!(denied.reason.is_some());
+        assert_eq!(
+            strict
+                .process_intent(&id, None, &fast(1))
+                .expect("denied")
+                .state,
+            IntentState::PolicyDenied
+        );
+        assert_eq!(server.node().sent.len(), 1);
+    }
+
+    #[test]
+    fn process_intent_tolerates_rebroadcasts_and_resigning_after_a_crash() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server).with_velocity(
+            VelocityPolicy::new(Arc::new(MemorySpendLedger::new())).with_limit(
+                VelocityLimit::Count {
+                    window_secs: 86_400,
+                    max_count: 1,
+                },
+            ),
+        );
+        let id = IntentId::new("payout-3");
+        runtime
+            .create_intent(id.clone(), &intent())
+            .expect("create");
+        let mut record = runtime.intent_record(&id).expect("record");
+        record.transition(IntentState::Quoted, 1).expect("quoted");
+        record.quote = Some(quote());
+        runtime.intents.put(&record).expect("put");
+
+        // Signed, but the process died before storing the signed record.
+        runtime.sign_intent(&intent(), &quote()).expect("signed");
+        let confirmed = runtime
+            .process_intent(&id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        let account = runtime
+            .expected_sender
+            .expect("sender")
+            .to_caip10(&intent().chain_id);
+        let velocity = runtime.velocity.as_ref().expect("velocity");
+        assert_eq!(velocity.spends(&account, 0).expect("spends").len(), 1);
+
+        // Broadcast, but the process died before storing the submission.
+        let mut record = confirmed;
+        record.state = IntentState::Signed;
+        record.history.truncate(4);
+        runtime.intents.put(&record).expect("put");
+        server.node().reject = Some("AlreadyKnown".to_string());
+        let confirmed = runtime
+            .process_intent(&id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        assert_eq!(server.node().sent.len(), 1);
+    }
+
+    #[test]
+    fn process_intent_holds_again_when_pending_approval_is_lost() {
+        let server = MockRpcServer::start(1);
+        let treasury = || {
+            let signer = KeySigner::new(0x11);
+            let sender = signer.address();
+            Runtime::new(treasury_policy(3_600), signer)
+                .with_expected_sender(sender)
+                .with_rpc(HttpRpcClient::new(server.url()))
+        };
+        let mut runtime = treasury();
+        let id = IntentId::new("treasury-2");
+        runtime
+            .create_intent(id.clone(), &intent())
+            .expect("create");
+        let held = runtime
+            .process_intent(&id, Some(&quote()), &fast(1))
+            .expect("held");
+        let hash = held.intent_hash.expect("hash");
+
+        // Restarted with the intent records but without the pending store.
+        let mut restarted = treasury();
+        restarted.intents =
+            std::mem::replace(&mut runtime.intents, Box::new(MemoryIntentStore::new()));
+        let held = restarted.process_intent(&id, None, &fast(1)).expect("held");
+        assert_eq!(held.state, IntentState::PendingApproval);
+        assert!(held
+            .reason
+            .is_some_and(|reason| reason.contains("held again")));
+        assert!(restarted.pending.get(&hash).expect("pending").is_some());
+
+        restarted
+            .approve(&hash, approval(0x42, &hash))
+            .expect("approve");
+        restarted
+            .approve(&hash, approval(0x43, &hash))
+            .expect("approve");
+        let confirmed = restarted
+            .process_intent(&id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn pending_approvals_survive_restarts_in_sqlite() {
+        let server = MockRpcServer::start(1);
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("wallet.db");
+        let treasury = || {
+            let signer = KeySigner::new(0x11);
+            let sender = signer.address();
+            Runtime::new(treasury_policy(3_600), signer)
+                .with_expected_sender(sender)
+                .with_rpc(HttpRpcClient::new(server.url()))
+                .with_intent_store(SqliteIntentStore::open(&path).expect("intents"))
+                .with_pending_store(SqlitePendingStore::open(&path).expect("pending"))
+        };
+        let id = IntentId::new("treasury-3");
+        let hash = {
+            let mut runtime = treasury();
+            runtime
+                .create_intent(id.clone(), &intent())
+                .expect("create");
+            let held = runtime
+                .process_intent(&id, Some(&quote()), &fast(1))
+                .expect("held");
+            let hash = held.intent_hash.expect("hash");
+            runtime
+                .approve(&hash, approval(0x42, &hash))
+                .expect("approve");
+            hash
+        };
+
+        let mut restarted = treasury();
+        assert_eq!(
+            restarted
+                .approve(&hash, approval(0x43, &hash))
+                .expect("approve"),
+            2
+        );
+        let confirmed = restarted
+            .process_intent(&id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        assert!(restarted.pending.list().expect("pending").is_empty());
+    }
+
+    #[test]
+    fn replacing_an_intent_links_both_records() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+        let id = IntentId::new("payout-2");
+        runtime
+            .create_intent(id.clone(), &intent())
+            .expect("create");
+        assert!(runtime.speed_up_intent(&id, None).is_err());
+
+        server.node().stall = true;
+        let unconfirmed = fast(1).with_timeout(Duration::ZERO);
+        assert!(runtime
+            .process_intent(&id, Some(&quote()), &unconfirmed)
+            .is_err());
+        server.node().stall = false;
+        assert_eq!(
+            runtime.intent_record(&id).expect("record").state,
+            IntentState::Submitted
+        );
+
+        let replacement = runtime.speed_up_intent(&id, None).expect("speed up");
+        assert_eq!(replacement.state, IntentState::Signed);
+        assert_eq!(replacement.replacement_of, Some(id.clone()));
+        assert_eq!(
+            decode_eip1559(replacement.raw.as_deref().expect("raw")).max_fee_per_gas,
+            U256::from(3)
+        );
+        // The original may still be mined, so it stays active.
+        let original = runtime.intent_record(&id).expect("record");
+        assert_eq!(original.state, IntentState::Submitted);
+        assert_eq!(original.replaced_by, Some(replacement.id.clone()));
+        assert!(runtime.cancel_intent(&id, None).is_err());
+
+        let confirmed = runtime
+            .process_intent(&replacement.id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        assert_eq!(server.node().sent.len(), 2);
+        let original = runtime.intent_record(&id).expect("record");
+        assert_eq!(original.state, IntentState::Replaced);
+        assert!(original.receipt.is_none());
+    }
+
+    #[test]
+    fn a_mined_original_settles_its_replacement() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+        let id = IntentId::new("payout-3");
+        runtime
+            .create_intent(id.clone(), &intent())
+            .expect("create");
+        let unconfirmed = fast(100).with_timeout(Duration::ZERO);
+        assert!(runtime
+            .process_intent(&id, Some(&quote()), &unconfirmed)
+            .is_err());
+        let replacement = runtime.speed_up_intent(&id, None).expect("speed up");
+
+        // The node rejects the speed-up because the original was mined.
+        let replaced = runtime
+            .process_intent(&replacement.id, None, &fast(1))
+            .expect("replaced");
+        assert_eq!(replaced.state, IntentState::Replaced);
+        assert_eq!(server.node().sent.len(), 1);
+        assert_eq!(
+            runtime.intent_record(&id).expect("record").state,
+            IntentState::Submitted
+        );
+
+        let confirmed = runtime
+            .process_intent(&id, None, &fast(1))
+            .expect("confirmed");
+        assert_eq!(confirmed.state, IntentState::Confirmed);
+        assert_eq!(
+            confirmed
+                .receipt
+                .as_ref()
+                .map(|receipt| receipt.transaction_hash),
+            confirmed.tx_hash
+        );
+    }
+
+    #[test]
+    fn recovery_continues_past_failing_intents() {
+        let server = MockRpcServer::start(1);
+        let mut runtime = node_runtime(&server);
+        let stuck = IntentId::new("payout-4");
+        let retried = IntentId::new("payout-5");
+        runtime
+            .create_intent(stuck.clone(), &intent())
+            .expect("create");
+        runtime
+            .create_intent(
+                retried.clone(),
+                &Intent {
+                    nonce: 1,
+                    ..intent()
+                },
+            )
+            .expect("create");
+
+        server.node().stall = true;
+        let once = fast(1).with_timeout(Duration::ZERO);
+        assert!(runtime
+            .process_intent(&stuck, Some(&quote()), &once)
+            .is_err());
+        server.node().reject = Some("connection reset".to_string());
+        assert!(runtime
+            .process_intent(&retried, Some(&quote()), &once)
+            .is_err());
+        server.node().reject = None;
+        server.node().stall = false;
+
+        let recovered = runtime.recover_intents(&once).expect("recover");
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].id, retried);
+        assert_eq!(recovered[0].state, IntentState::Confirmed);
+        assert_eq!(
+            runtime.intent_record(&stuck).expect("record").state,
+            IntentState::Submitted
+        );
+        let failed = runtime.audit_log.events.last().expect("audit event");
+        assert_eq!(failed.name, "intent_recovery_failed");
+        assert_eq!(failed.metadata["intent_id"], json!(stuck));
+    }
+
+    #[test]
+    fn replacements_do_not_count_twice_against_velocity_limits() {
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let ledger = Arc::new(MemorySpendLedger::new());
+        let velocity = VelocityPolicy::new(ledger.clone())
+            .with_limit(VelocityLimit::Value {
+                window_secs: 86_400,
+                max_value: U256::from(1_500),
+            })
+            .with_limit(VelocityLimit::Count {
+                window_secs: 86_400,
+                max_count: 2,
+            });
+        let mut runtime = Runtime::new(SpendLimitPolicy::new(10_000), signer)
+            .with_expected_sender(sender)
+            .with_velocity(velocity.clone());
+
+        runtime.sign_intent(&intent(), &quote()).expect("original");
+        runtime
+            .speed_up(&intent(), &quote(), None)
+            .expect("speed up near the cap");
+        let faster = quote().replacement().expect("replacement");
+        runtime
+            .cancel(&intent(), &faster, None)
+            .expect("cancel near the cap");
+
+        // The cancellation may lose the race, so the original still counts.
+        let account = sender.to_caip10(&CaipChainId::eip155(1));
+        assert_eq!(ledger.records(&account, 0).expect("records").len(), 3);
+        let spends = velocity.spends(&account, 0).expect("spends");
+        assert_eq!(spends.len(), 1);
+        assert_eq!(spends[0].value, U256::from(1_000));
+
+        let next = |value: u64| Intent {
+            nonce: 1,
+            action: IntentAction::Call {
+                to: Address([0x22; 20]),
+                value: U256::from(value),
+                data: Vec::new(),
+            },
+            ..intent()
+        };
+        assert!(matches!(
+            runtime.sign_intent(&next(600), &quote()),
+            Err(WalletError::PolicyViolation(reason)) if reason.contains("velocity limit")
+        ));
+        runtime
+            .sign_intent(&next(500), &quote())
+            .expect("fits beside the counted original");
+    }
+
+    #[test]
+    fn replacements_are_checked_against_fee_caps() {
+        let caps = FeeCapPolicy {
+            max_fee_per_gas: Some(U256::from(2)),
+            ..FeeCapPolicy::default()
+        };
+        let mut runtime = Runtime::new(caps, KeySigner::new(0x11));
+        runtime.sign_intent(&intent(), &quote()).expect("original");
+
+        assert!(matches!(
+            runtime.speed_up(&intent(), &quote(), None),
+            Err(WalletError::PolicyViolation(reason)) if reason.contains("exceeds cap 2")
+        ));
+        assert_eq!(
+            runtime.audit_log.events.last().expect("event").name,
+            "policy_denied"
+        );
+    }
+
+    /// Denies token calls to a blocked recipient.
+    struct BlockedRecipientPolicy(Address);
+
+    impl PolicyEngine for BlockedRecipientPolicy {
+        fn evaluate_evm(&self, tx: &EvmUnsignedTx) -> Result<PolicyDecision> {
+            let blocked = tx
+                .token_call()?
+                .is_some_and(|call| call.recipient() == self.0);
+            Ok(if blocked {
+                PolicyDecision::deny(format!("recipient {} is blocked", self.0))
+            } else {
+                PolicyDecision::allow()
+            })
+        }
+    }
+
+    const USDC: &str = "eip155:1/erc20:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+    #[test]
+    fn lowers_token_intents_into_calldata() {
+        let intent: Intent = serde_json::from_value(json!({
+            "chain_id": "eip155:1",
+            "nonce": 3,
+            "type": "erc20_transfer",
+            "asset": USDC,
+            "to": "0x2222222222222222222222222222222222222222",
+            "amount": "12.5",
+            "decimals": 6,
+        }))
+        .expect("intent");
+        let (to, value, data) = intent.call().expect("call");
+        assert_eq!(to.to_string(), "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        assert!(value.is_zero());
+        assert_eq!(
+            TokenCall::decode(&data).expect("decode"),
+            Some(TokenCall::Transfer {
+                to: Address([0x22; 20]),
+                amount: U256::from(12_500_000u64),
+            })
+        );
+
+        let nft = Intent {
+            chain_id: CaipChainId::eip155(1),
+            nonce: 0,
+            action: IntentAction::Erc721Transfer {
+                asset: "eip155:1/erc721:0x06012c8cf97BEaD5deAe237070F9587f8E7A266d/771769"
+                    .parse()
+                    .expect("asset"),
+                from: Address([0x11; 20]),
+                to: Address([0x22; 20]),
+            },
+        };
+        let (_, _, data) = nft.call().expect("call");
+        assert!(matches!(
+            TokenCall::decode(&data).expect("decode"),
+            Some(TokenCall::SafeTransferFrom { token_id, .. }) if token_id == U256::from(771_769u64)
+        ));
+
+        let wrong_chain = Intent {
+            chain_id: CaipChainId::eip155(10),
+            ..intent.clone()
+        };
+        assert!(wrong_chain.call().is_err());
+        let too_precise = Intent {
+            action: IntentAction::Erc20Transfer {
+                asset: USDC.parse().expect("asset"),
+                to: Address([0x22; 20]),
+                amount: "0.0000001".to_string(),
+                decimals: 6,
+            },
+            ..intent
+        };
+        assert!(too_precise.call().is_err());
+    }
+
+    #[test]
+    fn token_intents_expose_recipient_to_policy_and_audit() {
+        let transfer = |to: Address| Intent {
+            chain_id: CaipChainId::eip155(1),
+            nonce: 0,
+            action: IntentAction::Erc20Transfer {
+                asset: USDC.parse().expect("asset"),
+                to,
+                amount: "1.50".to_string(),
+                decimals: 6,
+            },
+        };
+        let mut runtime = Runtime::new(
+            BlockedRecipientPolicy(Address([0x66; 20])),
+            KeySigner::new(0x11),
+        );
+
+        runtime
+            .sign_intent(&transfer(Address([0x22; 20])), &quote())
+            .expect("signed");
+        let metadata = &runtime.audit_log.events[0].metadata;
+        assert_eq!(metadata["action"], json!("erc20_transfer"));
+        assert_eq!(metadata["asset"], json!(USDC));
+        assert_eq!(metadata["to"], json!(Address([0x22; 20])));
+        assert_eq!(metadata["amount"], json!("1.5"));
+        assert_eq!(metadata["value"], json!("0x0"));
+
+        assert!(matches!(
+            runtime.sign_intent(&transfer(Address([0x66; 20])), &quote()),
+            Err(WalletError::PolicyViolation(reason)) if reason.contains("is blocked")
+        ));
+        let denied = runtime.audit_log.events.last().expect("event");
+        assert_eq!(denied.name, "policy_denied");
+        assert_eq!(denied.metadata["to"], json!(Address([0x66; 20])));
+        assert_eq!(
+            denied.metadata["decision"]["reason"],
+            json!(format!("recipient {} is blocked", Address([0x66; 20])))
+        );
+    }
+
+    #[test]
+    fn delegations_are_checked_alone_and_inside_type_4_transactions() {
+        let banned = Address([0x66; 20]);
+        let mut allowlist = AllowListPolicy::new();
+        allowlist.deny_everywhere(banned);
+        let signer = KeySigner::new(0x11);
+        let sender = signer.address();
+        let mut runtime = Runtime::new(allowlist, signer).with_expected_sender(sender);
+        let delegation = |address| Authorization {
+            chain_id: 1,
+            address,
+            nonce: 1,
+        };
+
+        let good = runtime
+            .sign_authorization("", &delegation(Address([0x55; 20])))
+            .expect("allowed delegate");
+        let err = runtime
+            .sign_authorization("", &delegation(banned))
+            .expect_err("denied delegate");
+        assert!(matches!(err, WalletError::PolicyViolation(_)));
+        let denied = runtime.audit_log.events.last().expect("denial");
+        assert_eq!(denied.name, "policy_denied");
+        assert_eq!(denied.metadata["delegate"], json!(banned));
+        assert_eq!(denied.metadata["decision"]["code"], json!("denylisted"));
+
+        // A delegation signed elsewhere cannot ride along in a type-4 list.
+        let bad = KeySigner::new(0x12)
+            .sign_evm_authorization("", &delegation(banned))
+            .expect("signed elsewhere");
+        let tx = EvmEip7702TxBuilder::new(1, 0, sender)
+            .max_fee_per_gas(2)
+            .gas_limit(60_000)
+            .authorization(good.clone())
+            .authorization(bad)
+            .build();
+        let events = runtime.audit_log.events.len();
+        assert!(runtime.sign_eip7702("", &tx).is_err());
+        assert_eq!(runtime.audit_log.events.len(), events + 1);
+        assert_eq!(
+            runtime.audit_log.events.last().expect("denial").name,
+            "policy_denied"
+        );
+
+        let tx = EvmEip7702TxBuilder::new(1, 0, sender)
+            .max_fee_per_gas(2)
+            .gas_limit(60_000)
+            .authorization(good)
+            .build();
+        let raw = runtime.sign_eip7702("", &tx).expect("signed");
+        assert_eq!(recover_sender(&raw).expect("sender"), sender);
+        let event = runtime.audit_log.events.last().expect("audit event");
+        assert_eq!(event.name, "sign_evm_eip7702");
+        assert_eq!(event.metadata["delegates"], json!([Address([0x55; 20])]));
+
+        // The call itself is still checked against the allowlist.
+        let tx = EvmEip7702TxBuilder::new(1, 1, banned).build();
+        assert!(runtime.sign_eip7702("", &tx).is_err());
+    }
 }
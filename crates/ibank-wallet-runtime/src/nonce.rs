@@ -0,0 +1,434 @@
+//! Nonce reservation per (chain, account).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ibank_wallet_chains::Address;
+use ibank_wallet_core::{CaipAccountId, Result, WalletError};
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::{hash, BlockTag, RpcClient};
+
+/// State of a nonce handed out by a [`NonceManager`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum NonceStatus {
+    /// Reserved for an intent that is not signed yet.
+    Reserved,
+    /// A transaction with this nonce was signed.
+    Signed {
+        /// Hash of the signed transaction.
+        #[serde(with = "hash")]
+        tx_hash: [u8; 32],
+    },
+    /// Given back unused; handed out again before new nonces.
+    Released,
+}
+
+/// Nonces of one account on one chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountNonces {
+    /// Next new nonce to hand out.
+    pub next: u64,
+    /// Nonces handed out that are not mined yet.
+    pub reservations: BTreeMap<u64, NonceStatus>,
+}
+
+impl AccountNonces {
+    /// Forgets nonces below `mined`, the account's mined transaction count.
+    fn prune(&mut self, mined: u64) {
+        self.reservations = self.reservations.split_off(&mined);
+        self.next = self.next.max(mined);
+    }
+}
+
+/// Nonce whose transaction the node does not have.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NonceGap {
+    /// Missing nonce.
+    pub nonce: u64,
+    /// Hash of the signed transaction the node dropped, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dropped: Option<String>,
+}
+
+/// Storage for nonce reservations.
+pub trait NonceStore: fmt::Debug + Send + Sync {
+    /// Applies `update` to the account's nonces and saves the result as one
+    /// atomic step.
+    fn update(
+        &self,
+        account: &CaipAccountId,
+        update: &mut dyn FnMut(&mut AccountNonces),
+    ) -> Result<AccountNonces>;
+}
+
+/// In-memory [`NonceStore`].
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    accounts: Mutex<BTreeMap<CaipAccountId, AccountNonces>>,
+}
+
+impl MemoryNonceStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn update(
+        &self,
+        account: &CaipAccountId,
+        update: &mut dyn FnMut(&mut AccountNonces),
+    ) -> Result<AccountNonces> {
+        let mut accounts = self
+            .accounts
+            .lock()
+            .map_err(|_| WalletError::InvalidInput("nonce store lock poisoned".to_string()))?;
+        let nonces = accounts.entry(account.clone()).or_default();
+        update(nonces);
+        Ok(nonces.clone())
+    }
+}
+
+/// [`NonceStore`] kept in a JSON file, shared safely between processes
+/// through an exclusive lock on `<path>.lock`.
+#[derive(Debug)]
+pub struct FileNonceStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+}
+
+impl FileNonceStore {
+    /// Opens the store at `path`; the file is created on first write.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut lock_path = path.clone().into_os_string();
+        lock_path.push(".lock");
+        let store = Self {
+            path,
+            lock_path: lock_path.into(),
+        };
+        store.with_lock(|| store.read_all().map(drop))?;
+        Ok(store)
+    }
+
+    fn with_lock<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)
+            .map_err(|err| self.io_error(err))?;
+        lock.lock().map_err(|err| self.io_error(err))?;
+        let result = f();
+        lock.unlock().map_err(|err| self.io_error(err))?;
+        result
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<CaipAccountId, AccountNonces>> {
+        let mut contents = String::new();
+        match File::open(&self.path) {
+            Ok(mut file) => file
+                .read_to_string(&mut contents)
+                .map_err(|err| self.io_error(err))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(self.io_error(err)),
+        };
+        serde_json::from_str(&contents).map_err(|err| {
+            WalletError::InvalidInput(format!("nonce store {}: {err}", self.path.display()))
+        })
+    }
+
+    fn write_all(&self, accounts: &BTreeMap<CaipAccountId, AccountNonces>) -> Result<()> {
+        let contents = serde_json::to_string_pretty(accounts)
+            .map_err(|err| WalletError::InvalidInput(err.to_string()))?;
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let mut file = File::create(&temp).map_err(|err| self.io_error(err))?;
+        file.write_all(contents.as_bytes())
+            .and_then(|()| file.sync_all())
+            .map_err(|err| self.io_error(err))?;
+        fs::rename(&temp, &self.path).map_err(|err| self.io_error(err))
+    }
+
+    fn io_error(&self, err: std::io::Error) -> WalletError {
+        WalletError::InvalidInput(format!("nonce store {}: {err}", self.path.display()))
+    }
+}
+
+impl NonceStore for FileNonceStore {
+    fn update(
+        &self,
+        account: &CaipAccountId,
+        update: &mut dyn FnMut(&mut AccountNonces),
+    ) -> Result<AccountNonces> {
+        self.with_lock(|| {
+            let mut accounts = self.read_all()?;
+            let nonces = accounts.entry(account.clone()).or_default();
+            let before = nonces.clone();
+            update(nonces);
+            let nonces = nonces.clone();
+            if nonces != before {
+                self.write_all(&accounts)?;
+            }
+            Ok(nonces)
+        })
+    }
+}
+
+/// Hands out nonces so concurrent intents from one account do not collide.
+///
+/// Clones share the same store.
+#[derive(Clone, Debug)]
+pub struct NonceManager {
+    store: Arc<dyn NonceStore>,
+}
+
+impl NonceManager {
+    /// Creates a manager backed by `store`.
+    pub fn new(store: impl NonceStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    /// Returns the store.
+    pub fn store(&self) -> &dyn NonceStore {
+        self.store.as_ref()
+    }
+
+    /// Reserves a nonce for `account`: the lowest released one, otherwise the
+    /// next after both local reservations and the node's pending count.
+    /// Released nonces the node already has pending transactions for are
+    /// dropped rather than handed out again.
+    pub fn reserve(&self, rpc: &dyn RpcClient, account: &CaipAccountId) -> Result<u64> {
+        let (mined, pending) = counts(rpc, account)?;
+        let mut nonce = 0;
+        self.store.update(account, &mut |nonces| {
+            nonces.prune(mined);
+            nonces
+                .reservations
+                .retain(|nonce, status| *status != NonceStatus::Released || *nonce >= pending);
+            let released = nonces
+                .reservations
+                .iter()
+                .find(|(_, status)| **status == NonceStatus::Released)
+                .map(|(nonce, _)| *nonce);
+            nonce = released.unwrap_or_else(|| {
+                let next = nonces.next.max(pending);
+                nonces.next = next + 1;
+                next
+            });
+            nonces.reservations.insert(nonce, NonceStatus::Reserved);
+        })?;
+        Ok(nonce)
+    }
+
+    /// Records that a transaction with `nonce` was signed.
+    pub fn mark_signed(
+        &self,
+        account: &CaipAccountId,
+        nonce: u64,
+        tx_hash: [u8; 32],
+    ) -> Result<()> {
+        self.store.update(account, &mut |nonces| {
+            nonces
+                .reservations
+                .insert(nonce, NonceStatus::Signed { tx_hash });
+            nonces.next = nonces.next.max(nonce + 1);
+        })?;
+        Ok(())
+    }
+
+    /// Gives back an unsigned reservation. Released nonces at the top are
+    /// forgotten so they are not left as gaps.
+    pub fn release(&self, account: &CaipAccountId, nonce: u64) -> Result<()> {
+        self.store.update(account, &mut |nonces| {
+            if nonces.reservations.get(&nonce) != Some(&NonceStatus::Reserved) {
+                return;
+            }
+            nonces.reservations.insert(nonce, NonceStatus::Released);
+            while let Some(entry) = nonces.reservations.last_entry() {
+                if *entry.get() != NonceStatus::Released || *entry.key() + 1 != nonces.next {
+                    break;
+                }
+                nonces.next = *entry.key();
+                entry.remove();
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Returns released nonces at or above the node's pending count, and the
+    /// signed nonce at it, which the node dropped. Later transactions wait
+    /// until every gap is filled.
+    pub fn gaps(&self, rpc: &dyn RpcClient, account: &CaipAccountId) -> Result<Vec<NonceGap>> {
+        let (mined, pending) = counts(rpc, account)?;
+        let nonces = self
+            .store
+            .update(account, &mut |nonces| nonces.prune(mined))?;
+        Ok(nonces
+            .reservations
+            .range(pending..)
+            .filter_map(|(nonce, status)| match status {
+                NonceStatus::Reserved => None,
+                // Signed transactions above the first gap may be queued.
+                NonceStatus::Signed { .. } if *nonce != pending => None,
+                NonceStatus::Released => Some(NonceGap {
+                    nonce: *nonce,
+                    dropped: None,
+                }),
+                NonceStatus::Signed { tx_hash } => Some(NonceGap {
+                    nonce: *nonce,
+                    dropped: Some(format!("0x{}", hex::encode(tx_hash))),
+                }),
+            })
+            .collect())
+    }
+}
+
+/// Returns the account's mined and pending transaction counts.
+fn counts(rpc: &dyn RpcClient, account: &CaipAccountId) -> Result<(u64, u64)> {
+    let address = Address::from_caip10(account)?;
+    let mined = rpc.transaction_count(&address, BlockTag::Latest)?;
+    let pending = rpc.transaction_count(&address, BlockTag::Pending)?;
+    Ok((mined, pending.max(mined)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::mock::MockRpcServer;
+    use crate::HttpRpcClient;
+    use ibank_wallet_core::CaipChainId;
+    use std::thread;
+
+    fn account() -> CaipAccountId {
+        Address([0x11; 20]).to_caip10(&CaipChainId::eip155(1))
+    }
+
+    #[test]
+    fn reserves_unique_nonces_across_threads() {
+        let server = MockRpcServer::start(1);
+        server.node().nonces.insert(Address([0x11; 20]), 5);
+        let manager = NonceManager::new(MemoryNonceStore::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (manager, url) = (manager.clone(), server.url());
+                thread::spawn(move || {
+                    manager
+                        .reserve(&HttpRpcClient::new(url), &account())
+                        .expect("reserve")
+                })
+            })
+            .collect();
+        let mut nonces: Vec<u64> = handles
+            .into_iter()
+            .map(|handle| handle.join().expect("thread"))
+            .collect();
+        nonces.sort();
+        assert_eq!(nonces, (5..13).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn reuses_released_nonces_and_reports_gaps() {
+        let server = MockRpcServer::start(1);
+        let rpc = HttpRpcClient::new(server.url());
+        let manager = NonceManager::new(MemoryNonceStore::new());
+        let account = account();
+
+        let reserved: Vec<_> = (0..4)
+            .map(|_| manager.reserve(&rpc, &account).expect("reserve"))
+            .collect();
+        assert_eq!(reserved, [0, 1, 2, 3]);
+        manager.release(&account, 1).expect("release");
+        manager.release(&account, 3).expect("release");
+        assert_eq!(manager.reserve(&rpc, &account).expect("reserve"), 1);
+        assert_eq!(manager.reserve(&rpc, &account).expect("reserve"), 3);
+
+        manager
+            .mark_signed(&account, 0, [0xaa; 32])
+            .expect("signed");
+        manager
+            .mark_signed(&account, 1, [0xbb; 32])
+            .expect("signed");
+        manager.release(&account, 2).expect("release");
+        // The node mined nonce 0 and never saw nonce 1.
+        server.node().nonces.insert(Address([0x11; 20]), 1);
+        assert_eq!(
+            manager.gaps(&rpc, &account).expect("gaps"),
+            [
+                NonceGap {
+                    nonce: 1,
+                    dropped: Some(format!("0x{}", "bb".repeat(32))),
+                },
+                NonceGap {
+                    nonce: 2,
+                    dropped: None,
+                },
+            ]
+        );
+        let nonces = manager.store().update(&account, &mut |_| {}).expect("read");
+        assert_eq!(
+            nonces.reservations.keys().copied().collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn skips_released_nonces_the_node_already_has() {
+        let server = MockRpcServer::start(1);
+        let rpc = HttpRpcClient::new(server.url());
+        let manager = NonceManager::new(MemoryNonceStore::new());
+        let account = account();
+
+        for _ in 0..3 {
+            manager.reserve(&rpc, &account).expect("reserve");
+        }
+        manager.release(&account, 0).expect("release");
+        manager.release(&account, 1).expect("release");
+        // Another wallet sent nonces 0 and 1; they are pending, not mined.
+        server.node().pending.insert(Address([0x11; 20]), 2);
+        assert_eq!(manager.reserve(&rpc, &account).expect("reserve"), 3);
+        let nonces = manager.store().update(&account, &mut |_| {}).expect("read");
+        assert_eq!(
+            nonces.reservations.keys().copied().collect::<Vec<_>>(),
+            [2, 3]
+        );
+    }
+
+    #[test]
+    fn file_store_persists_reservations() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("nonces.json");
+        let server = MockRpcServer::start(1);
+        let rpc = HttpRpcClient::new(server.url());
+
+        let first = NonceManager::new(FileNonceStore::open(&path).expect("open"));
+        let second = NonceManager::new(FileNonceStore::open(&path).expect("open"));
+        assert_eq!(first.reserve(&rpc, &account()).expect("reserve"), 0);
+        assert_eq!(second.reserve(&rpc, &account()).expect("reserve"), 1);
+        first
+            .mark_signed(&account(), 0, [0xaa; 32])
+            .expect("signed");
+
+        let reopened = FileNonceStore::open(&path).expect("reopen");
+        let nonces = reopened.update(&account(), &mut |_| {}).expect("read");
+        assert_eq!(nonces.next, 2);
+        assert_eq!(
+            nonces.reservations[&0],
+            NonceStatus::Signed {
+                tx_hash: [0xaa; 32]
+            }
+        );
+        assert_eq!(nonces.reservations[&1], NonceStatus::Reserved);
+    }
+}
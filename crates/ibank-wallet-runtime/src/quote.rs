@@ -0,0 +1,474 @@
+//! Fee and gas quoting from node fee history.
+
+use ibank_wallet_chains::{quantity, AccessList, AccessListItem, Address, U256};
+use ibank_wallet_core::{Result, WalletError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::rpc::{hash, JsonRpc};
+use crate::{Intent, Quote};
+
+/// Node methods used to quote fees and gas.
+pub trait FeeRpc {
+    /// `eth_feeHistory` over the last `block_count` blocks.
+    fn fee_history(&self, block_count: u64, percentiles: &[f64]) -> Result<FeeHistory>;
+    /// `eth_maxPriorityFeePerGas`.
+    fn max_priority_fee_per_gas(&self) -> Result<U256>;
+    /// `eth_estimateGas`.
+    fn estimate_gas(&self, call: &CallRequest) -> Result<U256>;
+    /// `eth_createAccessList`; returns the list and the gas used with it.
+    fn create_access_list(&self, call: &CallRequest) -> Result<(AccessList, U256)>;
+}
+
+/// Result of `eth_feeHistory`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// Base fee of each block, plus the next block's.
+    #[serde(with = "quantities")]
+    pub base_fee_per_gas: Vec<U256>,
+    /// Gas used over gas limit for each block.
+    pub gas_used_ratio: Vec<f64>,
+    /// Priority fee at each requested percentile, per block.
+    #[serde(default)]
+    pub reward: Vec<Vec<Reward>>,
+}
+
+/// Priority fee paid at a percentile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reward(#[serde(with = "ibank_wallet_chains::quantity")] pub U256);
+
+/// Call to estimate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallRequest {
+    /// Sender, if known.
+    pub from: Option<Address>,
+    /// Target.
+    pub to: Address,
+    /// Native value in wei.
+    pub value: U256,
+    /// Calldata.
+    pub data: Vec<u8>,
+    /// Access list to estimate with.
+    pub access_list: AccessList,
+}
+
+impl CallRequest {
+    fn to_json(&self) -> Value {
+        let mut call = json!({
+            "to": self.to,
+            "value": quantity::to_hex(&self.value),
+            "data": format!("0x{}", hex::encode(&self.data)),
+        });
+        if let Some(from) = self.from {
+            call["from"] = json!(from);
+        }
+        if !self.access_list.0.is_empty() {
+            call["accessList"] = self
+                .access_list
+                .0
+                .iter()
+                .map(|item| RpcAccessListItem {
+                    address: item.address,
+                    storage_keys: item.storage_keys.iter().copied().map(Key).collect(),
+                })
+                .map(|item| json!(item))
+                .collect();
+        }
+        call
+    }
+}
+
+impl<T: JsonRpc> FeeRpc for T {
+    fn fee_history(&self, block_count: u64, percentiles: &[f64]) -> Result<FeeHistory> {
+        self.call(
+            "eth_feeHistory",
+            json!([format!("{block_count:#x}"), "latest", percentiles]),
+        )
+    }
+
+    fn max_priority_fee_per_gas(&self) -> Result<U256> {
+        let fee: String = self.call("eth_maxPriorityFeePerGas", json!([]))?;
+        quantity::from_hex(&fee)
+    }
+
+    fn estimate_gas(&self, call: &CallRequest) -> Result<U256> {
+        let gas: String = self.call("eth_estimateGas", json!([call.to_json()]))?;
+        quantity::from_hex(&gas)
+    }
+
+    fn create_access_list(&self, call: &CallRequest) -> Result<(AccessList, U256)> {
+        let result: RpcAccessList =
+            self.call("eth_createAccessList", json!([call.to_json(), "latest"]))?;
+        let list = result
+            .access_list
+            .into_iter()
+            .map(|item| AccessListItem {
+                address: item.address,
+                storage_keys: item.storage_keys.into_iter().map(|key| key.0).collect(),
+            })
+            .collect();
+        Ok((AccessList(list), result.gas_used))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcAccessList {
+    access_list: Vec<RpcAccessListItem>,
+    #[serde(with = "ibank_wallet_chains::quantity")]
+    gas_used: U256,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcAccessListItem {
+    address: Address,
+    storage_keys: Vec<Key>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Key(#[serde(with = "hash")] [u8; 32]);
+
+/// How aggressively to price the priority fee.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeStrategy {
+    /// Prices at [`QuoteConfig::slow_percentile`].
+    Slow,
+    /// Prices at [`QuoteConfig::normal_percentile`].
+    #[default]
+    Normal,
+    /// Prices at [`QuoteConfig::fast_percentile`].
+    Fast,
+}
+
+/// Settings for [`Quoter`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuoteConfig {
+    /// Blocks of fee history to sample.
+    pub block_count: u64,
+    /// Reward percentile used by [`FeeStrategy::Slow`].
+    pub slow_percentile: f64,
+    /// Reward percentile used by [`FeeStrategy::Normal`].
+    pub normal_percentile: f64,
+    /// Reward percentile used by [`FeeStrategy::Fast`].
+    pub fast_percentile: f64,
+    /// Headroom over the next base fee, in basis points of it. The default
+    /// of 20000 survives five consecutive full blocks.
+    pub base_fee_multiplier_bps: u32,
+    /// Margin added to the gas estimate, in basis points of it.
+    pub gas_limit_margin_bps: u32,
+    /// Lowest priority fee to quote, in wei.
+    #[serde(with = "ibank_wallet_chains::quantity::decimal")]
+    pub min_priority_fee: U256,
+    /// Whether to quote an access list with `eth_createAccessList`.
+    pub access_list: bool,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            block_count: 20,
+            slow_percentile: 10.0,
+            normal_percentile: 50.0,
+            fast_percentile: 90.0,
+            base_fee_multiplier_bps: 20_000,
+            gas_limit_margin_bps: 2_000,
+            min_priority_fee: U256::zero(),
+            access_list: false,
+        }
+    }
+}
+
+impl QuoteConfig {
+    /// Returns the reward percentile for `strategy`.
+    pub fn percentile(&self, strategy: FeeStrategy) -> f64 {
+        match strategy {
+            FeeStrategy::Slow => self.slow_percentile,
+            FeeStrategy::Normal => self.normal_percentile,
+            FeeStrategy::Fast => self.fast_percentile,
+        }
+    }
+}
+
+/// Builds [`Quote`]s from the node's fee history and gas estimates.
+#[derive(Debug)]
+pub struct Quoter<R> {
+    /// Node to query.
+    pub rpc: R,
+    /// Quoting settings.
+    pub config: QuoteConfig,
+}
+
+impl<R: FeeRpc> Quoter<R> {
+    /// Creates a quoter with default settings.
+    pub fn new(rpc: R) -> Self {
+        Self {
+            rpc,
+            config: QuoteConfig::default(),
+        }
+    }
+
+    /// Replaces the quoting settings.
+    pub fn with_config(mut self, config: QuoteConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Quotes fees and gas for `intent` sent from `from`.
+    ///
+    /// The priority fee is the median, over non-empty blocks, of the
+    /// strategy's reward percentile, falling back to
+    /// `eth_maxPriorityFeePerGas` when the history has no rewards. The max
+    /// fee adds the priority fee to the next base fee scaled by
+    /// `base_fee_multiplier_bps`.
+    pub fn quote(
+        &self,
+        intent: &Intent,
+        from: Option<Address>,
+        strategy: FeeStrategy,
+    ) -> Result<Quote> {
+        let (max_priority_fee_per_gas, base_fee) = self.fees(strategy)?;
+        let max_fee_per_gas = scale(base_fee, self.config.base_fee_multiplier_bps)?
+            .checked_add(max_priority_fee_per_gas)
+            .ok_or_else(|| WalletError::RpcError("max fee per gas overflows".to_string()))?;
+
+        let (to, value, data) = intent.call()?;
+        let mut call = CallRequest {
+            from,
+            to,
+            value,
+            data,
+            access_list: AccessList::default(),
+        };
+        if self.config.access_list {
+            call.access_list = self.rpc.create_access_list(&call)?.0;
+        }
+        let estimate = self.rpc.estimate_gas(&call)?;
+        let margin = self
+            .config
+            .gas_limit_margin_bps
+            .checked_add(10_000)
+            .ok_or_else(|| {
+                WalletError::InvalidInput(format!(
+                    "gas limit margin of {} bps is too large",
+                    self.config.gas_limit_margin_bps
+                ))
+            })?;
+        let gas_limit = scale(estimate, margin)?;
+
+        Ok(Quote {
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit,
+            access_list: call.access_list,
+        })
+    }
+
+    /// Returns the priority fee for `strategy`.
+    pub fn priority_fee(&self, strategy: FeeStrategy) -> Result<U256> {
+        Ok(self.fees(strategy)?.0)
+    }
+
+    /// Returns the priority fee and the next block's base fee.
+    fn fees(&self, strategy: FeeStrategy) -> Result<(U256, U256)> {
+        let percentile = self.config.percentile(strategy);
+        if !(0.0..=100.0).contains(&percentile) {
+            return Err(WalletError::InvalidInput(format!(
+                "percentile {percentile} must be between 0 and 100"
+            )));
+        }
+        let history = self
+            .rpc
+            .fee_history(self.config.block_count, &[percentile])?;
+        let base_fee = *history.base_fee_per_gas.last().ok_or_else(|| {
+            WalletError::RpcError("eth_feeHistory: no base fee returned".to_string())
+        })?;
+        let mut rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .zip(&history.gas_used_ratio)
+            .filter(|(_, ratio)| **ratio > 0.0)
+            .filter_map(|(reward, _)| reward.first().map(|reward| reward.0))
+            .collect();
+        rewards.sort();
+        let fee = match rewards.get(rewards.len() / 2) {
+            Some(median) => *median,
+            None => self.rpc.max_priority_fee_per_gas()?,
+        };
+        Ok((fee.max(self.config.min_priority_fee), base_fee))
+    }
+}
+
+/// Multiplies `value` by `bps` basis points.
+fn scale(value: U256, bps: u32) -> Result<U256> {
+    value
+        .checked_mul(U256::from(bps))
+        .map(|scaled| scaled / U256::from(10_000u64))
+        .ok_or_else(|| WalletError::RpcError(format!("{value} scaled by {bps} bps overflows")))
+}
+
+mod quantities {
+    use ibank_wallet_chains::{quantity, U256};
+    use serde::ser::SerializeSeq;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &[U256], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&quantity::to_hex(value))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<U256>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|value| quantity::from_hex(value).map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::RecordedRpc;
+    use crate::IntentAction;
+    use ibank_wallet_core::CaipChainId;
+
+    const GWEI: u64 = 1_000_000_000;
+
+    /// Synthetic responses shaped like a mainnet node's, with round fees:
+    /// five blocks, one of them empty.
+    const SYNTHETIC_MAINNET: &str = r#"{
+        "eth_feeHistory": {
+            "oldestBlock": "0x1312d00",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00", "0x3b9aca00", "0x3b9aca00", "0x3b9aca00", "0x4a817c80"],
+            "gasUsedRatio": [0.5, 0.9, 0.0, 0.4, 0.6],
+            "reward": [["0x5f5e100"], ["0xbebc200"], ["0x0"], ["0x77359400"], ["0x3b9aca00"]]
+        },
+        "eth_maxPriorityFeePerGas": "0x3b9aca00",
+        "eth_estimateGas": "0xc350",
+        "eth_createAccessList": {
+            "accessList": [{
+                "address": "0x2222222222222222222222222222222222222222",
+                "storageKeys": ["0x0000000000000000000000000000000000000000000000000000000000000001"]
+            }],
+            "gasUsed": "0xc000"
+        }
+    }"#;
+
+    fn intent() -> Intent {
+        Intent {
+            chain_id: CaipChainId::eip155(1),
+            nonce: 0,
+            action: IntentAction::Call {
+                to: Address([0x22; 20]),
+                value: U256::from(1_000),
+                data: vec![0xaa],
+            },
+        }
+    }
+
+    #[test]
+    fn quotes_from_synthetic_fee_history() {
+        let quoter = Quoter::new(RecordedRpc::from_json(SYNTHETIC_MAINNET).expect("fixture"));
+        let from = Address([0x11; 20]);
+
+        let quote = quoter
+            .quote(&intent(), Some(from), FeeStrategy::Normal)
+            .expect("quote");
+        // Median of 0.1, 0.2, 1 and 2 gwei, skipping the empty block.
+        assert_eq!(quote.max_priority_fee_per_gas, U256::from(GWEI));
+        // Twice the next base fee of 1.25 gwei, plus the priority fee.
+        assert_eq!(quote.max_fee_per_gas, U256::from(7 * GWEI / 2));
+        // 50000 gas plus 20%.
+        assert_eq!(quote.gas_limit, U256::from(60_000));
+        assert!(quote.access_list.0.is_empty());
+
+        let calls = quoter.rpc.calls();
+        let (method, params) = calls.last().expect("estimate");
+        assert_eq!(method, "eth_estimateGas");
+        assert_eq!(params[0]["from"], json!(from));
+        assert_eq!(params[0]["value"], "0x3e8");
+        assert_eq!(params[0]["data"], "0xaa");
+        assert_eq!(calls[0].1, json!(["0x14", "latest", [50.0]]));
+    }
+
+    #[test]
+    fn strategies_and_margins_are_configurable() {
+        let quoter = Quoter::new(RecordedRpc::from_json(SYNTHETIC_MAINNET).expect("fixture"))
+            .with_config(QuoteConfig {
+                base_fee_multiplier_bps: 10_000,
+                gas_limit_margin_bps: 0,
+                min_priority_fee: U256::from(GWEI / 2),
+                access_list: true,
+                ..QuoteConfig::default()
+            });
+
+        let quote = quoter
+            .quote(&intent(), None, FeeStrategy::Fast)
+            .expect("quote");
+        assert_eq!(quote.max_fee_per_gas, U256::from(9 * GWEI / 4));
+        assert_eq!(quote.gas_limit, U256::from(50_000));
+        assert_eq!(quote.access_list.0.len(), 1);
+        assert_eq!(quote.access_list.0[0].storage_keys[0][31], 1);
+
+        let estimate = quoter
+            .rpc
+            .calls()
+            .into_iter()
+            .find(|(method, _)| method == "eth_estimateGas")
+            .expect("estimate");
+        assert_eq!(
+            estimate.1[0]["accessList"][0]["address"],
+            json!(Address([0x22; 20]))
+        );
+        assert!(quoter
+            .rpc
+            .calls()
+            .iter()
+            .any(|(_, params)| params.get(2) == Some(&json!([90.0]))));
+
+        let quoter = quoter.with_config(QuoteConfig {
+            gas_limit_margin_bps: u32::MAX,
+            ..QuoteConfig::default()
+        });
+        assert!(matches!(
+            quoter.quote(&intent(), None, FeeStrategy::Normal),
+            Err(WalletError::InvalidInput(reason)) if reason.contains("too large")
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_node_priority_fee_and_floor() {
+        let rpc = RecordedRpc::new()
+            .with_response(
+                "eth_feeHistory",
+                json!({"baseFeePerGas": ["0x1", "0x1"], "gasUsedRatio": [0.0]}),
+            )
+            .with_response("eth_maxPriorityFeePerGas", json!("0x64"));
+        let quoter = Quoter::new(rpc);
+        assert_eq!(
+            quoter.priority_fee(FeeStrategy::Normal).expect("fee"),
+            U256::from(100)
+        );
+
+        let quoter = quoter.with_config(QuoteConfig {
+            min_priority_fee: U256::from(GWEI),
+            normal_percentile: 101.0,
+            ..QuoteConfig::default()
+        });
+        assert!(quoter.priority_fee(FeeStrategy::Normal).is_err());
+        assert_eq!(
+            quoter.priority_fee(FeeStrategy::Fast).expect("fee"),
+            U256::from(GWEI)
+        );
+        assert!(matches!(
+            quoter.quote(&intent(), None, FeeStrategy::Fast),
+            Err(WalletError::RpcError(reason)) if reason == "eth_estimateGas: no recorded response"
+        ));
+    }
+}
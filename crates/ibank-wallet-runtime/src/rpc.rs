@@ -0,0 +1,452 @@
+//! JSON-RPC access to EVM nodes.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ibank_wallet_chains::{quantity, Address, U256};
+use ibank_wallet_core::{Result, WalletError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[cfg(test)]
+pub(crate) mod mock;
+
+/// Block to read account state at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockTag {
+    /// Latest mined block.
+    Latest,
+    /// Latest block plus the node's pending transactions.
+    Pending,
+}
+
+impl BlockTag {
+    /// Returns the JSON-RPC block tag.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Latest => "latest",
+            Self::Pending => "pending",
+        }
+    }
+}
+
+/// Raw JSON-RPC transport.
+pub trait JsonRpc {
+    /// Sends a request and returns its `result`.
+    fn request(&self, method: &str, params: Value) -> Result<Value>;
+
+    /// Sends a request and decodes its `result`.
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T>
+    where
+        Self: Sized,
+    {
+        serde_json::from_value(self.request(method, params)?).map_err(|err| rpc_error(method, err))
+    }
+}
+
+/// Subset of the Ethereum JSON-RPC API used to submit transactions.
+pub trait RpcClient: fmt::Debug + Send {
+    /// `eth_chainId`.
+    fn chain_id(&self) -> Result<u64>;
+    /// `eth_blockNumber`.
+    fn block_number(&self) -> Result<u64>;
+    /// `eth_getTransactionCount`.
+    fn transaction_count(&self, address: &Address, block: BlockTag) -> Result<u64>;
+    /// `eth_sendRawTransaction`; returns the transaction hash.
+    fn send_raw_transaction(&self, raw: &[u8]) -> Result<[u8; 32]>;
+    /// `eth_getTransactionReceipt`; `None` while the transaction is unmined.
+    fn transaction_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>>;
+}
+
+/// Receipt of a mined transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    /// Transaction hash.
+    #[serde(with = "hash")]
+    pub transaction_hash: [u8; 32],
+    /// Hash of the including block.
+    #[serde(with = "hash")]
+    pub block_hash: [u8; 32],
+    /// Number of the including block.
+    #[serde(with = "quantity_u64")]
+    pub block_number: u64,
+    /// True if execution succeeded.
+    #[serde(with = "status")]
+    pub status: bool,
+    /// Gas used by the transaction.
+    #[serde(with = "ibank_wallet_chains::quantity")]
+    pub gas_used: U256,
+    /// Price paid per unit of gas.
+    #[serde(with = "ibank_wallet_chains::quantity")]
+    pub effective_gas_price: U256,
+}
+
+/// How [`crate::Runtime::submit_intent`] waits for a receipt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubmitOptions {
+    /// Blocks, including the one with the transaction, to wait for.
+    /// Zero returns as soon as a receipt exists.
+    pub confirmations: u64,
+    /// Delay between receipt polls.
+    pub poll_interval: Duration,
+    /// How long to wait before giving up.
+    pub timeout: Duration,
+}
+
+impl Default for SubmitOptions {
+    fn default() -> Self {
+        Self {
+            confirmations: 1,
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl SubmitOptions {
+    /// Sets the confirmation depth.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Sets the delay between receipt polls.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets how long to wait for confirmations.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// [`RpcClient`] over HTTP(S) JSON-RPC.
+pub struct HttpRpcClient {
+    url: String,
+    agent: ureq::Agent,
+    next_id: AtomicU64,
+}
+
+impl HttpRpcClient {
+    /// Creates a client for the node at `url` with a 30 second timeout.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Sets the per-request timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        self
+    }
+
+    /// Returns the node URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn quantity(&self, method: &str, params: Value) -> Result<u64> {
+        let value: String = self.call(method, params)?;
+        to_u64(&value)
+    }
+}
+
+impl JsonRpc for HttpRpcClient {
+    fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let body = match self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&request.to_string())
+        {
+            Ok(response) => response.into_string(),
+            // Nodes report JSON-RPC errors with non-2xx statuses too.
+            Err(ureq::Error::Status(_, response)) => response.into_string(),
+            Err(err) => return Err(rpc_error(method, err)),
+        }
+        .map_err(|err| rpc_error(method, err))?;
+
+        let response: RpcResponse =
+            serde_json::from_str(&body).map_err(|err| rpc_error(method, err))?;
+        if let Some(error) = response.error {
+            return Err(WalletError::RpcError(format!(
+                "{method}: {} (code {})",
+                error.message, error.code
+            )));
+        }
+        Ok(response.result.unwrap_or(Value::Null))
+    }
+}
+
+impl fmt::Debug for HttpRpcClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpRpcClient")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RpcClient for HttpRpcClient {
+    fn chain_id(&self) -> Result<u64> {
+        self.quantity("eth_chainId", json!([]))
+    }
+
+    fn block_number(&self) -> Result<u64> {
+        self.quantity("eth_blockNumber", json!([]))
+    }
+
+    fn transaction_count(&self, address: &Address, block: BlockTag) -> Result<u64> {
+        self.quantity("eth_getTransactionCount", json!([address, block.as_str()]))
+    }
+
+    fn send_raw_transaction(&self, raw: &[u8]) -> Result<[u8; 32]> {
+        let tx_hash: String = self.call(
+            "eth_sendRawTransaction",
+            json!([format!("0x{}", hex::encode(raw))]),
+        )?;
+        parse_hash(&tx_hash)
+    }
+
+    fn transaction_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>> {
+        self.call(
+            "eth_getTransactionReceipt",
+            json!([format!("0x{}", hex::encode(tx_hash))]),
+        )
+    }
+}
+
+/// [`JsonRpc`] stand-in that replays recorded results by method, for
+/// exercising RPC-driven code offline.
+#[derive(Debug, Default)]
+pub struct RecordedRpc {
+    responses: BTreeMap<String, Value>,
+    calls: Mutex<Vec<(String, Value)>>,
+}
+
+impl RecordedRpc {
+    /// Creates a stand-in with no recorded responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a JSON object mapping method names to results.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let responses = serde_json::from_str(json)
+            .map_err(|err| WalletError::InvalidInput(format!("recorded responses: {err}")))?;
+        Ok(Self {
+            responses,
+            ..Self::default()
+        })
+    }
+
+    /// Records the result returned for `method`.
+    pub fn with_response(mut self, method: impl Into<String>, result: Value) -> Self {
+        self.responses.insert(method.into(), result);
+        self
+    }
+
+    /// Returns the requests made so far.
+    pub fn calls(&self) -> Vec<(String, Value)> {
+        self.calls.lock().expect("recorded calls").clone()
+    }
+}
+
+impl JsonRpc for RecordedRpc {
+    fn request(&self, method: &str, params: Value) -> Result<Value> {
+        self.calls
+            .lock()
+            .expect("recorded calls")
+            .push((method.to_string(), params));
+        self.responses
+            .get(method)
+            .cloned()
+            .ok_or_else(|| WalletError::RpcError(format!("{method}: no recorded response")))
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcErrorObject>,
+}
+
+#[derive(Deserialize)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+fn rpc_error(method: &str, err: impl fmt::Display) -> WalletError {
+    WalletError::RpcError(format!("{method}: {err}"))
+}
+
+pub(crate) fn to_u64(value: &str) -> Result<u64> {
+    let value = quantity::from_hex(value)?;
+    if value > U256::from(u64::MAX) {
+        return Err(WalletError::RpcError(format!(
+            "quantity {value} does not fit in 64 bits"
+        )));
+    }
+    Ok(value.as_u64())
+}
+
+pub(crate) fn parse_hash(value: &str) -> Result<[u8; 32]> {
+    value
+        .strip_prefix("0x")
+        .and_then(|digits| hex::decode(digits).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| WalletError::RpcError(format!("invalid 32-byte hash {value}")))
+}
+
+pub(crate) mod hash {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        super::parse_hash(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+    pub mod option {
+        use serde::{de, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<[u8; 32]>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<[u8; 32]>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| crate::rpc::parse_hash(&value).map_err(de::Error::custom))
+                .transpose()
+        }
+    }
+}
+
+mod quantity_u64 {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{value:#x}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        super::to_u64(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+mod status {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(if *value { "0x1" } else { "0x0" })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "0x1" => Ok(true),
+            "0x0" => Ok(false),
+            other => Err(de::Error::custom(format!("invalid receipt status {other}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockRpcServer;
+    use super::*;
+
+    #[test]
+    fn http_client_speaks_json_rpc() {
+        let server = MockRpcServer::start(5);
+        let account = Address([0x11; 20]);
+        server.node().nonces.insert(account, 7);
+        let client = HttpRpcClient::new(server.url());
+
+        assert_eq!(client.chain_id().expect("chain id"), 5);
+        assert_eq!(
+            client
+                .transaction_count(&account, BlockTag::Latest)
+                .expect("nonce"),
+            7
+        );
+        assert_eq!(client.block_number().expect("head"), 1);
+        assert!(client
+            .transaction_receipt(&[0xab; 32])
+            .expect("receipt")
+            .is_none());
+        assert_eq!(
+            server.node().calls,
+            [
+                "eth_chainId",
+                "eth_getTransactionCount",
+                "eth_blockNumber",
+                "eth_getTransactionReceipt",
+            ]
+        );
+    }
+
+    #[test]
+    fn http_client_surfaces_node_errors() {
+        let server = MockRpcServer::start(1);
+        server.node().reject = Some("nonce too low".to_string());
+        let client = HttpRpcClient::new(server.url());
+
+        let err = client.send_raw_transaction(&[0x02, 0xc0]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rpc error: eth_sendRawTransaction: nonce too low (code -32000)"
+        );
+        assert!(matches!(
+            HttpRpcClient::new("http://127.0.0.1:1").chain_id(),
+            Err(WalletError::RpcError(reason)) if reason.starts_with("eth_chainId: ")
+        ));
+    }
+
+    #[test]
+    fn receipts_round_trip_as_json_rpc() {
+        let receipt: TransactionReceipt = serde_json::from_value(json!({
+            "transactionHash": format!("0x{}", "11".repeat(32)),
+            "blockHash": format!("0x{}", "22".repeat(32)),
+            "blockNumber": "0x10",
+            "status": "0x0",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "logs": [],
+        }))
+        .expect("receipt");
+        assert_eq!(receipt.block_number, 16);
+        assert!(!receipt.status);
+        assert_eq!(receipt.gas_used, U256::from(21_000));
+        let json = serde_json::to_value(&receipt).expect("json");
+        assert_eq!(json["blockNumber"], "0x10");
+        assert_eq!(json["status"], "0x0");
+    }
+}
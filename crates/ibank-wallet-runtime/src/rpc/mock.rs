@@ -0,0 +1,205 @@
+//! In-process JSON-RPC node for tests.
+//!
+//! Every `eth_blockNumber` call mines an empty block, and sent transactions
+//! are mined into the next block, so polling for confirmations makes
+//! progress deterministically.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use ibank_wallet_chains::{recover_sender, transaction_hash, Address, EvmSignedTypedTx};
+use serde_json::{json, Value};
+
+/// State of the mock node.
+#[derive(Debug, Default)]
+pub(crate) struct MockNode {
+    pub chain_id: u64,
+    pub head: u64,
+    /// Transaction count per account; sent transactions increment it.
+    pub nonces: BTreeMap<Address, u64>,
+    /// Unmined transactions per account, counted only at the `pending` tag.
+    pub pending: BTreeMap<Address, u64>,
+    /// Raw transactions accepted, in order.
+    pub sent: Vec<Vec<u8>>,
+    /// Block each accepted transaction was mined in.
+    pub mined: HashMap<[u8; 32], u64>,
+    /// When set, `eth_sendRawTransaction` fails with this message.
+    pub reject: Option<String>,
+    /// When set, accepted transactions stay in the mempool and are never
+    /// mined.
+    pub stall: bool,
+    /// When set, receipts report a failed execution.
+    pub revert: bool,
+    /// Methods called, in order.
+    pub calls: Vec<String>,
+}
+
+impl MockNode {
+    fn handle(&mut self, method: &str, params: &[Value]) -> Result<Value, String> {
+        self.calls.push(method.to_string());
+        match method {
+            "eth_chainId" => Ok(json!(format!("{:#x}", self.chain_id))),
+            "eth_blockNumber" => {
+                self.head += 1;
+                Ok(json!(format!("{:#x}", self.head)))
+            }
+            "eth_getTransactionCount" => {
+                let account: Address =
+                    serde_json::from_value(params[0].clone()).map_err(|err| err.to_string())?;
+                let mut nonce = self.nonces.get(&account).copied().unwrap_or_default();
+                if params.get(1).and_then(Value::as_str) == Some("pending") {
+                    nonce += self.pending.get(&account).copied().unwrap_or_default();
+                }
+                Ok(json!(format!("{nonce:#x}")))
+            }
+            "eth_sendRawTransaction" => {
+                if let Some(reason) = &self.reject {
+                    return Err(reason.clone());
+                }
+                let raw = decode_hex(&params[0])?;
+                let tx_hash = transaction_hash(&raw).map_err(|err| err.to_string())?;
+                let sender = recover_sender(&raw).map_err(|err| err.to_string())?;
+                let nonce = EvmSignedTypedTx::decode(&raw)
+                    .map_err(|err| err.to_string())?
+                    .tx
+                    .nonce();
+                let mined = self.nonces.entry(sender).or_default();
+                if nonce < *mined {
+                    return Err("nonce too low".to_string());
+                }
+                self.sent.push(raw);
+                if self.stall {
+                    return Ok(json!(format!("0x{}", hex::encode(tx_hash))));
+                }
+                *mined += 1;
+                self.head += 1;
+                self.mined.insert(tx_hash, self.head);
+                Ok(json!(format!("0x{}", hex::encode(tx_hash))))
+            }
+            "eth_getTransactionReceipt" => {
+                let tx_hash: [u8; 32] = decode_hex(&params[0])?
+                    .try_into()
+                    .map_err(|_| "invalid hash".to_string())?;
+                Ok(match self.mined.get(&tx_hash) {
+                    Some(block) => json!({
+                        "transactionHash": format!("0x{}", hex::encode(tx_hash)),
+                        "blockHash": format!("0x{}", hex::encode([*block as u8; 32])),
+                        "blockNumber": format!("{block:#x}"),
+                        "status": if self.revert { "0x0" } else { "0x1" },
+                        "gasUsed": "0x5208",
+                        "effectiveGasPrice": "0x2",
+                    }),
+                    None => Value::Null,
+                })
+            }
+            _ => Err(format!("method {method} not found")),
+        }
+    }
+}
+
+fn decode_hex(value: &Value) -> Result<Vec<u8>, String> {
+    value
+        .as_str()
+        .and_then(|value| value.strip_prefix("0x"))
+        .and_then(|digits| hex::decode(digits).ok())
+        .ok_or_else(|| format!("invalid hex {value}"))
+}
+
+/// HTTP server answering JSON-RPC from a [`MockNode`].
+pub(crate) struct MockRpcServer {
+    addr: SocketAddr,
+    node: Arc<Mutex<MockNode>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl MockRpcServer {
+    /// Starts a node for `chain_id` on an ephemeral port.
+    pub fn start(chain_id: u64) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let node = Arc::new(Mutex::new(MockNode {
+            chain_id,
+            ..MockNode::default()
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (shared, stopped) = (node.clone(), stop.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    serve(stream, &shared);
+                }
+            }
+        });
+        Self { addr, node, stop }
+    }
+
+    /// Returns the node URL.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Locks the node state.
+    pub fn node(&self) -> MutexGuard<'_, MockNode> {
+        self.node.lock().expect("mock node")
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+fn serve(stream: TcpStream, node: &Mutex<MockNode>) {
+    let mut reader = BufReader::new(&stream);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    if reader.read_exact(&mut body).is_err() {
+        return;
+    }
+
+    let request: Value = serde_json::from_slice(&body).unwrap_or_default();
+    let params = request["params"].as_array().cloned().unwrap_or_default();
+    let outcome = node
+        .lock()
+        .expect("mock node")
+        .handle(request["method"].as_str().unwrap_or_default(), &params);
+    let response = match outcome {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+        Err(message) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": {"code": -32000, "message": message},
+        }),
+    }
+    .to_string();
+    let _ = write!(
+        &stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+        response.len()
+    );
+}